@@ -1,17 +1,31 @@
-use std::collections::HashSet;
-use std::fs;
+use serde::Serialize;
 use std::path::PathBuf;
+use std::time::Duration;
+use crate::storage::ChangedKeys;
+use crate::events::{KeepProblems, Progress};
+use crate::filesystem::for_each_directory;
 use crate::utils::{Result, CleanerError, ErrorCollector};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct AugmentCleaningResult {
     pub processes_terminated: Vec<String>,
     pub directories_found: Vec<PathBuf>,
     pub databases_cleaned: Vec<String>,
     pub storage_updated: Vec<String>,
+    pub history_entries_cleared: usize,
+    /// Storage keys given new values, per storage directory
+    pub keys_changed: ChangedKeys,
+    /// State databases rebuilt after rows were deleted, with their sizes
+    pub compacted: Vec<crate::database::Compaction>,
     pub errors: ErrorCollector,
 }
 
+impl Default for AugmentCleaningResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AugmentCleaningResult {
     pub fn new() -> Self {
         Self {
@@ -19,209 +33,129 @@ impl AugmentCleaningResult {
             directories_found: Vec::new(),
             databases_cleaned: Vec::new(),
             storage_updated: Vec::new(),
+            history_entries_cleared: 0,
+            keys_changed: ChangedKeys::new(),
+            compacted: Vec::new(),
             errors: ErrorCollector::new(),
         }
     }
 }
 
 /// Find VSCode/Augment storage directories across different platforms and installations
-pub fn find_augment_storage_directories() -> Vec<PathBuf> {
-    let mut seen = HashSet::new();
-    let mut base_dirs = vec![dirs::config_dir(), dirs::home_dir(), dirs::data_dir()];
-
-    if let Some(home) = dirs::home_dir() {
-        base_dirs.push(Some(home.join(".vscode")));
-
-        #[cfg(target_os = "linux")]
-        {
-            base_dirs.push(Some(home.join("snap/code/common/.config")));
-            base_dirs.push(Some(home.join(".var/app/com.visualstudio.code/config")));
-            base_dirs.push(Some(home.join(".var/app/com.visualstudio.code-insiders/config")));
-        }
-
-        #[cfg(target_os = "macos")]
-        {
-            if let Some(app_support) = dirs::config_dir() {
-                base_dirs.push(Some(app_support.join("Code - Insiders")));
-                base_dirs.push(Some(app_support.join("Cursor")));
-                base_dirs.push(Some(app_support.join("VSCodium")));
-            }
-        }
-    }
-
-    let global_patterns = [
-        &["User", "globalStorage"] as &[&str],
-        &["data", "User", "globalStorage"],
-        &[crate::utils::MACHINE_ID],
-        &["data", crate::utils::MACHINE_ID],
-    ];
-
-    let workspace_patterns = [
-        &["User", "workspaceStorage"] as &[&str],
-        &["data", "User", "workspaceStorage"],
-    ];
-
-    base_dirs
-        .into_iter()
-        .filter_map(|dir| dir)
-        .flat_map(|base| scan_storage(&base, &global_patterns, &workspace_patterns))
-        .filter(|path| path.exists() && seen.insert(path.clone()))
-        .collect()
+pub fn find_augment_storage_directories(args: &crate::cli::CliArgs) -> Result<Vec<PathBuf>> {
+    crate::filesystem::find_vscode_storage_directories(args)
 }
 
-/// Scan a directory for VSCode/Augment storage using the provided patterns
-fn scan_storage(
-    base_dir: &PathBuf,
-    global_patterns: &[&[&str]],
-    workspace_patterns: &[&[&str]],
-) -> Vec<PathBuf> {
-    let Ok(entries) = fs::read_dir(base_dir) else { return Vec::new(); };
-
-    entries
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
-        .flat_map(|entry| {
-            let path = entry.path();
-
-            let global_paths: Vec<PathBuf> = global_patterns.iter()
-                .map(|pattern| pattern.iter().fold(path.clone(), |p, seg| p.join(seg)))
-                .collect();
-
-            let workspace_paths: Vec<PathBuf> = workspace_patterns.iter()
-                .flat_map(|pattern| {
-                    let workspace_base = pattern.iter().fold(path.clone(), |p, seg| p.join(seg));
-                    if !workspace_base.exists() { return Vec::new(); }
-
-                    let Ok(entries) = fs::read_dir(&workspace_base) else { return Vec::new(); };
-                    entries
-                        .filter_map(|entry| entry.ok())
-                        .filter(|entry| entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
-                        .map(|entry| entry.path())
-                        .collect()
-                })
-                .collect();
-
-            global_paths.into_iter().chain(workspace_paths)
-        })
-        .collect()
-}
+/// Process names of the editors that might be using the Augment extension
+pub const AUGMENT_PROCESSES: [&str; 13] = [
+    "code", "Code", "code.exe", "Code.exe",
+    "code-insiders", "code-insiders.exe",
+    "vscodium", "VSCodium", "vscodium.exe",
+    "cursor", "Cursor", "cursor.exe", "Cursor.exe"
+];
 
-/// Terminate VSCode processes that might be using Augment extension
-pub fn terminate_augment_processes() -> Result<Vec<String>> {
-    let process_names = vec![
-        "code", "Code", "code.exe", "Code.exe",
-        "code-insiders", "code-insiders.exe",
-        "vscodium", "VSCodium", "vscodium.exe",
-        "cursor", "Cursor", "cursor.exe", "Cursor.exe"
-    ];
-    
-    let mut terminated = Vec::new();
-    
-    for process_name in process_names {
-        #[cfg(target_os = "windows")]
-        {
-            let output = std::process::Command::new("tasklist")
-                .args(&["/FI", &format!("IMAGENAME eq {}", process_name)])
-                .output();
-                
-            if let Ok(output) = output {
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                if output_str.contains(process_name) {
-                    let result = std::process::Command::new("taskkill")
-                        .args(&["/F", "/IM", process_name])
-                        .output();
-                    
-                    if result.is_ok() {
-                        terminated.push(process_name.to_string());
-                    }
-                }
-            }
-        }
-        
-        #[cfg(any(target_os = "macos", target_os = "linux"))]
-        {
-            let output = std::process::Command::new("pgrep")
-                .arg(process_name)
-                .output();
-                
-            if let Ok(output) = output {
-                if !output.stdout.is_empty() {
-                    let result = std::process::Command::new("pkill")
-                        .args(&["-f", process_name])
-                        .output();
-                    
-                    if result.is_ok() {
-                        terminated.push(process_name.to_string());
-                    }
-                }
-            }
-        }
-    }
-    
-    if !terminated.is_empty() {
-        std::thread::sleep(std::time::Duration::from_secs(2));
-    }
-    
-    Ok(terminated)
+/// Terminate VSCode processes that might be using Augment extension, giving each `grace` to close
+pub fn terminate_augment_processes(grace: Duration) -> Result<Vec<String>> {
+    let process_names: Vec<String> = AUGMENT_PROCESSES.iter().map(|name| name.to_string()).collect();
+
+    Ok(crate::process::terminate_named_processes(&process_names, grace))
 }
 
-/// Clean Augment extension data from VSCode databases
-pub fn clean_augment_databases(directories: &[PathBuf]) -> Result<Vec<String>> {
+/// Clean Augment extension data from VSCode databases, also returning how many history entries were cleared
+pub fn clean_augment_databases(directories: &[PathBuf], args: &crate::cli::CliArgs, progress: &(impl Progress + Sync)) -> Result<(Vec<String>, usize)> {
     let mut cleaned = Vec::new();
-
-    for directory in directories {
-        // Create a dummy channel since we're not using the UI here
-        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
-
-        match crate::database::clean_vscode_databases(directory, &tx) {
-            Ok(_) => cleaned.push(directory.to_string_lossy().to_string()),
-            Err(_) => continue, // Skip failed directories
+    let mut history_cleared = 0;
+
+    let outcomes = for_each_directory(directories, &args.pause, |directory| {
+        let outcome = crate::database::clean_vscode_databases(directory, args, progress).ok();
+        progress.advance(1);
+        outcome
+    });
+    for (directory, outcome) in directories.iter().zip(outcomes) {
+        // Skip failed directories
+        if let Some(cleared) = outcome {
+            cleaned.push(directory.to_string_lossy().to_string());
+            history_cleared += cleared;
         }
     }
 
-    Ok(cleaned)
+    Ok((cleaned, history_cleared))
 }
 
-/// Update VSCode storage to remove Augment extension traces
-pub fn update_augment_storage(directories: &[PathBuf]) -> Result<Vec<String>> {
+/// Update VSCode storage to remove Augment extension traces, returning the updated directories and their changed keys
+pub fn update_augment_storage(directories: &[PathBuf], args: &crate::cli::CliArgs, progress: &(impl Progress + Sync)) -> Result<(Vec<String>, ChangedKeys)> {
     let mut updated = Vec::new();
-
-    for directory in directories {
-        // Create a dummy channel since we're not using the UI here
-        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
-
-        match crate::storage::update_vscode_storage(directory, &tx) {
-            Ok(_) => updated.push(directory.to_string_lossy().to_string()),
-            Err(_) => continue, // Skip failed directories
+    let mut keys_changed = ChangedKeys::new();
+
+    let outcomes = for_each_directory(directories, &args.pause, |directory| {
+        let outcome = crate::storage::update_vscode_storage(directory, args, progress).ok();
+        progress.advance(1);
+        outcome
+    });
+    for (directory, outcome) in directories.iter().zip(outcomes) {
+        // Skip failed directories
+        if let Some(changed) = outcome {
+            let directory = directory.to_string_lossy().to_string();
+            if !changed.is_empty() {
+                keys_changed.insert(directory.clone(), changed);
+            }
+            updated.push(directory);
         }
     }
 
-    Ok(updated)
+    Ok((updated, keys_changed))
 }
 
 /// Perform complete Augment extension cleaning
-pub async fn clean_augment_extension(args: &crate::cli::CliArgs) -> Result<AugmentCleaningResult> {
+pub async fn clean_augment_extension(args: &crate::cli::CliArgs, progress: &(impl Progress + Sync)) -> Result<AugmentCleaningResult> {
     let mut result = AugmentCleaningResult::new();
-    
-    // Step 1: Terminate processes (only if not disabled by no_terminate)
-    if !args.no_terminate {
-        match terminate_augment_processes() {
-            Ok(terminated) => result.processes_terminated = terminated,
+
+    // Step 1: Find storage directories
+    let found = find_augment_storage_directories(args)?;
+    (result.directories_found, _) = crate::filesystem::choose_directories(found, args);
+
+    // Step 2: Back up the databases this run is about to clean, before the kill when asked to
+    let snapshot = crate::backup::snapshot_before_terminate(args);
+    let backup = |result: &mut AugmentCleaningResult| {
+        if args.dry_run {
+            return;
+        }
+        let (_, warnings) = crate::backup::backup_directories(&result.directories_found, !args.no_signout, &args.db_files, args.backup_jobs, &args.backups);
+        result.errors.extend(warnings);
+    };
+    if snapshot {
+        backup(&mut result);
+    }
+
+    // Step 3: Terminate processes (only if not disabled by no_terminate, a dry run leaves them running)
+    if !args.no_terminate && !args.dry_run {
+        match terminate_augment_processes(crate::process::grace_period(args)) {
+            Ok(terminated) => {
+                crate::process::report_respawns(&terminated, args, &mut result.errors);
+                result.processes_terminated = terminated;
+            }
             Err(e) => result.errors.add_error(CleanerError::Process {
                 operation: "terminate".to_string(),
                 process: "augment_processes".to_string(),
                 source: e.to_string(),
             }),
         }
+        progress.advance(1);
+    }
+
+    // otherwise back up once the editor has let go of the files
+    if !snapshot {
+        backup(&mut result);
     }
     
-    // Step 2: Find storage directories
-    result.directories_found = find_augment_storage_directories();
-    
-    // Step 3: Clean databases (only if not disabled by no_signout)
+    // Step 4: Clean databases (only if not disabled by no_signout)
+    let progress = KeepProblems::new(progress);
     if !args.no_signout {
-        match clean_augment_databases(&result.directories_found) {
-            Ok(cleaned) => result.databases_cleaned = cleaned,
+        match clean_augment_databases(&result.directories_found, args, &progress) {
+            Ok((cleaned, history_cleared)) => {
+                result.databases_cleaned = cleaned;
+                result.history_entries_cleared = history_cleared;
+            }
             Err(e) => result.errors.add_error(CleanerError::Database {
                 operation: "clean".to_string(),
                 path: "augment_databases".to_string(),
@@ -230,15 +164,20 @@ pub async fn clean_augment_extension(args: &crate::cli::CliArgs) -> Result<Augme
         }
     }
     
-    // Step 4: Update storage
-    match update_augment_storage(&result.directories_found) {
-        Ok(updated) => result.storage_updated = updated,
+    // Step 5: Update storage
+    match update_augment_storage(&result.directories_found, args, &progress) {
+        Ok((updated, keys_changed)) => {
+            result.storage_updated = updated;
+            result.keys_changed = keys_changed;
+        }
         Err(e) => result.errors.add_error(CleanerError::Json {
             operation: "update_storage".to_string(),
             path: "augment_storage".to_string(),
             source: e.to_string(),
         }),
     }
+    result.errors.extend(progress.take());
+    result.compacted = progress.take_compactions();
     
     Ok(result)
 }
@@ -0,0 +1,419 @@
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::cli::CliArgs;
+use crate::database::{database_files, is_database_name, is_sqlite_file, sidecar_files};
+use crate::filesystem::find_vscode_storage_directories;
+use crate::storage::{set_immutable, FilePermissions, UnlockLedger};
+use crate::utils::{ErrorCollector, Result};
+
+pub const BACKUP_DIR_NAME: &str = "backups";
+pub const BACKUP_INDEX: &str = "index.json";
+
+/// One backup pass over a directory, as recorded in its backup index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupRecord {
+    pub timestamp: u64,
+    pub files: Vec<BackedUpFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackedUpFile {
+    pub original: PathBuf,
+    pub backup: PathBuf,
+}
+
+/// The files one run has backed up, shared by its modules so each file is copied once, before its first change
+#[derive(Debug, Clone, Default)]
+pub struct BackupLedger(Arc<Mutex<HashSet<PathBuf>>>);
+
+impl BackupLedger {
+    /// Whether `file` has no backup from this run yet, counting it as backed up from now on
+    pub fn first_backup(&self, file: &Path) -> bool {
+        // the same file is reached through a symlink or a relative --path as often as through its real path
+        let file = fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+        self.0.lock().map(|mut files| files.insert(file)).unwrap_or(true)
+    }
+}
+
+/// The files a clean of `directory` will rewrite, if present, with `db_files` naming databases beyond the usual ones
+pub fn files_to_modify(directory: &Path, clean_databases: bool, db_files: &[String]) -> Vec<PathBuf> {
+    if directory.is_file() {
+        return vec![directory.to_path_buf()];
+    }
+
+    let mut files = vec![directory.join("storage.json")];
+    if clean_databases {
+        files.extend(database_files(directory, db_files).iter().map(|name| directory.join(name)));
+    }
+    files.retain(|file| file.exists());
+    files
+}
+
+/// The backups folder for a storage directory, or for the directory holding a machine id file
+pub fn backup_dir_for(target: &Path) -> PathBuf {
+    let base = if target.is_file() { target.parent().unwrap_or(target) } else { target };
+    base.join(BACKUP_DIR_NAME)
+}
+
+/// Copy exactly `files` into the backups folder of `directory` and record them in its index
+pub fn backup_files(directory: &Path, files: &[PathBuf], warnings: &mut ErrorCollector) -> Result<Vec<BackedUpFile>> {
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let backup_dir = backup_dir_for(directory);
+    if !backup_dir.exists() {
+        fs::create_dir_all(&backup_dir)?;
+    }
+
+    // nanoseconds, so two backups of a file within the same second get their own names
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+
+    // a read-only backups folder left over from a locked run would otherwise abort the copy;
+    // it is the cleaner's own folder, so it isn't reported among the user's unlocked files
+    let backup_dir_permissions = match FilePermissions::backup_and_make_writable(&backup_dir, &UnlockLedger::default()) {
+        Ok(perms) => Some(perms),
+        Err(e) => {
+            warnings.add_warning(format!("could not make '{}' writable: {}", backup_dir.display(), e));
+            None
+        }
+    };
+
+    let result = copy_and_index(&backup_dir, files, timestamp, warnings);
+
+    if let Some(permissions) = backup_dir_permissions {
+        if let Err(e) = permissions.restore(&backup_dir) {
+            warnings.add_warning(format!("could not restore permissions for '{}': {}", backup_dir.display(), e));
+        }
+    }
+
+    result
+}
+
+fn copy_and_index(backup_dir: &Path, files: &[PathBuf], timestamp: u64, warnings: &mut ErrorCollector) -> Result<Vec<BackedUpFile>> {
+    let mut backed_up = Vec::new();
+
+    for file in files {
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let mut stamp = timestamp;
+        while backup_dir.join(format!("{}.backup_{}", name, stamp)).exists() || file.with_file_name(format!("{}.backup_{}", name, stamp)).exists() {
+            stamp += 1;
+        }
+        let backup_name = format!("{}.backup_{}", name, stamp);
+        let mut backup = backup_dir.join(&backup_name);
+
+        // fall back to backing up next to the file rather than skipping the clean entirely
+        if let Err(e) = fs::copy(file, &backup) {
+            backup = file.with_file_name(&backup_name);
+            warnings.add_warning(format!("could not back up into '{}' ({}), using '{}' instead", backup_dir.display(), e, backup.display()));
+            fs::copy(file, &backup)?;
+        }
+
+        backed_up.push(BackedUpFile { original: file.clone(), backup });
+    }
+
+    let index_path = backup_dir.join(BACKUP_INDEX);
+    let mut index = read_backup_index(backup_dir);
+    index.push(BackupRecord { timestamp, files: backed_up.clone() });
+    if let Err(e) = fs::write(&index_path, serde_json::to_string_pretty(&index)?) {
+        warnings.add_warning(format!("could not update backup index '{}': {}", index_path.display(), e));
+    }
+
+    Ok(backed_up)
+}
+
+/// Whether databases are copied before processes are terminated rather than after, see --backup-before-terminate
+pub fn snapshot_before_terminate(args: &crate::cli::CliArgs) -> bool {
+    args.backup_before_terminate && !args.no_terminate && !args.no_signout
+}
+
+/// Back up every directory before cleaning, copying at most `jobs` directories at once so large databases don't saturate the disk.
+///
+/// Files `ledger` already holds a backup of are left out, a second copy would catch what an earlier module changed.
+pub fn backup_directories(directories: &[PathBuf], clean_databases: bool, db_files: &[String], jobs: usize, ledger: &BackupLedger) -> (Vec<BackedUpFile>, ErrorCollector) {
+    let next = AtomicUsize::new(0);
+    let backed_up = Mutex::new(Vec::new());
+    let warnings = Mutex::new(ErrorCollector::new());
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, directories.len().max(1)) {
+            scope.spawn(|| {
+                let mut local = ErrorCollector::new();
+                while let Some(directory) = directories.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    // storage.json is copied by update_storage_json right before it is rewritten
+                    let mut files = files_to_modify(directory, clean_databases, db_files);
+                    files.retain(|file| file.file_name().is_none_or(|name| name != "storage.json") && ledger.first_backup(file));
+                    match backup_files(directory, &files, &mut local) {
+                        Ok(files) => backed_up.lock().unwrap().extend(files),
+                        Err(e) => local.add_warning(format!("could not back up '{}': {}", directory.display(), e)),
+                    }
+                }
+                warnings.lock().unwrap().extend(local);
+            });
+        }
+    });
+
+    (backed_up.into_inner().unwrap(), warnings.into_inner().unwrap())
+}
+
+/// Read the backup index of a backups folder, oldest record first
+pub fn read_backup_index(backup_dir: &Path) -> Vec<BackupRecord> {
+    fs::read_to_string(backup_dir.join(BACKUP_INDEX))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Backups of `original` that can safely be restored, newest first; partial or corrupt copies are skipped with a warning
+pub fn restore_candidates(original: &Path, db_files: &[String], warnings: &mut ErrorCollector) -> Vec<PathBuf> {
+    let Some(name) = original.file_name().map(|n| n.to_string_lossy().to_string()) else { return Vec::new(); };
+    let prefix = format!("{}.backup_", name);
+
+    // backups land in the backups folder, or next to the file when that folder wasn't writable
+    let mut found: Vec<(u64, PathBuf)> = Vec::new();
+    let search_dirs = [backup_dir_for(original), original.parent().map(Path::to_path_buf).unwrap_or_default()];
+    for dir in search_dirs {
+        let Ok(entries) = fs::read_dir(&dir) else { continue; };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let Some(timestamp) = file_name.strip_prefix(&prefix).and_then(|ts| ts.parse::<u64>().ok()) else { continue; };
+            found.push((timestamp, entry.path()));
+        }
+    }
+
+    found.sort_by_key(|(timestamp, _)| std::cmp::Reverse(*timestamp));
+
+    found.into_iter()
+        .filter_map(|(_, path)| match validate_backup(&name, &path, db_files) {
+            Ok(_) => Some(path),
+            Err(e) => {
+                warnings.add_warning(format!("skipping corrupt backup '{}': {}", path.display(), e));
+                None
+            }
+        })
+        .collect()
+}
+
+/// Check that a backup is complete enough to restore: a readable database for state files, valid json for storage, an id otherwise.
+///
+/// What a backup must be comes from the name of the file it was taken of, a truncated copy can't be trusted to say.
+pub fn validate_backup(original_name: &str, backup: &Path, db_files: &[String]) -> Result<()> {
+    // an interrupted copy can leave a zero-length file, which sqlite would happily treat as an empty database
+    if fs::metadata(backup)?.len() == 0 {
+        return Err("backup is empty".into());
+    }
+
+    if is_database_name(original_name, db_files) {
+        if !is_sqlite_file(backup) {
+            return Err("backup is not a complete sqlite database".into());
+        }
+        let conn = Connection::open_with_flags(backup, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let status: String = conn.query_row("PRAGMA quick_check;", [], |row| row.get(0))?;
+        if status != "ok" {
+            return Err(format!("integrity check failed: {}", status).into());
+        }
+    } else if original_name.ends_with(".json") {
+        let content = fs::read_to_string(backup)?;
+        serde_json::from_str::<serde_json::Value>(&content)?;
+    } else if fs::read_to_string(backup)?.trim().is_empty() {
+        return Err("backup holds no id".into());
+    }
+
+    Ok(())
+}
+
+/// Restore the newest valid backup over each file a clean rewrites, in every discovered storage directory
+pub fn run_restore(args: &CliArgs) -> Result<()> {
+    let mut warnings = ErrorCollector::new();
+    let mut restored = 0;
+    let mut skipped = 0;
+
+    for directory in find_vscode_storage_directories(args)? {
+        let originals = if directory.is_file() {
+            vec![directory.clone()]
+        } else {
+            std::iter::once("storage.json".to_string())
+                .chain(database_files(&directory, &args.db_files))
+                .map(|name| directory.join(name))
+                .collect()
+        };
+
+        for original in originals {
+            let Some(backup) = restore_candidates(&original, &args.db_files, &mut warnings).into_iter().next() else {
+                if original.exists() {
+                    println!("skipped {}: no valid backup", original.display());
+                    skipped += 1;
+                }
+                continue;
+            };
+
+            match restore_file(&original, &backup, &args.unlocked) {
+                Ok(_) => {
+                    println!("restored {} from {}", original.display(), backup.display());
+                    restored += 1;
+                }
+                Err(e) => {
+                    println!("skipped {}: {}", original.display(), e);
+                    skipped += 1;
+                }
+            }
+        }
+    }
+
+    for warning in &warnings.warnings {
+        println!("warning: {}", warning);
+    }
+    println!("{} files restored, {} skipped", restored, skipped);
+    Ok(())
+}
+
+// a locked file has its read-only and immutable attributes lifted first, then put back once the copy is in place
+fn restore_file(original: &Path, backup: &Path, unlocked: &UnlockLedger) -> Result<()> {
+    // sqlite would replay the current wal or journal over the restored copy, so they go with the file they belong to
+    if is_sqlite_file(backup) {
+        for sidecar in sidecar_files(original) {
+            fs::remove_file(&sidecar).map_err(|e| format!("could not remove {}: {}", sidecar.display(), e))?;
+        }
+    }
+
+    if !original.exists() {
+        fs::copy(backup, original)?;
+        return Ok(());
+    }
+
+    if fs::metadata(original)?.permissions().readonly() {
+        let _ = set_immutable(original, false);
+    }
+    let permissions = FilePermissions::backup_and_make_writable(original, unlocked)
+        .map_err(|e| format!("could not clear the read-only attribute: {}", e))?;
+    fs::copy(backup, original)?;
+    permissions.restore(original)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cleaner-backup-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn backups_in_the_same_second_keep_their_own_copies() {
+        let dir = scratch_dir("same-second");
+        let file = dir.join("storage.json");
+        let mut warnings = ErrorCollector::new();
+
+        fs::write(&file, "{\"id\":1}").unwrap();
+        let first = backup_files(&dir, std::slice::from_ref(&file), &mut warnings).unwrap();
+        fs::write(&file, "{\"id\":2}").unwrap();
+        let second = backup_files(&dir, std::slice::from_ref(&file), &mut warnings).unwrap();
+
+        assert_ne!(first[0].backup, second[0].backup);
+        assert_eq!(fs::read_to_string(&first[0].backup).unwrap(), "{\"id\":1}");
+        assert_eq!(fs::read_to_string(&second[0].backup).unwrap(), "{\"id\":2}");
+        assert_eq!(restore_candidates(&file, &[], &mut warnings), vec![second[0].backup.clone(), first[0].backup.clone()]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_truncated_database_backup_is_rejected() {
+        let dir = scratch_dir("truncated");
+        let db = dir.join("state.vscdb");
+        Connection::open(&db).unwrap().execute("CREATE TABLE ItemTable (key TEXT, value BLOB);", []).unwrap();
+        let whole = dir.join("state.vscdb.backup_2");
+        fs::copy(&db, &whole).unwrap();
+        // shorter than the sqlite header, which used to make it pass as a plain id file
+        let truncated = dir.join("state.vscdb.backup_1");
+        fs::write(&truncated, "SQLite").unwrap();
+        let custom = dir.join("custom.db.backup_1");
+        fs::write(&custom, "SQLite format 3").unwrap();
+
+        assert!(validate_backup("state.vscdb", &whole, &[]).is_ok());
+        assert!(validate_backup("state.vscdb", &truncated, &[]).is_err());
+        assert!(validate_backup("custom.db", &custom, &["custom.db".to_string()]).is_err());
+        assert!(validate_backup("machineid", &truncated, &[]).is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn the_ledger_counts_each_file_once() {
+        let dir = scratch_dir("ledger");
+        let file = dir.join("storage.json");
+        fs::write(&file, "{}").unwrap();
+
+        let ledger = BackupLedger::default();
+        assert!(ledger.first_backup(&file));
+        assert!(!ledger.first_backup(&file));
+        assert!(!ledger.clone().first_backup(&dir.join(".").join("storage.json")));
+        assert!(ledger.first_backup(&dir.join("state.vscdb")));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn backup_directories_skips_what_the_ledger_already_holds() {
+        let dir = scratch_dir("skip");
+        fs::write(dir.join("state.vscdb"), "not really sqlite").unwrap();
+        let ledger = BackupLedger::default();
+
+        let (first, _) = backup_directories(std::slice::from_ref(&dir), true, &[], 1, &ledger);
+        let (second, _) = backup_directories(std::slice::from_ref(&dir), true, &[], 1, &ledger);
+        assert_eq!(first.len(), 1);
+        assert!(second.is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn restore_covers_the_db_files_named_on_the_command_line() {
+        use clap::Parser;
+        let dir = scratch_dir("restore-db-file");
+        let global = dir.join("Code").join("User").join("globalStorage");
+        fs::create_dir_all(&global).unwrap();
+        let extra = global.join("extra.vscdb");
+        Connection::open(&extra).unwrap().execute("CREATE TABLE Original (key TEXT);", []).unwrap();
+        backup_files(&global, std::slice::from_ref(&extra), &mut ErrorCollector::new()).unwrap();
+        fs::write(&extra, "cleaned").unwrap();
+
+        let mut args = CliArgs::parse_from(["cleaner", "--db-file", "extra.vscdb"]);
+        args.discovery_roots = Some(crate::filesystem::DiscoveryRoots { base_dirs: vec![dir.clone()], ..Default::default() });
+        run_restore(&args).unwrap();
+
+        let tables: i64 = Connection::open(&extra).unwrap()
+            .query_row("SELECT COUNT(*) FROM sqlite_master WHERE name = 'Original';", [], |row| row.get(0)).unwrap();
+        assert_eq!(tables, 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn restoring_a_database_drops_the_wal_of_the_one_it_replaces() {
+        let dir = scratch_dir("restore-wal");
+        let db = dir.join("state.vscdb");
+        let conn = Connection::open(&db).unwrap();
+        conn.execute_batch("CREATE TABLE ItemTable (key TEXT, value TEXT); INSERT INTO ItemTable VALUES ('id', 'original');").unwrap();
+        drop(conn);
+        let backed_up = backup_files(&dir, std::slice::from_ref(&db), &mut ErrorCollector::new()).unwrap();
+
+        Connection::open(&db).unwrap().execute("UPDATE ItemTable SET value = 'cleaned'", []).unwrap();
+        fs::write(dir.join("state.vscdb-wal"), "frames of the cleaned database").unwrap();
+        fs::write(dir.join("state.vscdb-shm"), "index of those frames").unwrap();
+
+        restore_file(&db, &backed_up[0].backup, &UnlockLedger::default()).unwrap();
+
+        assert!(sidecar_files(&db).is_empty());
+        let value: String = Connection::open(&db).unwrap().query_row("SELECT value FROM ItemTable", [], |row| row.get(0)).unwrap();
+        assert_eq!(value, "original");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
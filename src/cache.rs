@@ -0,0 +1,83 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use sysinfo::System;
+use crate::filesystem::install_name;
+use crate::process::is_editor_process;
+use crate::utils::Result;
+
+/// Per-install cache folders that can hold fragments of opened files
+pub const CACHE_DIRS: [&str; 3] = ["CachedData", "Code Cache", "GPUCache"];
+
+/// A cache folder and how much space it takes up
+#[derive(Debug, Clone)]
+pub struct CacheFolder {
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+/// The install folder a storage directory or machine id file belongs to, e.g. ~/.config/Code
+pub fn install_root(target: &Path) -> Option<PathBuf> {
+    if target.is_file() {
+        return target.parent().map(Path::to_path_buf);
+    }
+    target.ancestors()
+        .find(|a| a.file_name().is_some_and(|name| name == "User"))
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+}
+
+/// Every cache folder in the installs the targets belong to
+pub fn find_cache_folders(targets: &[PathBuf]) -> Vec<CacheFolder> {
+    let mut roots: Vec<PathBuf> = targets.iter().filter_map(|target| install_root(target)).collect();
+    roots.sort();
+    roots.dedup();
+
+    roots.iter()
+        .flat_map(|root| CACHE_DIRS.iter().map(move |name| root.join(name)))
+        .filter(|path| path.is_dir())
+        .map(|path| CacheFolder { bytes: dir_size(&path), path })
+        .collect()
+}
+
+/// Total size of the files under `path`, without following symlinks
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else { return 0; };
+
+    entries.filter_map(|entry| entry.ok())
+        .map(|entry| match entry.file_type() {
+            Ok(ft) if ft.is_dir() => dir_size(&entry.path()),
+            Ok(ft) if ft.is_file() => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Whether an editor that looks like the owner of the install at `root` is running
+pub fn install_in_use(root: &Path) -> bool {
+    let install = install_name(&root.join("User")).to_lowercase();
+    System::new_all().processes().values()
+        .filter(|process| is_editor_process(process))
+        .any(|process| {
+            let name = process.name().to_string_lossy().to_lowercase();
+            let exe = process.exe().map(|p| p.to_string_lossy().to_lowercase()).unwrap_or_default();
+            name.contains(&install) || exe.contains(&install)
+        })
+}
+
+/// Sizes in the largest fitting unit, e.g. "12.3 mb"
+pub fn format_size(bytes: u64) -> String {
+    let units = ["b", "kb", "mb", "gb"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} b", bytes) } else { format!("{:.1} {}", size, units[unit]) }
+}
+
+/// Delete a cache folder, which the editor rebuilds on its next launch
+pub fn remove_cache_folder(folder: &CacheFolder) -> Result<()> {
+    fs::remove_dir_all(&folder.path)
+        .map_err(|e| format!("could not remove {}: {}", folder.path.display(), e).into())
+}
@@ -1,9 +1,95 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum LogFormat {
+    /// One line per event with its severity
+    #[default]
+    Plain,
+    /// A section per phase and a results table, for pasting into tickets
+    Markdown,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum LockLevel {
+    /// Read-only permissions, which the editor can still undo
+    #[default]
+    Readonly,
+    /// Read-only plus the linux immutable attribute (`chattr +i`), which needs root to undo
+    Immutable,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum GroupBy {
+    /// One line per editor install with its status counts
+    #[default]
+    Editor,
+    /// One line per status (cleaned, planned, error, skipped) with its editors
+    Status,
+    /// One line per directory
+    None,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum IdFormat {
+    /// Lowercase hyphenated uuid, e.g. 6f1c...-...
+    Uuid,
+    /// Sha256 digest of a random uuid as 64 hex characters
+    Sha256,
+    /// 64 hex characters taken directly from random bytes
+    Hex64,
+    /// Uppercase braced guid as written by windows editors, e.g. {6F1C...}
+    Guid,
+}
+
+impl std::fmt::Display for IdFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = self.to_possible_value().expect("no skipped id formats");
+        write!(f, "{}", value.get_name())
+    }
+}
+
+/// A parsed `--id-format` value, either a default format or one scoped to a single key
+#[derive(Clone, Debug)]
+pub struct IdFormatSpec {
+    pub key: Option<String>,
+    pub format: IdFormat,
+}
+
+fn parse_id_format(value: &str) -> Result<IdFormatSpec, String> {
+    let (key, format) = match value.split_once('=') {
+        Some((key, format)) if key.trim().is_empty() => return Err(format!("missing key before '={}'", format)),
+        Some((key, format)) => (Some(key.trim().to_string()), format),
+        None => (None, value),
+    };
+
+    let format = IdFormat::from_str(format.trim(), true)?;
+    Ok(IdFormatSpec { key, format })
+}
+
+#[derive(Subcommand, Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Put the newest valid backup of every discovered storage file back in place
+    Restore,
+}
+
+/// Flags that pick what to clean without going through card selection
+pub const MODULE_FLAGS: [&str; 4] = ["--augment", "--cursor", "--windsurf", "--vscodium"];
 
 #[derive(Parser, Clone)]
 #[command(name = "cleaner")]
 #[command(about = "A tool for managing VSCode/Cursor telemetry and privacy settings")]
 pub struct CliArgs {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[arg(long)]
     pub no_pause: bool,
 
@@ -21,4 +107,213 @@ pub struct CliArgs {
 
     #[arg(long, help = "Automatically clean Cursor IDE (skips selection)")]
     pub cursor: bool,
+
+    #[arg(long, help = "Automatically clean Windsurf IDE (skips selection)")]
+    pub windsurf: bool,
+
+    #[arg(long, help = "Automatically clean VSCodium (skips selection)")]
+    pub vscodium: bool,
+
+    #[arg(long, help = "Leave mouse capture off so terminal text selection keeps working")]
+    pub no_mouse: bool,
+
+    #[arg(long, value_name = "SECONDS", default_value_t = 0, help = "Keep the final screen open for SECONDS, then exit (0 waits for a keypress)")]
+    pub hold: u64,
+
+    #[arg(long = "telemetry-key", value_name = "KEY", help = "Only rotate this telemetry key (repeatable, defaults to all known keys)")]
+    pub telemetry_keys: Vec<String>,
+
+    #[arg(long, help = "Keep --telemetry-key values that are not known telemetry keys")]
+    pub allow_unknown_keys: bool,
+
+    #[arg(long, value_name = "KEY,KEY", value_delimiter = ',', help = "Also rotate these storage.json keys, adding them when missing")]
+    pub extra_keys: Vec<String>,
+
+    #[arg(long = "preserve-key", value_name = "KEY", help = "Never rotate this storage.json key, even when it is a telemetry key (repeatable)")]
+    pub preserve_keys: Vec<String>,
+
+    #[arg(long, value_name = "FILE", help = "Read extra keys from this config file instead of the default location")]
+    pub config: Option<PathBuf>,
+
+    #[arg(long = "id-format", value_name = "FORMAT|KEY=FORMAT", value_parser = parse_id_format, help = "Format for regenerated ids: uuid, sha256, hex64 or guid, optionally for one key (repeatable)")]
+    pub id_formats: Vec<IdFormatSpec>,
+
+    #[arg(long, visible_alias = "check", help = "Print a read-only diagnostic of discovered storage and exit")]
+    pub doctor: bool,
+
+    #[arg(long, help = "Fully mask telemetry values in diagnostic output")]
+    pub anonymize: bool,
+
+    #[arg(long, help = "Print how much telemetry is present across all installs and exit")]
+    pub count_only: bool,
+
+    #[arg(long, visible_alias = "list-only", help = "Print every storage folder, database and editor process discovery finds and exit")]
+    pub list: bool,
+
+    #[arg(long, help = "Print the tables and columns of every discovered database and exit")]
+    pub dump_schema: bool,
+
+    #[arg(long, help = "Check every file the clean will touch for permission problems before starting")]
+    pub preflight: bool,
+
+    #[arg(long, help = "Show what would change without modifying anything")]
+    pub dry_run: bool,
+
+    #[arg(long, value_name = "PATH", help = "Write a JSON report of what the run found")]
+    pub report: Option<PathBuf>,
+
+    #[arg(long, value_name = "PATH", help = "Write the run's event log to this file")]
+    pub log_file: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t = LogFormat::Plain, help = "Layout of the --log-file output")]
+    pub log_format: LogFormat,
+
+    #[arg(long, value_name = "PATH", help = "Save the ids written by this run and report any the editor regenerated since the last one")]
+    pub export_ids: Option<PathBuf>,
+
+    #[arg(long, num_args = 2, value_names = ["BEFORE", "AFTER"], help = "Compare two --report files and exit")]
+    pub diff_reports: Option<Vec<PathBuf>>,
+
+    #[arg(long, value_enum, default_value_t = GroupBy::Editor, help = "How the end-of-run summary and report group directories")]
+    pub group_by: GroupBy,
+
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, help = "Output format for non-interactive modes")]
+    pub format: OutputFormat,
+
+    #[arg(long, help = "Print one compact json object with the run's totals to stdout on exit")]
+    pub summary_json_line: bool,
+
+    #[arg(long, help = "Print the final result banner without colors (also honors NO_COLOR)")]
+    pub no_color: bool,
+
+    #[arg(long, help = "Skip the interface and print plain progress to stderr, exiting non-zero on errors")]
+    pub quiet: bool,
+
+    #[arg(long, help = "Record the time of this clean in storage.json under _cleaner.lastRun")]
+    pub mark: bool,
+
+    #[arg(long, help = "Remove the _cleaner.lastRun marker from every storage.json and exit")]
+    pub unmark: bool,
+
+    #[arg(long, help = "Sign out of Cursor by clearing only its cursorAuth session data, leaving the telemetry ids alone, and exit")]
+    pub cursor_signout: bool,
+
+    #[arg(long, help = "Fail instead of skipping storage directories that can't be read")]
+    pub strict_discovery: bool,
+
+    #[arg(long, help = "Write through a storage.json that is a symlink instead of skipping it")]
+    pub follow_symlinks: bool,
+
+    #[arg(long, value_enum, default_value_t = LockLevel::Readonly, help = "How strictly to lock regenerated machine id files")]
+    pub lock_level: LockLevel,
+
+    #[arg(long, help = "Also clear search, terminal and command palette history from state.vscdb")]
+    pub clean_history_keys: bool,
+
+    #[arg(long = "history-key", value_name = "PATTERN", help = "SQL LIKE pattern of history keys to clear (repeatable, replaces the defaults)")]
+    pub history_keys: Vec<String>,
+
+    #[arg(long, help = "Strip install timestamps from each editor's extensions/extensions.json, keeping the extension list")]
+    pub clean_extensions_json: bool,
+
+    #[arg(long, help = "Also delete the CachedData, Code Cache and GPUCache folders of each install")]
+    pub clean_cache: bool,
+
+    #[arg(long, value_name = "MB", default_value_t = 512, help = "Skip cache folders larger than MB unless this is raised to confirm")]
+    pub cache_confirm_mb: u64,
+
+    #[arg(long, value_name = "MB", default_value_t = 16, help = "Skip a storage.json larger than MB instead of loading it, since only a corrupted one gets that big")]
+    pub max_file_size: u64,
+
+    #[arg(long, value_name = "ROOT", help = "Also remove telemetry ids from .vscode/settings.json files in projects under ROOT")]
+    pub scan_workspaces: Option<PathBuf>,
+
+    #[arg(long, value_name = "N", default_value_t = 4, help = "How many folders deep --scan-workspaces looks under ROOT")]
+    pub max_depth: usize,
+
+    #[arg(long, value_name = "N", default_value_t = 2, help = "Copy backups for at most N directories at once")]
+    pub backup_jobs: usize,
+
+    #[arg(long, value_name = "N", help = "Clean at most N directories in this run across all selected modules, least recently cleaned first, so the next run picks up the rest")]
+    pub limit: Option<usize>,
+
+    #[arg(long, help = "With --limit, take every global storage before any workspace storage")]
+    pub prefer_global: bool,
+
+    #[arg(long, help = "Rewrite storage.json without first copying the original to the backups folder")]
+    pub no_backup: bool,
+
+    #[arg(long, help = "Skip compacting state databases after rows are deleted (vacuuming large ones is slow)")]
+    pub no_vacuum: bool,
+
+    #[arg(long, help = "Copy the state databases before terminating editors, in case the kill corrupts them")]
+    pub backup_before_terminate: bool,
+
+    #[arg(long, visible_alias = "force", help = "Clean even the directories of an editor still running (use with --no-terminate); it may overwrite the new ids or keep databases locked")]
+    pub ignore_running: bool,
+
+    #[arg(long, help = "Under WSL, also clean the Windows-side installs in /mnt/c/Users/<name>/AppData")]
+    pub wsl_windows: bool,
+
+    #[arg(long = "extension", value_name = "ID", help = "Delete the database rows of this extension, e.g. augment.vscode-augment, instead of every key mentioning augment (repeatable)")]
+    pub extensions: Vec<String>,
+
+    #[arg(long = "db-file", value_name = "NAME", help = "Also clean this database file in each storage directory, besides state.vscdb* (repeatable)")]
+    pub db_files: Vec<String>,
+
+    #[arg(long = "path", value_name = "DIR", help = "Also look for editor storage in DIR, e.g. a portable or custom install (repeatable)")]
+    pub paths: Vec<PathBuf>,
+
+    #[arg(long, value_name = "SECONDS", help = "How long terminating an editor may take: SECONDS to close once asked before it is killed (5 by default), and as long again to keep killing it if it respawns (without the flag respawns are only reported)")]
+    pub kill_timeout: Option<u64>,
+
+    #[arg(long, value_name = "N", default_value_t = 3, help = "Retry a locked database N times before asking to close the editor")]
+    pub lock_retries: u32,
+
+    #[arg(long, value_name = "MS", default_value_t = 500, help = "Base wait between database lock retries, growing with each attempt")]
+    pub lock_wait_ms: u64,
+
+    #[arg(long, help = "Wait in the background and clean again each time the editor closes")]
+    pub watch: bool,
+
+    #[arg(long, value_name = "MS", default_value_t = 2000, help = "How often --watch checks whether the editor is running")]
+    pub watch_interval: u64,
+
+    #[arg(long, help = "Like --watch, but stop after the first clean")]
+    pub watch_once: bool,
+
+    /// Directories unticked in the interface's checklist, left out of every module's clean
+    #[arg(skip)]
+    pub deselected: Vec<PathBuf>,
+
+    /// Held by the interface while the user has paused the run
+    #[arg(skip)]
+    pub pause: crate::events::PauseFlag,
+
+    /// Files this run has backed up so far
+    #[arg(skip)]
+    pub backups: crate::backup::BackupLedger,
+
+    /// Read-only files this run has unlocked so far
+    #[arg(skip)]
+    pub unlocked: crate::storage::UnlockLedger,
+
+    /// Where discovery looks instead of this machine's usual locations, set by tests
+    #[arg(skip)]
+    pub discovery_roots: Option<crate::filesystem::DiscoveryRoots>,
+}
+
+impl CliArgs {
+    /// Whether a module flag was given, so cleaning can start without anyone at the keyboard
+    pub fn has_module_flag(&self) -> bool {
+        self.augment || self.cursor || self.windsurf || self.vscodium
+    }
+
+    /// Fail early when there is no terminal to pick cards in and no module flag says what to clean
+    pub fn require_module_flag(&self) -> crate::utils::Result<()> {
+        if self.has_module_flag() {
+            return Ok(());
+        }
+        Err(format!("nothing selected to clean and no terminal to choose in - pass one of {}", MODULE_FLAGS.join(", ")).into())
+    }
 }
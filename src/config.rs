@@ -0,0 +1,53 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::cli::CliArgs;
+use crate::utils::Result;
+
+/// Settings read from the optional config file, merged into the command line options
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CleanerConfig {
+    /// Keys rotated in storage.json on top of the built-in telemetry keys
+    pub extra_keys: Vec<String>,
+    /// Keys never rotated, on top of any --preserve-key
+    pub preserve_keys: Vec<String>,
+}
+
+/// Where the config file is looked for when `--config` is not given
+pub fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("vscode-telemetry-cleaner").join("config.json"))
+}
+
+/// Read a config file, treating a missing file as empty unless it was asked for explicitly
+pub fn load_config(path: &Path, required: bool) -> Result<CleanerConfig> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => return Ok(CleanerConfig::default()),
+        Err(e) => return Err(format!("could not read config '{}': {}", path.display(), e).into()),
+    };
+
+    serde_json::from_str(&content).map_err(|e| format!("invalid config '{}': {}", path.display(), e).into())
+}
+
+/// Append the config file's extra and preserved keys to `args`, after the ones given on the command line
+pub fn apply_config(args: &mut CliArgs) -> Result<()> {
+    let config = match (&args.config, default_config_path()) {
+        (Some(path), _) => load_config(path, true)?,
+        (None, Some(path)) => load_config(&path, false)?,
+        (None, None) => return Ok(()),
+    };
+
+    for key in config.extra_keys {
+        if !args.extra_keys.contains(&key) {
+            args.extra_keys.push(key);
+        }
+    }
+    for key in config.preserve_keys {
+        if !args.preserve_keys.contains(&key) {
+            args.preserve_keys.push(key);
+        }
+    }
+
+    Ok(())
+}
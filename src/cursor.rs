@@ -1,7 +1,14 @@
+use serde::Serialize;
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 use serde_json::{json, Value};
 use uuid::Uuid;
+use crate::backup::{backup_directories, backup_files};
+use crate::events::{KeepProblems, Progress};
+use crate::cli::IdFormat;
+use crate::filesystem::{for_each_directory, symlink_target};
+use crate::storage::{fresh_id, rewrite_json_keys, ChangedKeys, RewriteOpts};
 use crate::utils::{Result, CleanerError, ErrorCollector};
 
 #[derive(Debug, Clone)]
@@ -12,15 +19,26 @@ pub struct CursorConfig {
     pub telemetry_sqm_id: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CursorCleaningResult {
     pub processes_terminated: Vec<String>,
     pub directories_removed: Vec<PathBuf>,
     pub config_updated: bool,
     pub backup_created: Option<PathBuf>,
+    pub history_entries_cleared: usize,
+    /// Storage keys given new values, per storage directory
+    pub keys_changed: ChangedKeys,
+    /// State databases rebuilt after rows were deleted, with their sizes
+    pub compacted: Vec<crate::database::Compaction>,
     pub errors: ErrorCollector,
 }
 
+impl Default for CursorCleaningResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CursorCleaningResult {
     pub fn new() -> Self {
         Self {
@@ -28,83 +46,17 @@ impl CursorCleaningResult {
             directories_removed: Vec::new(),
             config_updated: false,
             backup_created: None,
+            history_entries_cleared: 0,
+            keys_changed: ChangedKeys::new(),
+            compacted: Vec::new(),
             errors: ErrorCollector::new(),
         }
     }
 }
 
-/// Find Cursor storage directories (similar to VSCode storage directories)
-pub fn find_cursor_storage_directories() -> Vec<PathBuf> {
-    let mut cursor_dirs = Vec::new();
-
-    // Windows: AppData\Roaming\Cursor\User\globalStorage and workspaceStorage
-    #[cfg(target_os = "windows")]
-    {
-        if let Some(appdata) = dirs::config_dir() {
-            let cursor_global = appdata.join("Cursor/User/globalStorage");
-            if cursor_global.exists() {
-                cursor_dirs.push(cursor_global);
-            }
-
-            let cursor_workspace = appdata.join("Cursor/User/workspaceStorage");
-            if cursor_workspace.exists() {
-                // Add all workspace directories
-                if let Ok(entries) = std::fs::read_dir(&cursor_workspace) {
-                    for entry in entries.filter_map(|e| e.ok()) {
-                        if entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
-                            cursor_dirs.push(entry.path());
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    // macOS: ~/Library/Application Support/Cursor/User/globalStorage
-    #[cfg(target_os = "macos")]
-    {
-        if let Some(home) = dirs::home_dir() {
-            let cursor_global = home.join("Library/Application Support/Cursor/User/globalStorage");
-            if cursor_global.exists() {
-                cursor_dirs.push(cursor_global);
-            }
-
-            let cursor_workspace = home.join("Library/Application Support/Cursor/User/workspaceStorage");
-            if cursor_workspace.exists() {
-                if let Ok(entries) = std::fs::read_dir(&cursor_workspace) {
-                    for entry in entries.filter_map(|e| e.ok()) {
-                        if entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
-                            cursor_dirs.push(entry.path());
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    // Linux: ~/.config/Cursor/User/globalStorage
-    #[cfg(target_os = "linux")]
-    {
-        if let Some(home) = dirs::home_dir() {
-            let cursor_global = home.join(".config/Cursor/User/globalStorage");
-            if cursor_global.exists() {
-                cursor_dirs.push(cursor_global);
-            }
-
-            let cursor_workspace = home.join(".config/Cursor/User/workspaceStorage");
-            if cursor_workspace.exists() {
-                if let Ok(entries) = std::fs::read_dir(&cursor_workspace) {
-                    for entry in entries.filter_map(|e| e.ok()) {
-                        if entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) {
-                            cursor_dirs.push(entry.path());
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    cursor_dirs
+/// Find Cursor storage directories, kept under Cursor/User in the config dir like VSCode's under Code/User
+pub fn find_cursor_storage_directories(args: &crate::cli::CliArgs) -> Result<Vec<PathBuf>> {
+    crate::filesystem::find_editor_storage_directories(args, "Cursor")
 }
 
 pub fn find_cursor_directories() -> Vec<PathBuf> {
@@ -126,12 +78,20 @@ pub fn find_cursor_directories() -> Vec<PathBuf> {
             }
         }
         
-        if let Some(local_appdata) = std::env::var("LOCALAPPDATA").ok() {
+        // var_os keeps non-unicode profile paths intact where env::var would drop them
+        if let Some(local_appdata) = std::env::var_os("LOCALAPPDATA") {
             let cursor_local = PathBuf::from(local_appdata).join("cursor");
             if cursor_local.exists() {
                 cursor_dirs.push(cursor_local);
             }
         }
+
+        for appdata in crate::filesystem::redirected_known_folders() {
+            let cursor_path = appdata.join("Cursor");
+            if cursor_path.exists() && !cursor_dirs.contains(&cursor_path) {
+                cursor_dirs.push(cursor_path);
+            }
+        }
     }
     
     #[cfg(target_os = "macos")]
@@ -195,35 +155,64 @@ pub fn get_cursor_storage_path() -> Option<PathBuf> {
     None
 }
 
-pub fn generate_cursor_ids() -> CursorConfig {
-    let mac_machine_id = Uuid::new_v4().to_string();
-    let dev_device_id = Uuid::new_v4().to_string();
-    let sqm_id = format!("{{{}}}", Uuid::new_v4().to_string().to_uppercase());
-
+/// Generate new Cursor ids that all differ from `previous`, returning the keys whose first pick had to be redrawn
+pub fn generate_cursor_ids(previous: Option<&CursorConfig>) -> Result<(CursorConfig, Vec<String>)> {
     let prefix = "auth0|user_";
     let prefix_bytes = prefix.as_bytes();
     let prefix_hex = prefix_bytes.iter()
         .map(|b| format!("{:02x}", b))
         .collect::<String>();
 
-    // use uuid for random bytes instead of rand crate
-    let random_uuid = Uuid::new_v4();
-    let random_bytes = random_uuid.as_bytes();
-    let random_hex = random_bytes.iter()
-        .map(|b| format!("{:02x}", b))
-        .collect::<String>();
+    let machine_id = || {
+        // use uuid for random bytes instead of rand crate
+        let random_uuid = Uuid::new_v4();
+        let random_bytes = random_uuid.as_bytes();
+        let random_hex = random_bytes.iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        format!("{}{}", prefix_hex, random_hex)
+    };
 
-    let machine_id = format!("{}{}", prefix_hex, random_hex);
+    let mut collisions = Vec::new();
+    let mut fresh = |key: &str, old: Option<&String>, generate: &dyn Fn() -> String| -> Result<String> {
+        let (value, collided) = fresh_id(old.map(String::as_str), generate)?;
+        if collided {
+            collisions.push(key.to_string());
+        }
+        Ok(value)
+    };
 
-    CursorConfig {
-        telemetry_machine_id: machine_id,
-        telemetry_mac_machine_id: mac_machine_id,
-        telemetry_dev_device_id: dev_device_id,
-        telemetry_sqm_id: sqm_id,
-    }
+    let config = CursorConfig {
+        telemetry_machine_id: fresh("telemetry.machineId", previous.map(|p| &p.telemetry_machine_id), &machine_id)?,
+        telemetry_mac_machine_id: fresh("telemetry.macMachineId", previous.map(|p| &p.telemetry_mac_machine_id), &|| Uuid::new_v4().to_string())?,
+        telemetry_dev_device_id: fresh("telemetry.devDeviceId", previous.map(|p| &p.telemetry_dev_device_id), &|| Uuid::new_v4().to_string())?,
+        telemetry_sqm_id: fresh("telemetry.sqmId", previous.map(|p| &p.telemetry_sqm_id), &|| format!("{{{}}}", Uuid::new_v4().to_string().to_uppercase()))?,
+    };
+
+    Ok((config, collisions))
+}
+
+/// The ids currently in Cursor's storage.json, if it can be read
+pub fn read_cursor_ids() -> Option<CursorConfig> {
+    let content = fs::read_to_string(get_cursor_storage_path()?).ok()?;
+    let data: Value = serde_json::from_str(&content).ok()?;
+    let field = |key: &str| data.get(key).and_then(Value::as_str).unwrap_or_default().to_string();
+
+    Some(CursorConfig {
+        telemetry_machine_id: field("telemetry.machineId"),
+        telemetry_mac_machine_id: field("telemetry.macMachineId"),
+        telemetry_dev_device_id: field("telemetry.devDeviceId"),
+        telemetry_sqm_id: field("telemetry.sqmId"),
+    })
 }
 
-pub fn clean_cursor_config(config: &CursorConfig) -> Result<bool> {
+/// Write the pre-generated `config` ids into Cursor's storage.json, backing it up first unless this run already has
+pub fn clean_cursor_config(config: &CursorConfig, args: &crate::cli::CliArgs, warnings: &mut ErrorCollector) -> Result<bool> {
+    // a dry run only reports the ids it would write
+    if args.dry_run {
+        return Ok(false);
+    }
+
     let storage_path = match get_cursor_storage_path() {
         Some(path) => path,
         None => return Ok(false),
@@ -233,88 +222,45 @@ pub fn clean_cursor_config(config: &CursorConfig) -> Result<bool> {
         return Ok(false);
     }
     
-    let backup_dir = storage_path.parent()
-        .unwrap_or(&storage_path)
-        .join("backups");
-    
-    if !backup_dir.exists() {
-        fs::create_dir_all(&backup_dir)?;
+    if let Some(target) = symlink_target(&storage_path).filter(|_| !args.follow_symlinks) {
+        warnings.add_warning(format!("{} is a symlink to {} - left untouched, pass --follow-symlinks to write through it", storage_path.display(), target.display()));
+        return Ok(false);
+    }
+
+    let storage_dir = storage_path.parent().unwrap_or(&storage_path).to_path_buf();
+    // step 5 usually backed it up already, before it rotated the ids
+    if !args.no_backup && args.backups.first_backup(&storage_path) {
+        backup_files(&storage_dir, std::slice::from_ref(&storage_path), warnings)?;
     }
     
     let timestamp = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs();
-    let backup_path = backup_dir.join(format!("storage.json.backup_{}", timestamp));
-    
-    fs::copy(&storage_path, &backup_path)?;
-    
-    let content = fs::read_to_string(&storage_path)?;
-    let mut storage_json: Value = serde_json::from_str(&content)
-        .unwrap_or_else(|_| json!({}));
-    
-    if let Some(obj) = storage_json.as_object_mut() {
-        obj.insert("telemetry.machineId".to_string(), json!(config.telemetry_machine_id));
-        obj.insert("telemetry.macMachineId".to_string(), json!(config.telemetry_mac_machine_id));
-        obj.insert("telemetry.devDeviceId".to_string(), json!(config.telemetry_dev_device_id));
-        obj.insert("telemetry.sqmId".to_string(), json!(config.telemetry_sqm_id));
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        obj.insert("lastModified".to_string(), json!(timestamp.to_string()));
+
+    // the ids were generated up front so they are written as given rather than rotated again
+    let mut opts = RewriteOpts::default();
+    opts.values.insert("telemetry.machineId".to_string(), json!(config.telemetry_machine_id));
+    opts.values.insert("telemetry.macMachineId".to_string(), json!(config.telemetry_mac_machine_id));
+    opts.values.insert("telemetry.devDeviceId".to_string(), json!(config.telemetry_dev_device_id));
+    opts.values.insert("telemetry.sqmId".to_string(), json!(config.telemetry_sqm_id));
+    opts.values.insert("lastModified".to_string(), json!(timestamp.to_string()));
+    opts.values.retain(|key, _| !crate::utils::is_preserved(key, &args.preserve_keys));
+
+    let result = rewrite_json_keys(&storage_path, &[], IdFormat::Sha256, &opts)?;
+    for warning in result.warnings {
+        warnings.add_warning(warning);
     }
     
-    let updated_content = serde_json::to_string_pretty(&storage_json)?;
-    fs::write(&storage_path, updated_content)?;
-    
     Ok(true)
 }
 
-pub fn terminate_cursor_processes() -> Result<bool> {
-    let process_names = vec!["Cursor", "cursor", "Cursor.exe", "cursor.exe"];
-    let mut terminated = false;
-    
-    for process_name in process_names {
-        #[cfg(target_os = "windows")]
-        {
-            let output = std::process::Command::new("tasklist")
-                .args(&["/FI", &format!("IMAGENAME eq {}", process_name)])
-                .output();
-                
-            if let Ok(output) = output {
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                if output_str.contains(process_name) {
-                    let _ = std::process::Command::new("taskkill")
-                        .args(&["/F", "/IM", process_name])
-                        .output();
-                    terminated = true;
-                }
-            }
-        }
-        
-        #[cfg(any(target_os = "macos", target_os = "linux"))]
-        {
-            let output = std::process::Command::new("pgrep")
-                .arg(process_name)
-                .output();
-                
-            if let Ok(output) = output {
-                if !output.stdout.is_empty() {
-                    let _ = std::process::Command::new("pkill")
-                        .args(&["-f", process_name])
-                        .output();
-                    terminated = true;
-                }
-            }
-        }
-    }
-    
-    if terminated {
-        std::thread::sleep(std::time::Duration::from_secs(2));
-    }
-    
-    Ok(terminated)
+/// Process names Cursor runs under on each platform
+pub const CURSOR_PROCESSES: [&str; 4] = ["Cursor", "cursor", "Cursor.exe", "cursor.exe"];
+
+pub fn terminate_cursor_processes(grace: Duration) -> Result<bool> {
+    let process_names: Vec<String> = CURSOR_PROCESSES.iter().map(|name| name.to_string()).collect();
+    Ok(!crate::process::terminate_named_processes(&process_names, grace).is_empty())
 }
 
 pub fn remove_cursor_directories() -> Result<Vec<PathBuf>> {
@@ -333,15 +279,144 @@ pub fn remove_cursor_directories() -> Result<Vec<PathBuf>> {
     Ok(removed_dirs)
 }
 
+/// ItemTable keys holding Cursor's login session, matched with LIKE
+pub const CURSOR_AUTH_PATTERN: &str = "cursorAuth/%";
+
+/// Sign out of Cursor by clearing only its session rows and storage.json keys, leaving the telemetry ids as they are
+pub fn run_cursor_signout(args: &crate::cli::CliArgs) -> Result<()> {
+    let running: Vec<String> = crate::process::running_editors().into_iter()
+        .filter(|process| process.to_lowercase().contains("cursor"))
+        .collect();
+    if !running.is_empty() {
+        println!("warning: {} is running and may write its session back - close it first", running.join(", "));
+    }
+
+    let verb = if args.dry_run { "would clear" } else { "cleared" };
+    let listing = crate::cli::CliArgs { dry_run: true, ..args.clone() };
+    let mut cleared = 0;
+    let mut failed = 0;
+
+    let (directories, _) = crate::filesystem::choose_directories(find_cursor_storage_directories(args)?, args);
+    for directory in directories {
+        let databases: Vec<PathBuf> = crate::database::database_files(&directory, &args.db_files).into_iter()
+            .map(|db| directory.join(db))
+            .filter(|db| db.is_file())
+            .collect();
+        let storage_path = directory.join("storage.json");
+
+        // only the files that hold a session are copied before it is cleared
+        if !args.dry_run && !args.no_backup {
+            let mut files: Vec<PathBuf> = databases.iter()
+                .filter(|db| crate::database::clear_item_keys(db, CURSOR_AUTH_PATTERN, &listing).is_ok_and(|keys| !keys.is_empty()))
+                .cloned()
+                .collect();
+            if storage_auth_keys(&storage_path, args.max_file_size).is_some_and(|keys| !keys.is_empty()) {
+                files.push(storage_path.clone());
+            }
+            let mut warnings = ErrorCollector::new();
+            if let Err(e) = backup_files(&directory, &files, &mut warnings) {
+                println!("error: could not back up {}, left signed in: {}", directory.display(), e);
+                failed += 1;
+                continue;
+            }
+            for warning in warnings.warnings {
+                println!("warning: {}", warning);
+            }
+        }
+
+        for db in &databases {
+            match crate::database::clear_item_keys(db, CURSOR_AUTH_PATTERN, args) {
+                Ok(keys) => {
+                    for key in keys {
+                        println!("{} {} from {}", verb, key, db.display());
+                        cleared += 1;
+                    }
+                }
+                Err(e) => {
+                    println!("error: {}", e);
+                    failed += 1;
+                }
+            }
+        }
+
+        match clear_storage_auth_keys(&storage_path, args) {
+            Ok(keys) => {
+                for key in keys {
+                    println!("{} {} from {}", verb, key, storage_path.display());
+                    cleared += 1;
+                }
+            }
+            Err(e) => {
+                println!("error: {}: {}", storage_path.display(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    if args.dry_run {
+        println!("{} cursor session entries would be cleared - telemetry ids left as they are", cleared);
+    } else {
+        println!("{} cursor session entries cleared - telemetry ids left as they are", cleared);
+    }
+    if failed > 0 {
+        return Err(format!("{} files could not be signed out", failed).into());
+    }
+    Ok(())
+}
+
+/// The session keys in a storage.json, none when it is missing, too large or unreadable
+fn storage_auth_keys(storage_path: &std::path::Path, max_file_size: u64) -> Option<Vec<String>> {
+    let size = fs::metadata(storage_path).ok()?.len();
+    if size > max_file_size.saturating_mul(1024 * 1024) {
+        return None;
+    }
+    let data: serde_json::Map<String, Value> = serde_json::from_str(&fs::read_to_string(storage_path).ok()?).ok()?;
+    Some(data.keys().filter(|key| key.starts_with("cursorAuth/")).cloned().collect())
+}
+
+/// Remove the session keys from a storage.json, returning them; a dry run only lists them
+fn clear_storage_auth_keys(storage_path: &std::path::Path, args: &crate::cli::CliArgs) -> Result<Vec<String>> {
+    let Some(keys) = storage_auth_keys(storage_path, args.max_file_size) else { return Ok(Vec::new()); };
+    if keys.is_empty() || args.dry_run {
+        return Ok(keys);
+    }
+
+    if let Some(target) = symlink_target(storage_path).filter(|_| !args.follow_symlinks) {
+        return Err(format!("a symlink to {} - left untouched, pass --follow-symlinks to write through it", target.display()).into());
+    }
+
+    crate::storage::remove_json_keys(storage_path, &keys, &args.unlocked)
+}
+
 /// Perform complete Cursor IDE cleaning
-pub async fn clean_cursor_ide(args: &crate::cli::CliArgs) -> Result<CursorCleaningResult> {
+pub async fn clean_cursor_ide(args: &crate::cli::CliArgs, progress: &(impl Progress + Sync)) -> Result<CursorCleaningResult> {
     let mut result = CursorCleaningResult::new();
 
-    // Step 1: Terminate Cursor processes (only if not disabled by no_terminate)
-    if !args.no_terminate {
-        match terminate_cursor_processes() {
+    // Step 1: Find Cursor storage directories (like VSCode)
+    let (cursor_storage_dirs, _) = crate::filesystem::choose_directories(find_cursor_storage_directories(args)?, args);
+    result.directories_removed = cursor_storage_dirs.clone(); // Track found directories
+
+    // Step 2: Back up only the files this run is about to rewrite, before the kill when asked to
+    let snapshot = crate::backup::snapshot_before_terminate(args);
+    let backup = |result: &mut CursorCleaningResult| {
+        if args.dry_run {
+            return;
+        }
+        let (backed_up, backup_warnings) = backup_directories(&cursor_storage_dirs, !args.no_signout, &args.db_files, args.backup_jobs, &args.backups);
+        result.backup_created = backed_up.first().map(|file| file.backup.clone());
+        result.errors.extend(backup_warnings);
+    };
+    if snapshot {
+        backup(&mut result);
+    }
+
+    // Step 3: Terminate Cursor processes (only if not disabled by no_terminate, a dry run leaves them running)
+    if !args.no_terminate && !args.dry_run {
+        match terminate_cursor_processes(crate::process::grace_period(args)) {
             Ok(terminated) => {
                 if terminated {
+                    let names: Vec<String> = CURSOR_PROCESSES.iter().map(|n| n.to_string()).collect();
+                    crate::process::report_respawns(&names, args, &mut result.errors);
                     result.processes_terminated.push("Cursor".to_string());
                 }
             },
@@ -351,49 +426,52 @@ pub async fn clean_cursor_ide(args: &crate::cli::CliArgs) -> Result<CursorCleani
                 source: e.to_string(),
             }),
         }
+        progress.advance(1);
     }
 
-    // Step 2: Generate new IDs
-    let new_config = generate_cursor_ids();
-
-    // Step 3: Find Cursor storage directories (like VSCode)
-    let cursor_storage_dirs = find_cursor_storage_directories();
-    result.directories_removed = cursor_storage_dirs.clone(); // Track found directories
+    // Step 4: Generate new IDs
+    let (new_config, collisions) = generate_cursor_ids(read_cursor_ids().as_ref())?;
+    for key in collisions {
+        result.errors.add_warning(format!("new {} matched the old one, generated another", key));
+    }
 
-    // Step 4: Update storage files (like VSCode storage.json updates)
-    for directory in &cursor_storage_dirs {
-        // Create a dummy channel since we're not using the UI here
-        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+    // otherwise back up once the editor has let go of the files
+    if !snapshot {
+        backup(&mut result);
+    }
 
-        match crate::storage::update_vscode_storage(directory, &tx) {
-            Ok(_) => result.config_updated = true,
-            Err(e) => result.errors.add_error(CleanerError::Json {
-                operation: "update_storage".to_string(),
-                path: directory.display().to_string(),
-                source: e.to_string(),
-            }),
+    // Step 5: Update storage files (like VSCode storage.json updates)
+    let progress = KeepProblems::new(progress);
+    let outcomes = for_each_directory(&cursor_storage_dirs, &args.pause, |directory| {
+        let outcome = crate::storage::update_vscode_storage(directory, args, &progress).ok();
+        progress.advance(1);
+        outcome
+    });
+    for (directory, outcome) in cursor_storage_dirs.iter().zip(outcomes) {
+        // a failure's details reach result.errors through progress
+        if let Some(changed) = outcome {
+            result.config_updated = true;
+            if !changed.is_empty() {
+                result.keys_changed.insert(directory.display().to_string(), changed);
+            }
         }
     }
 
-    // Step 5: Clean databases (only if not disabled by no_signout)
+    // Step 6: Clean databases (only if not disabled by no_signout)
     if !args.no_signout {
-        for directory in &cursor_storage_dirs {
-            // Create a dummy channel since we're not using the UI here
-            let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
-
-            match crate::database::clean_vscode_databases(directory, &tx) {
-                Ok(_) => {}, // Success - databases cleaned
-                Err(e) => result.errors.add_error(CleanerError::Database {
-                    operation: "clean_databases".to_string(),
-                    path: directory.display().to_string(),
-                    source: e.to_string(),
-                }),
-            }
-        }
+        let outcomes = for_each_directory(&cursor_storage_dirs, &args.pause, |directory| {
+            let outcome = crate::database::clean_vscode_databases(directory, args, &progress).ok();
+            progress.advance(1);
+            outcome
+        });
+        result.history_entries_cleared += outcomes.into_iter().flatten().sum::<usize>();
     }
 
-    // Step 6: Update Cursor-specific configuration (legacy approach)
-    match clean_cursor_config(&new_config) {
+    result.errors.extend(progress.take());
+    result.compacted = progress.take_compactions();
+
+    // Step 7: Update Cursor-specific configuration (legacy approach)
+    match clean_cursor_config(&new_config, args, &mut result.errors) {
         Ok(updated) => {
             if updated {
                 result.config_updated = true;
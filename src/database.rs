@@ -1,92 +1,483 @@
-use rusqlite::Connection;
-use std::path::Path;
-use crate::utils::{Result, CleanerError, ErrorCollector, COUNT_QUERY, DELETE_QUERY};
-use tokio::sync::mpsc;
-use crate::zen_garden::ZenEvent;
+use rusqlite::{params_from_iter, Connection, ErrorCode, OpenFlags};
+use serde::Serialize;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crate::cache::format_size;
+use crate::cli::CliArgs;
+use crate::filesystem::install_name;
+use crate::utils::{Result, CleanerError, ErrorCollector, extension_row_filter, history_key_patterns};
+use crate::events::Progress;
 use crate::storage::FilePermissions;
 
-pub fn clean_vscode_databases(directory: &Path, tx: &mpsc::UnboundedSender<ZenEvent>) -> Result<()> {
-    let mut error_collector = ErrorCollector::new();
+/// The databases every editor keeps in its storage directories
+pub const DEFAULT_DATABASES: [&str; 2] = ["state.vscdb", "state.vscdb.backup"];
 
-    // try to clean both database files, collecting errors instead of stopping
-    if let Err(e) = clean_database_file(directory, "state.vscdb", tx) {
-        let error = CleanerError::Database {
-            operation: "cleaning state.vscdb".to_string(),
-            path: directory.join("state.vscdb").display().to_string(),
-            source: e.to_string(),
-        };
-        error_collector.add_error(error.clone());
-        let _ = tx.send(ZenEvent::DetailedError(error));
+/// Endings of the files sqlite keeps beside a database while it works, none of them a database to clean
+const SIDECAR_SUFFIXES: [&str; 3] = ["-wal", "-shm", "-journal"];
+
+/// Whether a file name in a storage directory is a state database variant, not a sidecar or a copy the cleaner made.
+///
+/// Backups go next to the file as `<name>.backup_<timestamp>` when the backups folder isn't writable.
+fn is_database_variant(name: &str) -> bool {
+    name.starts_with("state.vscdb")
+        && !name.contains(".backup_")
+        && !SIDECAR_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// Whether `name` is a database a clean touches: a state database variant or one of the `--db-file` names
+pub fn is_database_name(name: &str, db_files: &[String]) -> bool {
+    is_database_variant(name) || db_files.iter().any(|db| db.trim() == name)
+}
+
+/// The sidecar files sqlite left beside `db_path`, which belong to that exact database file
+pub fn sidecar_files(db_path: &Path) -> Vec<PathBuf> {
+    SIDECAR_SUFFIXES.iter()
+        .map(|suffix| {
+            let mut name = db_path.as_os_str().to_os_string();
+            name.push(suffix);
+            PathBuf::from(name)
+        })
+        .filter(|sidecar| sidecar.exists())
+        .collect()
+}
+
+/// Names of the databases to clean in `directory`: the defaults, any other state.vscdb* file there, then each `--db-file`
+pub fn database_files(directory: &Path, extra: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = DEFAULT_DATABASES.iter().map(|name| name.to_string()).collect();
+
+    // forks and older versions leave variants such as state.vscdb.old beside the usual two
+    if let Ok(entries) = fs::read_dir(directory) {
+        let mut found: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().map(|ft| ft.is_file()).unwrap_or(false))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| is_database_variant(name))
+            .collect();
+        found.sort();
+        names.extend(found);
     }
 
-    if let Err(e) = clean_database_file(directory, "state.vscdb.backup", tx) {
-        let error = CleanerError::Database {
-            operation: "cleaning state.vscdb.backup".to_string(),
-            path: directory.join("state.vscdb.backup").display().to_string(),
-            source: e.to_string(),
-        };
-        error_collector.add_error(error.clone());
-        let _ = tx.send(ZenEvent::DetailedError(error));
+    names.extend(extra.iter().map(|name| name.trim().to_string()).filter(|name| !name.is_empty()));
+
+    let mut seen = std::collections::HashSet::new();
+    names.retain(|name| seen.insert(name.clone()));
+    names
+}
+
+/// A state database rebuilt by VACUUM after rows were deleted, with its size in bytes before and after
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Compaction {
+    pub path: String,
+    pub before: u64,
+    pub after: u64,
+}
+
+impl Compaction {
+    /// e.g. "compacted 'state.vscdb' from 12.0 MB to 3.1 MB"
+    pub fn describe(&self) -> String {
+        let name = Path::new(&self.path).file_name().unwrap_or_default().to_string_lossy();
+        format!("compacted '{}' from {} to {}", name, format_size(self.before), format_size(self.after))
+    }
+}
+
+/// Whether `path` starts with the sqlite header, so other files that happen to match a name are left alone
+pub fn is_sqlite_file(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    fs::File::open(path).and_then(|mut file| file.read_exact(&mut header)).is_ok() && &header == b"SQLite format 3\0"
+}
+
+/// Clean every state database in `directory`, returning how many history entries were cleared
+pub fn clean_vscode_databases(directory: &Path, args: &CliArgs, progress: &impl Progress) -> Result<usize> {
+    let mut error_collector = ErrorCollector::new();
+    let mut history_cleared = 0;
+
+    // try to clean each database file, collecting errors instead of stopping
+    for filename in database_files(directory, &args.db_files) {
+        match clean_database_file(directory, &filename, args, progress) {
+            Ok(cleared) => history_cleared += cleared,
+            Err(e) => {
+                let error = CleanerError::Database {
+                    operation: format!("cleaning {}", filename),
+                    path: directory.join(&filename).display().to_string(),
+                    source: e.to_string(),
+                };
+                error_collector.add_error(error.clone());
+                progress.error(error);
+            }
+        }
     }
 
     // send error summary if there were any errors
     if error_collector.has_errors() {
-        let _ = tx.send(ZenEvent::ErrorSummary(error_collector));
+        progress.error_summary(error_collector);
         return Err("database cleaning encountered errors".into());
     }
 
-    Ok(())
+    Ok(history_cleared)
 }
 
-fn clean_database_file(directory: &Path, filename: &str, tx: &mpsc::UnboundedSender<ZenEvent>) -> Result<()> {
+fn clean_database_file(directory: &Path, filename: &str, args: &CliArgs, progress: &impl Progress) -> Result<usize> {
     let db_path = directory.join(filename);
-    if !db_path.exists() {
-        let _ = tx.send(ZenEvent::LogMessage(format!("database file '{}' not found - already at peace", filename)));
-        return Ok(());
+    if !db_path.is_file() {
+        // a stray -wal or -shm without its main database is left alone rather than replayed into a new one
+        let wal_path = directory.join(format!("{}-wal", filename));
+        if wal_path.exists() {
+            progress.warn(format!("found '{}' without its database - leaving it untouched", wal_path.display()));
+        }
+        progress.log(format!("database file '{}' not found - already at peace", filename));
+        return Ok(0);
     }
 
     let display_name = db_path.file_name().unwrap_or_default().to_string_lossy();
-    let _ = tx.send(ZenEvent::LogMessage(format!("examining data spirits in '{}'", display_name)));
 
-    let _permissions = match FilePermissions::backup_and_make_writable(&db_path) {
-        Ok(perms) => Some(perms),
-        Err(e) => {
-            let _ = tx.send(ZenEvent::Warning(format!("could not modify permissions for '{}': {}", display_name, e)));
+    // an empty file is what sqlite itself creates first, anything else without the header isn't ours
+    if file_size(&db_path) > 0 && !is_sqlite_file(&db_path) {
+        progress.warn(format!("'{}' is not a sqlite database - skipped", db_path.display()));
+        return Ok(0);
+    }
+    progress.log(format!("examining data spirits in '{}'", display_name));
+
+    // a dry run only reads, so permissions stay as they are
+    let permissions = match (!args.dry_run).then(|| FilePermissions::backup_and_make_writable(&db_path, &args.unlocked)) {
+        None => None,
+        Some(Ok(perms)) => Some(perms),
+        Some(Err(e)) => {
+            progress.warn(format!("could not modify permissions for '{}': {}", display_name, e));
             None
         }
     };
 
-    let conn = match Connection::open(&db_path) {
+    // the file is locked again however the clean ends, including when it finds nothing to do
+    let cleaned = clean_unlocked_database(directory, &db_path, args, progress);
+
+    if let Some(permissions) = permissions {
+        if let Err(e) = permissions.restore(&db_path) {
+            progress.warn(format!("could not restore permissions for '{}': {}", display_name, e));
+        }
+    }
+
+    cleaned
+}
+
+fn clean_unlocked_database(directory: &Path, db_path: &Path, args: &CliArgs, progress: &impl Progress) -> Result<usize> {
+    let display_name = db_path.file_name().unwrap_or_default().to_string_lossy();
+
+    // without SQLITE_OPEN_CREATE a database that vanished since the check errors instead of being recreated empty
+    let access = if args.dry_run { OpenFlags::SQLITE_OPEN_READ_ONLY } else { OpenFlags::SQLITE_OPEN_READ_WRITE };
+    let flags = access | OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    let conn = match Connection::open_with_flags(db_path, flags) {
         Ok(conn) => conn,
         Err(e) => {
             return Err(format!("failed to open database '{}': {}", display_name, e).into());
         }
     };
 
-    let count: i64 = match conn.prepare(COUNT_QUERY).and_then(|mut stmt| stmt.query_row([], |row| row.get(0))) {
+    // an editor that was just terminated can hold its lock for a moment, so wait it out before giving up
+    let delay = Duration::from_millis(args.lock_wait_ms);
+    let _ = conn.busy_timeout(delay);
+    let retry = |op: &mut dyn FnMut() -> rusqlite::Result<usize>| retry_locked(args.lock_retries, delay, op);
+    let still_locked = || format!("'{}' is still locked after {} retries - close {} manually and run again", display_name, args.lock_retries, install_name(directory));
+
+    // an editor-less or foreign database opens fine but has nothing of ours to clean
+    match retry_locked(args.lock_retries, delay, &mut || has_item_table(&conn)) {
+        Ok(true) => {}
+        Ok(false) => {
+            progress.log(format!("'{}' has no ItemTable - nothing to clean here", display_name));
+            return Ok(0);
+        }
+        Err(e) if is_locked(&e) => return Err(still_locked().into()),
+        Err(e) => {
+            return Err(format!("failed to read the schema of '{}': {}", display_name, e).into());
+        }
+    }
+
+    // rows still sitting in a -wal left by a killed editor would come back at the next checkpoint
+    if !args.dry_run {
+        match retry_locked(args.lock_retries, delay, &mut || conn.query_row("PRAGMA wal_checkpoint(TRUNCATE);", [], |row| row.get::<_, i64>(0))) {
+            Ok(0) => {}
+            Ok(_) => {
+                progress.warn(format!("could not fully checkpoint the write-ahead log of '{}' - it may still be in use", display_name));
+            }
+            Err(e) if is_locked(&e) => return Err(still_locked().into()),
+            Err(e) => {
+                progress.warn(format!("could not checkpoint '{}': {}", display_name, e));
+            }
+        }
+    }
+
+    // the patterns are bound rather than spliced in, so an --extension id can't change the query
+    let (filter, patterns) = extension_row_filter(&args.extensions);
+    let count_query = format!("SELECT COUNT(*) FROM ItemTable WHERE {};", filter);
+    let delete_query = format!("DELETE FROM ItemTable WHERE {};", filter);
+
+    let count = match retry(&mut || conn.query_row(&count_query, params_from_iter(&patterns), |row| row.get::<_, i64>(0)).map(|count| count as usize)) {
         Ok(count) => count,
+        Err(e) if is_locked(&e) => return Err(still_locked().into()),
         Err(e) => {
             return Err(format!("failed to count entries in '{}': {}", display_name, e).into());
         }
     };
 
+    if args.dry_run {
+        progress.log(format!("would delete {} matching rows from '{}'", count, display_name));
+        if args.clean_history_keys {
+            for pattern in history_key_patterns(&args.history_keys) {
+                let matching = retry(&mut || conn.query_row("SELECT COUNT(*) FROM ItemTable WHERE key LIKE ?1;", [&pattern], |row| row.get::<_, i64>(0)).map(|n| n as usize))
+                    .map_err(|e| format!("failed to count history '{}' in '{}': {}", pattern, display_name, e))?;
+                progress.log(format!("would sweep {} history entries matching '{}' from '{}'", matching, pattern, display_name));
+            }
+        }
+        return Ok(0);
+    }
+
     if count > 0 {
-        let _ = tx.send(ZenEvent::LogMessage(format!("discovered {} restless data spirits in '{}'", count, display_name)));
+        progress.log(format!("discovered {} restless data spirits in '{}'", count, display_name));
 
-        if let Err(e) = conn.execute(DELETE_QUERY, []) {
-            return Err(format!("failed to delete entries from '{}': {}", display_name, e).into());
+        match retry(&mut || conn.execute(&delete_query, params_from_iter(&patterns))) {
+            Ok(_) => {}
+            Err(e) if is_locked(&e) => return Err(still_locked().into()),
+            Err(e) => {
+                return Err(format!("failed to delete entries from '{}': {}", display_name, e).into());
+            }
         }
 
-        let _ = tx.send(ZenEvent::LogMessage(format!("peacefully guided {} data spirits to rest in '{}'", count, display_name)));
+        progress.log(format!("peacefully guided {} data spirits to rest in '{}'", count, display_name));
     } else {
-        let _ = tx.send(ZenEvent::LogMessage(format!("no restless spirits found in '{}' - already harmonious", display_name)));
+        progress.log(format!("no restless spirits found in '{}' - already harmonious", display_name));
     }
 
-    if let Some(permissions) = _permissions {
-        if let Err(e) = permissions.restore(&db_path) {
-            let _ = tx.send(ZenEvent::Warning(format!("could not restore permissions for '{}': {}", display_name, e)));
+    let mut history_cleared = 0;
+    if args.clean_history_keys {
+        for pattern in history_key_patterns(&args.history_keys) {
+            match retry(&mut || conn.execute("DELETE FROM ItemTable WHERE key LIKE ?1;", [&pattern])) {
+                Ok(removed) => history_cleared += removed,
+                Err(e) if is_locked(&e) => return Err(still_locked().into()),
+                Err(e) => {
+                    return Err(format!("failed to clear history '{}' from '{}': {}", pattern, display_name, e).into());
+                }
+            }
         }
+
+        progress.log(format!("swept {} history entries from '{}'", history_cleared, display_name));
     }
 
-    Ok(())
+    // sqlite keeps freed pages around, so the file only shrinks once it is rebuilt
+    if (count > 0 || history_cleared > 0) && !args.no_vacuum {
+        let before = file_size(db_path);
+        match retry(&mut || conn.execute("VACUUM;", [])) {
+            Ok(_) => {
+                progress.compacted(Compaction { path: db_path.display().to_string(), before, after: file_size(db_path) });
+            }
+            Err(e) => {
+                progress.warn(format!("could not vacuum '{}': {}", display_name, e));
+            }
+        }
+    }
+
+    drop(conn);
+    remove_empty_sidecars(db_path, progress);
+
+    Ok(history_cleared)
+}
+
+/// Delete the ItemTable rows whose key matches the LIKE `pattern`, returning their keys; a dry run only lists them
+pub fn clear_item_keys(db_path: &Path, pattern: &str, args: &CliArgs) -> Result<Vec<String>> {
+    if !is_sqlite_file(db_path) {
+        return Ok(Vec::new());
+    }
+
+    let permissions = if args.dry_run { None } else { Some(FilePermissions::backup_and_make_writable(db_path, &args.unlocked)?) };
+    let cleared = clear_unlocked_item_keys(db_path, pattern, args);
+    if let Some(permissions) = permissions {
+        permissions.restore(db_path)?;
+    }
+    cleared
+}
+
+fn clear_unlocked_item_keys(db_path: &Path, pattern: &str, args: &CliArgs) -> Result<Vec<String>> {
+    let display_name = db_path.display();
+    let access = if args.dry_run { OpenFlags::SQLITE_OPEN_READ_ONLY } else { OpenFlags::SQLITE_OPEN_READ_WRITE };
+    let conn = Connection::open_with_flags(db_path, access | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+        .map_err(|e| format!("failed to open database '{}': {}", display_name, e))?;
+
+    let delay = Duration::from_millis(args.lock_wait_ms);
+    let _ = conn.busy_timeout(delay);
+    if !retry_locked(args.lock_retries, delay, &mut || has_item_table(&conn)).map_err(|e| format!("failed to read the schema of '{}': {}", display_name, e))? {
+        return Ok(Vec::new());
+    }
+
+    let keys: Vec<String> = retry_locked(args.lock_retries, delay, &mut || {
+        let mut stmt = conn.prepare("SELECT key FROM ItemTable WHERE key LIKE ?1 ORDER BY key;")?;
+        let keys = stmt.query_map([pattern], |row| row.get(0))?.collect();
+        keys
+    }).map_err(|e| format!("failed to list entries in '{}': {}", display_name, e))?;
+
+    if !keys.is_empty() && !args.dry_run {
+        retry_locked(args.lock_retries, delay, &mut || conn.execute("DELETE FROM ItemTable WHERE key LIKE ?1;", [pattern]))
+            .map_err(|e| format!("failed to delete entries from '{}': {}", display_name, e))?;
+    }
+
+    Ok(keys)
+}
+
+/// Remove the -wal and -shm files left beside a database once the log holds nothing the database lacks
+fn remove_empty_sidecars(db_path: &Path, progress: &impl Progress) {
+    let sidecar = |suffix: &str| {
+        let mut name = db_path.as_os_str().to_os_string();
+        name.push(suffix);
+        std::path::PathBuf::from(name)
+    };
+    let wal = sidecar("-wal");
+    let shm = sidecar("-shm");
+
+    // a non-empty log means something else still writes to it, so neither file is ours to remove
+    if wal.exists() && file_size(&wal) > 0 {
+        progress.warn(format!("'{}' still holds unwritten changes - left in place", wal.display()));
+        return;
+    }
+
+    for path in [wal, shm] {
+        if !path.exists() { continue; }
+        match std::fs::remove_file(&path) {
+            Ok(()) => progress.log(format!("cleared leftover '{}'", path.display())),
+            Err(e) => progress.warn(format!("could not remove '{}': {}", path.display(), e)),
+        }
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn has_item_table(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'ItemTable';", [], |row| row.get::<_, i64>(0))
+        .map(|n| n > 0)
+}
+
+fn is_locked(error: &rusqlite::Error) -> bool {
+    matches!(error.sqlite_error_code(), Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked))
+}
+
+/// Run `op` again while sqlite reports the database busy or locked, up to `retries` more times
+fn retry_locked<T>(retries: u32, delay: Duration, op: &mut dyn FnMut() -> rusqlite::Result<T>) -> rusqlite::Result<T> {
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e) if is_locked(&e) && attempt < retries => {
+                attempt += 1;
+                std::thread::sleep(delay * attempt);
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::ZenEvent;
+    use clap::Parser;
+
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("cleaner-database-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn database_files_leaves_out_sidecars_and_backups() {
+        let dir = scratch_dir("variants");
+        for name in ["state.vscdb", "state.vscdb-wal", "state.vscdb-shm", "state.vscdb.old", "state.vscdb.old-journal",
+            "state.vscdb.backup_1791971550645242555", "state.vscdb.backup.backup_1700000000", "storage.json"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        assert_eq!(database_files(&dir, &[]), vec!["state.vscdb", "state.vscdb.backup", "state.vscdb.old"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_database_without_item_table_is_locked_again() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = scratch_dir("no-item-table");
+        let db = dir.join("state.vscdb");
+        Connection::open(&db).unwrap().execute("CREATE TABLE Other (key TEXT);", []).unwrap();
+        fs::set_permissions(&db, fs::Permissions::from_mode(0o444)).unwrap();
+
+        let args = CliArgs::parse_from(["cleaner"]);
+        assert_eq!(clean_database_file(&dir, "state.vscdb", &args, &crate::events::NoProgress).unwrap(), 0);
+        assert_eq!(fs::metadata(&db).unwrap().permissions().mode() & 0o777, 0o444);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_database_without_item_table_is_left_as_it_is() {
+        let dir = scratch_dir("foreign");
+        let db = dir.join("state.vscdb");
+        let conn = Connection::open(&db).unwrap();
+        conn.execute("CREATE TABLE Other (key TEXT);", []).unwrap();
+        conn.execute("INSERT INTO Other VALUES ('telemetry.machineId');", []).unwrap();
+        drop(conn);
+
+        let args = CliArgs::parse_from(["cleaner", "--no-backup"]);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        assert_eq!(clean_vscode_databases(&dir, &args, &tx).unwrap(), 0);
+
+        let mut logs = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                ZenEvent::LogMessage(message) => logs.push(message),
+                ZenEvent::Warning(_) | ZenEvent::DetailedError(_) | ZenEvent::ErrorSummary(_) => panic!("unexpected problem: {:?}", event),
+                _ => {}
+            }
+        }
+        assert!(logs.iter().any(|line| line.contains("has no ItemTable")));
+        let conn = Connection::open(&db).unwrap();
+        assert_eq!(conn.query_row("SELECT COUNT(*) FROM Other;", [], |row| row.get::<_, i64>(0)).unwrap(), 1);
+        assert!(!has_item_table(&conn).unwrap());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_stray_wal_does_not_become_a_database() {
+        let dir = scratch_dir("stray-wal");
+        fs::write(dir.join("state.vscdb-wal"), "leftover").unwrap();
+
+        let args = CliArgs::parse_from(["cleaner", "--no-backup"]);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        assert_eq!(clean_vscode_databases(&dir, &args, &tx).unwrap(), 0);
+
+        assert!(!dir.join("state.vscdb").exists());
+        assert_eq!(fs::read_to_string(dir.join("state.vscdb-wal")).unwrap(), "leftover");
+        let mut warned = false;
+        while let Ok(event) = rx.try_recv() {
+            warned |= matches!(event, ZenEvent::Warning(message) if message.contains("without its database"));
+        }
+        assert!(warned);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn extension_ids_match_literally() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE ItemTable (key TEXT);", []).unwrap();
+        for key in ["my_ext", "myXext", "my_ext.state", "myXext.state", "100%done", "100Xdone", r#"secret://{"extensionId":"my_ext","key":"token"}"#, "Augment.vscode-augment"] {
+            conn.execute("INSERT INTO ItemTable VALUES (?1);", [key]).unwrap();
+        }
+        let matching = |extensions: &[&str]| -> Vec<String> {
+            let extensions: Vec<String> = extensions.iter().map(|id| id.to_string()).collect();
+            let (filter, values) = extension_row_filter(&extensions);
+            let mut stmt = conn.prepare(&format!("SELECT key FROM ItemTable WHERE {} ORDER BY key;", filter)).unwrap();
+            stmt.query_map(params_from_iter(values.iter()), |row| row.get(0)).unwrap().map(|key| key.unwrap()).collect()
+        };
+
+        assert_eq!(matching(&["my_ext"]), vec!["my_ext", "my_ext.state", r#"secret://{"extensionId":"my_ext","key":"token"}"#]);
+        assert_eq!(matching(&["100%done"]), vec!["100%done"]);
+        // blank ids fall back to the default pattern
+        assert_eq!(matching(&[" ", ""]), vec!["Augment.vscode-augment"]);
+    }
 }
@@ -0,0 +1,288 @@
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+use crate::cli::{CliArgs, OutputFormat};
+use crate::cache::format_size;
+use crate::database::database_files;
+use crate::editors::supported_editors;
+use crate::filesystem::{discover_storage_directories, find_vscode_storage_directories};
+use crate::preflight::check_permissions;
+use crate::process::running_editors;
+use crate::report::shared_identities;
+use crate::utils::{extension_row_filter, resolve_telemetry_keys, Result, MARKER_KEY};
+
+/// Print a read-only diagnostic of every discovered storage location
+pub fn run_doctor(args: &CliArgs) {
+    let (directories, discovery_warnings) = discover_storage_directories(args, false).unwrap_or_default();
+    let (keys, warnings) = resolve_telemetry_keys(&args.telemetry_keys, &args.extra_keys, args.allow_unknown_keys, &args.preserve_keys);
+
+    for warning in warnings {
+        println!("warning: {}", warning);
+    }
+
+    println!("inspecting {} storage locations", directories.len());
+    if let Err(e) = discover_storage_directories(args, true) {
+        println!("warning: --strict-discovery would fail: {}", e);
+    }
+    for warning in discovery_warnings {
+        println!("warning: {}", warning);
+    }
+
+    for directory in &directories {
+        println!();
+        println!("{}", directory.display());
+        print_storage_json(directory, &keys, args.anonymize);
+
+        for db in database_files(directory, &args.db_files) {
+            if directory.join(&db).exists() {
+                println!("  {}: present", db);
+            }
+        }
+    }
+
+    println!();
+    let shared = shared_identities(&directories, &keys);
+    if shared.is_empty() {
+        println!("identity: every location has its own telemetry values");
+    }
+    for identity in shared {
+        println!("identity: {}", identity.describe());
+    }
+
+    for editor in supported_editors() {
+        println!("editor: {}", editor.describe());
+    }
+
+    let issues = check_permissions(&directories, !args.no_signout, &args.db_files);
+    if issues.is_empty() {
+        println!("permissions: every file to be cleaned is writable");
+    }
+    for issue in issues {
+        let verdict = if issue.fixable { "needs a permission change" } else { "blocked" };
+        println!("permissions: {} {} - {}", verdict, issue.path.display(), issue.problem);
+    }
+}
+
+/// Print a single aggregate line of telemetry keys and database rows across all storage locations
+pub fn run_count_only(args: &CliArgs) -> Result<()> {
+    let directories = find_vscode_storage_directories(args)?;
+    let (keys, _) = resolve_telemetry_keys(&args.telemetry_keys, &args.extra_keys, args.allow_unknown_keys, &args.preserve_keys);
+
+    let mut key_count = 0;
+    let mut row_count = 0;
+
+    for directory in &directories {
+        if let Some(data) = read_storage_json(directory) {
+            key_count += keys.iter().filter(|key| data.contains_key(key.as_str())).count();
+        }
+
+        for db in database_files(directory, &args.db_files) {
+            row_count += count_database_rows(&directory.join(db), &args.extensions).unwrap_or(0);
+        }
+    }
+
+    println!("{} telemetry keys set, {} database rows matching across {} storage locations", key_count, row_count, directories.len());
+    Ok(())
+}
+
+/// Print what each module's discovery finds, with sizes and how much telemetry each place holds, changing nothing
+pub fn run_list(args: &CliArgs) -> Result<()> {
+    let (keys, _) = resolve_telemetry_keys(&args.telemetry_keys, &args.extra_keys, args.allow_unknown_keys, &args.preserve_keys);
+    let groups = [
+        ("vscode and its forks (--augment)", find_vscode_storage_directories(args)?),
+        ("cursor (--cursor)", crate::cursor::find_cursor_storage_directories(args)?),
+        ("windsurf (--windsurf)", crate::windsurf::find_windsurf_storage_directories(args)?),
+        ("vscodium (--vscodium)", crate::vscodium::find_vscodium_storage_directories(args)?),
+    ];
+
+    for (label, directories) in groups {
+        println!("{}: {} storage locations", label, directories.len());
+        for directory in &directories {
+            print_listed_target(directory, &keys, args);
+        }
+        println!();
+    }
+
+    let cursor_folders = crate::cursor::find_cursor_directories();
+    println!("cursor data folders: {}", cursor_folders.len());
+    for folder in cursor_folders {
+        println!("  {}", folder.display());
+    }
+    println!();
+
+    let processes = running_editors();
+    println!("editor processes: {} running", processes.len());
+    for process in processes {
+        println!("  {}", process);
+    }
+    Ok(())
+}
+
+fn print_listed_target(directory: &Path, keys: &[String], args: &CliArgs) {
+    if directory.is_file() {
+        println!("  {} - machine id, {}", directory.display(), format_size(fs::metadata(directory).map(|m| m.len()).unwrap_or(0)));
+        return;
+    }
+
+    let storage = directory.join("storage.json");
+    let telemetry = match read_storage_json(directory) {
+        Some(data) => match keys.iter().filter(|key| data.contains_key(key.as_str())).count() {
+            0 => "no telemetry keys".to_string(),
+            present => format!("{} of {} telemetry keys", present, keys.len()),
+        },
+        None if storage.exists() => "unreadable storage.json".to_string(),
+        None => "no storage.json".to_string(),
+    };
+    println!("  {} - {}", directory.display(), telemetry);
+
+    if let Ok(metadata) = fs::metadata(&storage) {
+        println!("    storage.json {}", format_size(metadata.len()));
+    }
+    for db in database_files(directory, &args.db_files) {
+        let path = directory.join(&db);
+        let Ok(metadata) = fs::metadata(&path) else { continue; };
+        let rows = count_database_rows(&path, &args.extensions)
+            .map(|rows| format!(", {} rows to delete", rows))
+            .unwrap_or_default();
+        println!("    {} {}{}", db, format_size(metadata.len()), rows);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseSchema {
+    pub path: String,
+    pub tables: Vec<TableSchema>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TableSchema {
+    pub name: String,
+    pub kind: String,
+    pub columns: Vec<ColumnSchema>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnSchema {
+    pub name: String,
+    pub data_type: String,
+}
+
+/// Print the tables and columns of every discovered database, opened read-only
+pub fn run_dump_schema(args: &CliArgs) -> Result<()> {
+    let mut schemas = Vec::new();
+
+    for directory in find_vscode_storage_directories(args)? {
+        for db in database_files(&directory, &args.db_files) {
+            let db_path = directory.join(db);
+            if !db_path.is_file() {
+                continue;
+            }
+
+            match read_schema(&db_path) {
+                Ok(tables) => schemas.push(DatabaseSchema { path: db_path.display().to_string(), tables }),
+                Err(e) => eprintln!("warning: could not read schema of {}: {}", db_path.display(), e),
+            }
+        }
+    }
+
+    if args.format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&schemas)?);
+        return Ok(());
+    }
+
+    if schemas.is_empty() {
+        println!("no databases found");
+    }
+
+    for schema in &schemas {
+        println!("{}", schema.path);
+        for table in &schema.tables {
+            println!("  {} {}", table.kind, table.name);
+            for column in &table.columns {
+                println!("    {}: {}", column.name, column.data_type);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_schema(db_path: &Path) -> Result<Vec<TableSchema>> {
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let mut stmt = conn.prepare("SELECT name, type FROM sqlite_master WHERE type IN ('table', 'view') ORDER BY name;")?;
+    let entries: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut tables = Vec::new();
+    for (name, kind) in entries {
+        let mut info = conn.prepare("SELECT name, type FROM pragma_table_info(?1);")?;
+        let columns = info
+            .query_map([&name], |row| Ok(ColumnSchema { name: row.get(0)?, data_type: row.get(1)? }))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        tables.push(TableSchema { name, kind, columns });
+    }
+
+    Ok(tables)
+}
+
+pub(crate) fn read_storage_json(directory: &Path) -> Option<Map<String, Value>> {
+    let content = fs::read_to_string(directory.join("storage.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Rows a clean would delete from `db_path` for the given `--extension` ids, none when it can't be read
+pub(crate) fn count_database_rows(db_path: &Path, extensions: &[String]) -> Option<i64> {
+    if !db_path.exists() {
+        return None;
+    }
+
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY).ok()?;
+    let (filter, patterns) = extension_row_filter(extensions);
+    conn.query_row(&format!("SELECT COUNT(*) FROM ItemTable WHERE {};", filter), rusqlite::params_from_iter(&patterns), |row| row.get(0)).ok()
+}
+
+fn print_storage_json(directory: &Path, keys: &[String], anonymize: bool) {
+    let storage_path = directory.join("storage.json");
+    if !storage_path.exists() {
+        return;
+    }
+
+    let data: Map<String, Value> = match fs::read_to_string(&storage_path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+    {
+        Ok(data) => data,
+        Err(e) => {
+            println!("  storage.json: unreadable ({})", e);
+            return;
+        }
+    };
+
+    println!("  storage.json: present");
+    if let Some(marked) = data.get(MARKER_KEY) {
+        println!("    last cleaned: {} (unix time, from --mark)", marked);
+    }
+    for key in keys {
+        match data.get(key).and_then(|v| v.as_str()) {
+            Some(value) => println!("    {}: {}", key, mask_value(value, anonymize)),
+            None => println!("    {}: not set", key),
+        }
+    }
+}
+
+/// Mask an identifier down to its first 6 and last 4 characters, or entirely when anonymizing
+pub fn mask_value(value: &str, anonymize: bool) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if anonymize || chars.len() <= 10 {
+        return "********".to_string();
+    }
+
+    let head: String = chars[..6].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}…{}", head, tail)
+}
@@ -0,0 +1,142 @@
+use serde::Serialize;
+use std::path::PathBuf;
+use crate::backup::{backup_directories, snapshot_before_terminate};
+use crate::events::{KeepProblems, Progress};
+use crate::filesystem::{choose_directories, find_editor_storage_directories, for_each_directory};
+use crate::process::{grace_period, report_respawns, terminate_named_processes};
+use crate::storage::ChangedKeys;
+use crate::utils::{Result, ErrorCollector};
+
+/// What cleaning one of the editors without a module of its own, such as Windsurf or VSCodium, found and did
+#[derive(Debug, Clone, Serialize)]
+pub struct EditorCleaningResult {
+    pub processes_terminated: Vec<String>,
+    pub directories_found: Vec<PathBuf>,
+    pub config_updated: bool,
+    pub backup_created: Option<PathBuf>,
+    pub history_entries_cleared: usize,
+    /// Storage keys given new values, per storage directory
+    pub keys_changed: ChangedKeys,
+    /// State databases rebuilt after rows were deleted, with their sizes
+    pub compacted: Vec<crate::database::Compaction>,
+    pub errors: ErrorCollector,
+}
+
+impl Default for EditorCleaningResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditorCleaningResult {
+    pub fn new() -> Self {
+        Self {
+            processes_terminated: Vec::new(),
+            directories_found: Vec::new(),
+            config_updated: false,
+            backup_created: None,
+            history_entries_cleared: 0,
+            keys_changed: ChangedKeys::new(),
+            compacted: Vec::new(),
+            errors: ErrorCollector::new(),
+        }
+    }
+}
+
+/// Clean the editor whose data lives in `<config dir>/<name>/User` the way VSCode is cleaned, stopping the processes in `processes` first
+pub async fn clean_editor(name: &str, processes: &[&str], args: &crate::cli::CliArgs, progress: &(impl Progress + Sync)) -> Result<EditorCleaningResult> {
+    let mut result = EditorCleaningResult::new();
+
+    // Step 1: Find the editor's storage directories
+    let (storage_dirs, _) = choose_directories(find_editor_storage_directories(args, name)?, args);
+    result.directories_found = storage_dirs.clone();
+
+    // Step 2: Back up only the files this run is about to rewrite, before the kill when asked to
+    let snapshot = snapshot_before_terminate(args);
+    let backup = |result: &mut EditorCleaningResult| {
+        if args.dry_run {
+            return;
+        }
+        let (backed_up, backup_warnings) = backup_directories(&storage_dirs, !args.no_signout, &args.db_files, args.backup_jobs, &args.backups);
+        result.backup_created = backed_up.first().map(|file| file.backup.clone());
+        result.errors.extend(backup_warnings);
+    };
+    if snapshot {
+        backup(&mut result);
+    }
+
+    // Step 3: Terminate the editor's processes (only if not disabled by no_terminate, a dry run leaves them running)
+    if !args.no_terminate && !args.dry_run {
+        let names: Vec<String> = processes.iter().map(|n| n.to_string()).collect();
+        let terminated = terminate_named_processes(&names, grace_period(args));
+        if !terminated.is_empty() {
+            report_respawns(&terminated, args, &mut result.errors);
+            result.processes_terminated.push(name.to_string());
+        }
+        progress.advance(1);
+    }
+
+    // otherwise back up once the editor has let go of the files
+    if !snapshot {
+        backup(&mut result);
+    }
+
+    // Step 4: Update storage files
+    let progress = KeepProblems::new(progress);
+    let outcomes = for_each_directory(&storage_dirs, &args.pause, |directory| {
+        let outcome = crate::storage::update_vscode_storage(directory, args, &progress).ok();
+        progress.advance(1);
+        outcome
+    });
+    for (directory, outcome) in storage_dirs.iter().zip(outcomes) {
+        // a failure's details reach result.errors through progress
+        if let Some(changed) = outcome {
+            result.config_updated = true;
+            if !changed.is_empty() {
+                result.keys_changed.insert(directory.display().to_string(), changed);
+            }
+        }
+    }
+
+    // Step 5: Clean databases (only if not disabled by no_signout)
+    if !args.no_signout {
+        let outcomes = for_each_directory(&storage_dirs, &args.pause, |directory| {
+            let outcome = crate::database::clean_vscode_databases(directory, args, &progress).ok();
+            progress.advance(1);
+            outcome
+        });
+        result.history_entries_cleared += outcomes.into_iter().flatten().sum::<usize>();
+    }
+
+    result.errors.extend(progress.take());
+    result.compacted = progress.take_compactions();
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn a_dry_run_writes_nothing() {
+        let config = std::env::temp_dir().join(format!("cleaner-editor-clean-dry-run-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&config);
+        let global = config.join("Windsurf").join("User").join("globalStorage");
+        std::fs::create_dir_all(&global).unwrap();
+        std::fs::write(global.join("storage.json"), "{\"telemetry.machineId\": \"old\"}").unwrap();
+        rusqlite::Connection::open(global.join("state.vscdb")).unwrap()
+            .execute("CREATE TABLE ItemTable (key TEXT, value BLOB);", []).unwrap();
+
+        let mut args = crate::cli::CliArgs::parse_from(["cleaner", "--dry-run"]);
+        args.discovery_roots = Some(crate::filesystem::DiscoveryRoots { config_dirs: vec![config.clone()], ..Default::default() });
+        let result = clean_editor("Windsurf", &["cleaner-no-such-editor"], &args, &crate::events::NoProgress).await.unwrap();
+
+        assert_eq!(result.directories_found, vec![global.clone()]);
+        assert!(result.backup_created.is_none());
+        assert!(!crate::backup::backup_dir_for(&global).exists());
+        assert_eq!(std::fs::read_to_string(global.join("storage.json")).unwrap(), "{\"telemetry.machineId\": \"old\"}");
+        let _ = std::fs::remove_dir_all(&config);
+    }
+}
@@ -0,0 +1,48 @@
+use serde::Serialize;
+
+/// Where every editor keeps its storage, relative to its data folder
+const STORAGE_SUBPATHS: &[&str] = &["User/globalStorage", "User/workspaceStorage", "machineid"];
+
+/// What the cleaner knows about one editor
+#[derive(Debug, Clone, Serialize)]
+pub struct EditorInfo {
+    pub name: &'static str,
+    /// Folder under the config dir holding the editor's data, e.g. "Cursor" for ~/.config/Cursor
+    pub data_folder: &'static str,
+    /// Process names of the editor and its helpers, lowercased without .exe
+    pub processes: &'static [&'static str],
+    pub storage_subpaths: &'static [&'static str],
+    /// The flag of the cleaning module written for this editor, None for editors only reached by the shared discovery pass
+    pub module: Option<&'static str>,
+}
+
+const EDITORS: [EditorInfo; 7] = [
+    EditorInfo { name: "Visual Studio Code", data_folder: "Code", processes: &["code"], storage_subpaths: STORAGE_SUBPATHS, module: Some("--augment") },
+    EditorInfo { name: "Visual Studio Code Insiders", data_folder: "Code - Insiders", processes: &["code-insiders"], storage_subpaths: STORAGE_SUBPATHS, module: None },
+    EditorInfo { name: "Code - OSS", data_folder: "Code - OSS", processes: &["code-oss"], storage_subpaths: STORAGE_SUBPATHS, module: None },
+    EditorInfo { name: "Cursor", data_folder: "Cursor", processes: &["cursor"], storage_subpaths: STORAGE_SUBPATHS, module: Some("--cursor") },
+    EditorInfo { name: "Windsurf", data_folder: "Windsurf", processes: &["windsurf"], storage_subpaths: STORAGE_SUBPATHS, module: Some("--windsurf") },
+    EditorInfo { name: "Trae", data_folder: "Trae", processes: &["trae"], storage_subpaths: STORAGE_SUBPATHS, module: None },
+    EditorInfo { name: "VSCodium", data_folder: "VSCodium", processes: &["codium", "vscodium"], storage_subpaths: STORAGE_SUBPATHS, module: Some("--vscodium") },
+];
+
+/// Every editor the cleaner supports, in the order they are listed to the user
+pub fn supported_editors() -> Vec<EditorInfo> {
+    EDITORS.to_vec()
+}
+
+/// Every editor process name, lowercased without .exe
+pub fn editor_process_names() -> impl Iterator<Item = &'static str> {
+    EDITORS.iter().flat_map(|editor| editor.processes.iter().copied())
+}
+
+impl EditorInfo {
+    /// One line for the support matrix, e.g. "Cursor - cleaned by --cursor, runs as cursor"
+    pub fn describe(&self) -> String {
+        let cleaned_by = match self.module {
+            Some(flag) => format!("cleaned by {}", flag),
+            None => "cleaned with the other discovered editors by --augment".to_string(),
+        };
+        format!("{} - {}, runs as {}, data in {}/{{{}}}", self.name, cleaned_by, self.processes.join(", "), self.data_folder, self.storage_subpaths.join(","))
+    }
+}
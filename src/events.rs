@@ -0,0 +1,195 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Progress events sent from the cleaning operations to the interface
+#[derive(Debug, Clone)]
+pub enum ZenEvent {
+    StartScanning,
+    /// A new part of the run begins, e.g. "augment" or "cache", used to group the log
+    PhaseStarted(String),
+    ProcessFound(ProcessStone),
+    LocationFound(String),
+    ProcessTerminated(String),
+    StorageUpdated(String),
+    DatabaseCleaned(String),
+    /// Storage keys rewritten in a directory, as (key, old, new)
+    KeysChanged(String, Vec<crate::storage::KeyDiff>),
+    OperationComplete,
+    Error(String),
+    DetailedError(crate::utils::CleanerError),
+    Warning(String),
+    LogMessage(String),
+    SetTotalOperations(usize),
+    /// Steps finished since the last update, counted against `SetTotalOperations`
+    OperationsAdvanced(usize),
+    ErrorSummary(crate::utils::ErrorCollector),
+    NextSteps(Vec<String>),
+    RunSummary(Vec<String>),
+    RunTotals(crate::report::RunTotals),
+}
+
+/// Where library functions report what they are doing, so they can run without the interface
+pub trait Progress {
+    fn log(&self, message: String);
+    fn warn(&self, message: String);
+
+    /// A failure the caller carries on past, reported as a warning unless the sink keeps the details
+    fn error(&self, error: crate::utils::CleanerError) {
+        self.warn(error.to_string());
+    }
+
+    /// Every error one call collected, reported once it gives up
+    fn error_summary(&self, _errors: crate::utils::ErrorCollector) {}
+
+    /// A database rebuilt after rows were deleted, logged unless the sink keeps it for a result
+    fn compacted(&self, compaction: crate::database::Compaction) {
+        self.log(compaction.describe());
+    }
+
+    /// `steps` more of the counted steps are done, for sinks that show how far along the run is
+    fn advance(&self, _steps: usize) {}
+}
+
+impl Progress for tokio::sync::mpsc::UnboundedSender<ZenEvent> {
+    fn log(&self, message: String) {
+        let _ = self.send(ZenEvent::LogMessage(message));
+    }
+
+    fn warn(&self, message: String) {
+        let _ = self.send(ZenEvent::Warning(message));
+    }
+
+    fn error(&self, error: crate::utils::CleanerError) {
+        let _ = self.send(ZenEvent::DetailedError(error));
+    }
+
+    fn error_summary(&self, errors: crate::utils::ErrorCollector) {
+        let _ = self.send(ZenEvent::ErrorSummary(errors));
+    }
+
+    fn advance(&self, steps: usize) {
+        let _ = self.send(ZenEvent::OperationsAdvanced(steps));
+    }
+}
+
+/// Progress that is thrown away, for callers that only want the returned result
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn log(&self, _message: String) {}
+    fn warn(&self, _message: String) {}
+}
+
+/// Set by the interface to hold the cleaning operations at the next directory until it is cleared, or to stop them there for good
+#[derive(Debug, Clone, Default)]
+pub struct PauseFlag {
+    paused: Arc<AtomicBool>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl PauseFlag {
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed) && !self.is_cancelled()
+    }
+
+    pub fn set(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Flip the flag, returning whether the operations are now paused
+    pub fn toggle(&self) -> bool {
+        !self.paused.fetch_xor(true, Ordering::Relaxed)
+    }
+
+    /// Stop the operations at their next directory, releasing them if they are paused
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Block the calling thread while paused, for the synchronous per-directory loops
+    pub fn wait(&self) {
+        while self.is_paused() {
+            std::thread::sleep(PAUSE_POLL);
+        }
+    }
+
+    /// Wait while paused without holding up the runtime
+    pub async fn resumed(&self) {
+        while self.is_paused() {
+            tokio::time::sleep(PAUSE_POLL).await;
+        }
+    }
+}
+
+const PAUSE_POLL: Duration = Duration::from_millis(100);
+
+/// Progress that keeps the warnings, errors and compactions and drops the log lines, for the module flows that report through their results.
+///
+/// Finished steps still reach the caller's progress, so its gauge follows the module directory by directory.
+pub struct KeepProblems<'a, P> {
+    problems: std::sync::Mutex<crate::utils::ErrorCollector>,
+    compactions: std::sync::Mutex<Vec<crate::database::Compaction>>,
+    caller: &'a P,
+}
+
+impl<'a, P: Progress> KeepProblems<'a, P> {
+    pub fn new(caller: &'a P) -> Self {
+        Self { problems: Default::default(), compactions: Default::default(), caller }
+    }
+
+    pub fn take(&self) -> crate::utils::ErrorCollector {
+        self.problems.lock().map(|mut problems| std::mem::take(&mut *problems)).unwrap_or_default()
+    }
+
+    pub fn take_compactions(&self) -> Vec<crate::database::Compaction> {
+        self.compactions.lock().map(|mut compactions| std::mem::take(&mut *compactions)).unwrap_or_default()
+    }
+}
+
+impl<P: Progress> Progress for KeepProblems<'_, P> {
+    fn log(&self, _message: String) {}
+
+    fn warn(&self, message: String) {
+        if let Ok(mut problems) = self.problems.lock() {
+            problems.add_warning(message);
+        }
+    }
+
+    fn error(&self, error: crate::utils::CleanerError) {
+        if let Ok(mut problems) = self.problems.lock() {
+            problems.add_error(error);
+        }
+    }
+
+    fn compacted(&self, compaction: crate::database::Compaction) {
+        if let Ok(mut compactions) = self.compactions.lock() {
+            compactions.push(compaction);
+        }
+    }
+
+    fn advance(&self, steps: usize) {
+        self.caller.advance(steps);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessStone {
+    pub name: String,
+    pub pid: u32,
+    pub path: String,
+    pub is_selected: bool,
+    pub is_terminated: bool,
+    /// Helper processes killed along with this one, as (depth, label) from the discovery snapshot
+    pub children: Vec<(usize, String)>,
+}
+
+impl std::fmt::Display for ProcessStone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.pid)
+    }
+}
@@ -0,0 +1,65 @@
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::backup::backup_files;
+use crate::product::find_products;
+use crate::storage::{FilePermissions, UnlockLedger};
+use crate::utils::{ErrorCollector, Result};
+
+/// Per-extension metadata fields that fingerprint an install without being needed to load the extension
+pub const EXTENSION_METADATA_FIELDS: [&str; 1] = ["installedTimestamp"];
+
+const EXTENSION_HOMES: [&str; 5] = [".vscode", ".vscode-insiders", ".vscode-oss", ".cursor", ".windsurf"];
+
+/// Every extensions.json manifest under the known editor homes
+pub fn find_extensions_manifests() -> Vec<PathBuf> {
+    let Some(home) = dirs::home_dir() else { return Vec::new(); };
+
+    // forks name their own home folder in product.json, the hardcoded list covers installs we can't see
+    let mut folders: Vec<String> = EXTENSION_HOMES.iter().map(|dir| dir.to_string()).collect();
+    for folder in find_products().into_iter().filter_map(|product| product.data_folder_name) {
+        if !folders.contains(&folder) {
+            folders.push(folder);
+        }
+    }
+
+    folders.iter()
+        .map(|dir| home.join(dir).join("extensions").join("extensions.json"))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Count the identifying fields a sanitize pass would strip from a manifest
+pub fn count_identifying_fields(manifest: &Path) -> Result<usize> {
+    let mut entries: Vec<Value> = serde_json::from_str(&fs::read_to_string(manifest)?)?;
+    Ok(strip_identifying_fields(&mut entries))
+}
+
+/// Back up the manifest, then strip identifying metadata while keeping the extension list intact
+pub fn sanitize_extensions_json(manifest: &Path, unlocked: &UnlockLedger, warnings: &mut ErrorCollector) -> Result<usize> {
+    let content = fs::read_to_string(manifest)?;
+    let mut entries: Vec<Value> = serde_json::from_str(&content)
+        .map_err(|e| format!("extensions.json is not a list of extensions: {}", e))?;
+
+    let stripped = strip_identifying_fields(&mut entries);
+    if stripped == 0 {
+        return Ok(0);
+    }
+
+    // keep the backup beside the extensions folder so the editor doesn't mistake it for an extension
+    let install_dir = manifest.parent().and_then(Path::parent).unwrap_or(manifest);
+    backup_files(install_dir, &[manifest.to_path_buf()], warnings)?;
+
+    let permissions = FilePermissions::backup_and_make_writable(manifest, unlocked)?;
+    fs::write(manifest, serde_json::to_string(&entries)?)?;
+    permissions.restore(manifest)?;
+
+    Ok(stripped)
+}
+
+fn strip_identifying_fields(entries: &mut [Value]) -> usize {
+    entries.iter_mut()
+        .filter_map(|entry| entry.get_mut("metadata").and_then(Value::as_object_mut))
+        .map(|metadata| EXTENSION_METADATA_FIELDS.iter().filter(|field| metadata.remove(**field).is_some()).count())
+        .sum()
+}
@@ -1,32 +1,150 @@
 use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
-use crate::utils::MACHINE_ID;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use crate::product::find_products;
+use crate::backup::BACKUP_DIR_NAME;
+use crate::cli::CliArgs;
+use crate::utils::{Result, MACHINE_ID, MARKER_KEY};
 
-pub fn find_vscode_storage_directories() -> Vec<PathBuf> {
-    let mut seen = HashSet::new();
-    let mut base_dirs = vec![dirs::config_dir(), dirs::home_dir(), dirs::data_dir()];
+/// How many storage directories a module updates and cleans at once
+const DIRECTORY_JOBS: usize = 4;
+
+/// Folder name used for quarantined files, skipped by discovery like the backups folder
+pub const QUARANTINE_DIR_NAME: &str = "cleaner_quarantine";
+
+/// Suffix of the file in a backups folder recording when a limited run last cleaned the target beside it
+const JOURNAL_SUFFIX: &str = ".cleaned_at";
+
+/// Where WSL mounts the Windows users' profile folders by default
+const WSL_WINDOWS_USERS: &str = "/mnt/c/Users";
+
+/// Warnings for the --path directories that aren't directories, which discovery leaves out
+pub fn check_search_paths(paths: &[PathBuf]) -> Vec<String> {
+    paths.iter()
+        .filter(|path| !path.is_dir())
+        .map(|path| format!("--path '{}' is not a directory - ignored", path.display()))
+        .collect()
+}
+
+/// Whether this is running under WSL, whose kernel version names microsoft
+pub fn is_wsl() -> bool {
+    fs::read_to_string("/proc/version").is_ok_and(|version| version.to_lowercase().contains("microsoft"))
+}
+
+/// The profile folder of every Windows user, e.g. /mnt/c/Users/alex, as seen from WSL
+pub fn wsl_windows_homes() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(WSL_WINDOWS_USERS) else { return Vec::new(); };
+
+    entries.filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
+        .filter(|entry| !["Public", "Default", "Default User", "All Users"].iter().any(|name| entry.file_name() == *name))
+        .map(|entry| entry.path())
+        .collect()
+}
+
+/// Every storage directory discovery finds for `args`, failing on unreadable or unresolvable directories with --strict-discovery
+pub fn find_vscode_storage_directories(args: &CliArgs) -> Result<Vec<PathBuf>> {
+    discover_storage_directories(args, args.strict_discovery).map(|(directories, _)| directories)
+}
 
-    if let Some(home) = dirs::home_dir() {
-        base_dirs.push(Some(home.join(".vscode")));
+/// Where discovery looks for editor data, so it can be pointed at a fixture tree instead of this machine
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryRoots {
+    /// Folders holding editor data directories, scanned one level down, e.g. ~/.config holding Code and Cursor
+    pub base_dirs: Vec<PathBuf>,
+    /// Editor data directories scanned directly, e.g. a portable install
+    pub editor_dirs: Vec<PathBuf>,
+    /// Config dirs the per-editor modules look in for a data folder of their editor's name, e.g. ~/.config/Windsurf
+    pub config_dirs: Vec<PathBuf>,
+    /// The --path directories, each searched as a config dir or as an editor's own data directory
+    pub search_paths: Vec<PathBuf>,
+}
 
-        #[cfg(target_os = "linux")]
-        {
-            base_dirs.push(Some(home.join("snap/code/common/.config")));
-            base_dirs.push(Some(home.join(".var/app/com.visualstudio.code/config")));
-            base_dirs.push(Some(home.join(".var/app/com.visualstudio.code-insiders/config")));
+impl DiscoveryRoots {
+    /// The usual config, home and data locations of this machine, plus --wsl-windows, --path and product.json installs
+    pub fn system(args: &CliArgs) -> Self {
+        let mut base_dirs: Vec<PathBuf> = [dirs::config_dir(), dirs::home_dir(), dirs::data_dir()].into_iter().flatten().collect();
+        let mut config_dirs: Vec<PathBuf> = dirs::config_dir().into_iter().collect();
+
+        if let Some(home) = dirs::home_dir() {
+            base_dirs.push(home.join(".vscode"));
+
+            #[cfg(target_os = "linux")]
+            {
+                base_dirs.push(home.join("snap/code/common/.config"));
+                base_dirs.push(home.join(".var/app/com.visualstudio.code/config"));
+                base_dirs.push(home.join(".var/app/com.visualstudio.code-insiders/config"));
+            }
+
+            #[cfg(target_os = "windows")]
+            {
+                // onedrive known folder move can put appdata somewhere dirs doesn't report
+                let redirected = redirected_known_folders();
+                base_dirs.extend(redirected.iter().cloned());
+                config_dirs.extend(redirected);
+            }
+
+            #[cfg(target_os = "macos")]
+            {
+                if let Some(app_support) = dirs::config_dir() {
+                    base_dirs.push(app_support.join("Code - Insiders"));
+                    base_dirs.push(app_support.join("Cursor"));
+                    base_dirs.push(app_support.join("VSCodium"));
+                }
+            }
         }
 
-        #[cfg(target_os = "macos")]
-        {
-            if let Some(app_support) = dirs::config_dir() {
-                base_dirs.push(Some(app_support.join("Code - Insiders")));
-                base_dirs.push(Some(app_support.join("Cursor")));
-                base_dirs.push(Some(app_support.join("VSCodium")));
+        // the windows side of a wsl machine, scanned like its own config and home directories
+        if args.wsl_windows && is_wsl() {
+            for home in wsl_windows_homes() {
+                let appdata = home.join("AppData").join("Roaming");
+                if appdata.is_dir() {
+                    config_dirs.push(appdata.clone());
+                }
+                base_dirs.push(appdata);
+                base_dirs.push(home);
             }
         }
+
+        // a --path may be a config dir holding several editors or one editor's own data directory
+        let search_paths: Vec<PathBuf> = args.paths.iter().filter(|path| path.is_dir()).cloned().collect();
+        let mut editor_dirs = search_paths.clone();
+        base_dirs.extend(editor_dirs.iter().cloned());
+
+        // product.json names the folders of rebranded forks and points at portable installs
+        for product in find_products() {
+            if let Some(config) = dirs::config_dir() {
+                editor_dirs.push(config.join(&product.name_short));
+            }
+            editor_dirs.push(product.install_dir);
+        }
+
+        Self { base_dirs, editor_dirs, config_dirs, search_paths }
     }
 
+    /// The roots a caller set on `args`, e.g. a test's fixture tree, or else this machine's
+    pub fn of(args: &CliArgs) -> Self {
+        args.discovery_roots.clone().unwrap_or_else(|| Self::system(args))
+    }
+}
+
+/// Discover storage directories and the problems worked around on the way, treating unreadable or unresolvable base directories as errors when `strict`
+pub fn discover_storage_directories(args: &CliArgs, strict: bool) -> Result<(Vec<PathBuf>, Vec<String>)> {
+    let unresolved = [dirs::config_dir(), dirs::home_dir(), dirs::data_dir()].iter().any(|dir| dir.is_none());
+    if strict && args.discovery_roots.is_none() && unresolved {
+        return Err("could not resolve the config, home or data directory".into());
+    }
+
+    discover_storage_directories_in(&DiscoveryRoots::of(args), strict)
+}
+
+/// Global and workspace storage under `roots`, in the order found and without duplicates or cleaner artifacts,
+/// with a warning for each problem discovery worked around
+pub fn discover_storage_directories_in(roots: &DiscoveryRoots, strict: bool) -> Result<(Vec<PathBuf>, Vec<String>)> {
+    // the data/ patterns also pick up remote servers under home, e.g. ~/.vscode-server and ~/.vscode-server-insiders
     let global_patterns = [
         &["User", "globalStorage"] as &[&str],
         &["data", "User", "globalStorage"],
@@ -39,47 +157,642 @@ pub fn find_vscode_storage_directories() -> Vec<PathBuf> {
         &["data", "User", "workspaceStorage"],
     ];
 
-    base_dirs
-        .into_iter()
-        .filter_map(|dir| dir)
-        .flat_map(|base| scan_storage(&base, &global_patterns, &workspace_patterns))
-        .filter(|path| path.exists() && seen.insert(path.clone()))
-        .collect()
+    let mut candidates = Vec::new();
+    for base in &roots.base_dirs {
+        candidates.extend(scan_storage(base, &global_patterns, &workspace_patterns, strict)?);
+    }
+    for editor in &roots.editor_dirs {
+        candidates.extend(storage_paths_in(editor, &global_patterns, &workspace_patterns, strict)?);
+    }
+
+    // the same folder reached through a symlink or a .. is cleaned once, under the first path it was found by
+    let mut seen = HashSet::new();
+    let mut directories = Vec::new();
+    let mut warnings = Vec::new();
+    for path in candidates {
+        if !path.exists() || is_cleaner_artifact(&path) {
+            continue;
+        }
+        let resolved = match fs::canonicalize(&path) {
+            Ok(resolved) => resolved,
+            Err(e) if strict => return Err(format!("could not resolve {}: {}", path.display(), e).into()),
+            Err(e) => {
+                warnings.push(format!("could not resolve {} ({}) - duplicates of it may be cleaned twice", path.display(), e));
+                path.clone()
+            }
+        };
+        if seen.insert(resolved) {
+            directories.push(path);
+        }
+    }
+
+    Ok((directories, warnings))
+}
+
+/// The storage of the editor named `app_name` for `args`, failing on unreadable or unresolvable directories with --strict-discovery
+pub fn find_editor_storage_directories(args: &CliArgs, app_name: &str) -> Result<Vec<PathBuf>> {
+    if args.strict_discovery && args.discovery_roots.is_none() && dirs::config_dir().is_none() {
+        return Err("could not resolve the config directory".into());
+    }
+    editor_storage_directories(&DiscoveryRoots::of(args), app_name, args.strict_discovery)
+}
+
+/// Global storage and every workspace storage folder of the editor whose data lives in `<config dir>/<app_name>`
+pub fn editor_storage_directories(roots: &DiscoveryRoots, app_name: &str, strict: bool) -> Result<Vec<PathBuf>> {
+    // the config dir is %APPDATA%, ~/Library/Application Support or ~/.config depending on the platform
+    let mut directories = Vec::new();
+    for dir in &roots.config_dirs {
+        directories.extend(user_storage_directories(&dir.join(app_name).join("User"), strict)?);
+    }
+
+    for directory in search_path_storage_directories(roots, app_name, strict)? {
+        if !directories.contains(&directory) {
+            directories.push(directory);
+        }
+    }
+    Ok(directories)
+}
+
+/// Storage of the editor named `app_name` under the --path directories, found as a config dir or as the editor's own data directory
+pub fn search_path_storage_directories(roots: &DiscoveryRoots, app_name: &str, strict: bool) -> Result<Vec<PathBuf>> {
+    let mut directories = Vec::new();
+    for path in &roots.search_paths {
+        let mut users = vec![path.join(app_name).join("User")];
+        // a portable install keeps its User folder under data/
+        if path.file_name().is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case(app_name)) {
+            users.push(path.join("User"));
+            users.push(path.join("data").join("User"));
+        }
+        for user in users {
+            directories.extend(user_storage_directories(&user, strict)?);
+        }
+    }
+    Ok(directories)
+}
+
+/// Global storage and every workspace storage folder inside an editor's `User` folder, failing on an unreadable one when `strict`
+pub fn user_storage_directories(user: &Path, strict: bool) -> Result<Vec<PathBuf>> {
+    let mut directories = Vec::new();
+    let global = user.join("globalStorage");
+    if global.exists() {
+        directories.push(global);
+    }
+
+    if let Some(entries) = read_dir_checked(&user.join("workspaceStorage"), strict)? {
+        directories.extend(entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
+            .map(|entry| entry.path())
+            .filter(|path| !is_cleaner_artifact(path)));
+    }
+
+    Ok(directories)
+}
+
+/// AppData folders redirected in the registry, e.g. into OneDrive, that differ from the usual locations
+#[cfg(target_os = "windows")]
+pub fn redirected_known_folders() -> Vec<PathBuf> {
+    let output = std::process::Command::new("reg")
+        .args(["query", r"HKCU\Software\Microsoft\Windows\CurrentVersion\Explorer\User Shell Folders"])
+        .output();
+    let Ok(output) = output else { return Vec::new(); };
+
+    let usual: Vec<PathBuf> = [dirs::config_dir(), dirs::data_local_dir()].into_iter().flatten().collect();
+    shell_folders_from_reg(&output.stdout, &usual)
+}
+
+/// The AppData folders named in `reg query` output of the User Shell Folders key, expanded, leaving out the `usual` ones
+#[cfg(any(target_os = "windows", test))]
+fn shell_folders_from_reg(stdout: &[u8], usual: &[PathBuf]) -> Vec<PathBuf> {
+    let mut folders = Vec::new();
+
+    // lines look like "    AppData    REG_EXPAND_SZ    %USERPROFILE%\OneDrive\AppData\Roaming"
+    for line in stdout.split(|byte| *byte == b'\n') {
+        // a line that doesn't decode would name the wrong folder once mangled, so it is skipped rather than guessed at
+        let Ok(line) = std::str::from_utf8(line) else { continue; };
+        let line = line.trim();
+        let Some(value) = ["AppData", "Local AppData"].iter().find_map(|name| {
+            let rest = line.strip_prefix(name)?.trim_start();
+            let rest = rest.strip_prefix("REG_EXPAND_SZ").or_else(|| rest.strip_prefix("REG_SZ"))?;
+            Some(rest.trim())
+        }) else { continue; };
+
+        let folder = PathBuf::from(expand_env_vars(value));
+        if !usual.contains(&folder) && !folders.contains(&folder) {
+            folders.push(folder);
+        }
+    }
+
+    folders
+}
+
+/// Expand %VAR% references the way the registry's expandable strings expect, keeping non-unicode values as they are
+#[cfg(any(target_os = "windows", test))]
+fn expand_env_vars(value: &str) -> std::ffi::OsString {
+    let mut expanded = std::ffi::OsString::new();
+    let mut parts = value.split('%');
+    expanded.push(parts.next().unwrap_or_default());
+
+    while let Some(name) = parts.next() {
+        match (std::env::var_os(name).filter(|_| !name.is_empty()), parts.next()) {
+            (Some(var), Some(rest)) => {
+                expanded.push(var);
+                expanded.push(rest);
+            }
+            (_, rest) => {
+                expanded.push("%");
+                expanded.push(name);
+                if let Some(rest) = rest {
+                    expanded.push("%");
+                    expanded.push(rest);
+                }
+            }
+        }
+    }
+
+    expanded
+}
+
+/// Whether `path` is a backups or quarantine folder this tool created, which must never be cleaned itself
+pub fn is_cleaner_artifact(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == BACKUP_DIR_NAME || name == QUARANTINE_DIR_NAME)
+}
+
+/// Read a directory, tolerating failures unless `strict`; a missing directory is never an error
+fn read_dir_checked(dir: &Path, strict: bool) -> Result<Option<fs::ReadDir>> {
+    match fs::read_dir(dir) {
+        Ok(entries) => Ok(Some(entries)),
+        Err(e) if strict && e.kind() != io::ErrorKind::NotFound => {
+            Err(format!("could not read '{}': {}", dir.display(), e).into())
+        }
+        Err(_) => Ok(None),
+    }
 }
 
 /// Scan a directory for VSCode storage using the provided patterns
 fn scan_storage(
-    base_dir: &PathBuf,
+    base_dir: &Path,
     global_patterns: &[&[&str]],
     workspace_patterns: &[&[&str]],
-) -> Vec<PathBuf> {
-    let Ok(entries) = fs::read_dir(base_dir) else { return Vec::new(); };
+    strict: bool,
+) -> Result<Vec<PathBuf>> {
+    let Some(entries) = read_dir_checked(base_dir, strict)? else { return Ok(Vec::new()); };
 
-    entries
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
-        .flat_map(|entry| {
-            let path = entry.path();
-
-            let global_paths: Vec<PathBuf> = global_patterns.iter()
-                .map(|pattern| pattern.iter().fold(path.clone(), |p, seg| p.join(seg)))
-                .collect();
-
-            let workspace_paths: Vec<PathBuf> = workspace_patterns.iter()
-                .flat_map(|pattern| {
-                    let workspace_base = pattern.iter().fold(path.clone(), |p, seg| p.join(seg));
-                    if !workspace_base.exists() { return Vec::new(); }
-
-                    let Ok(entries) = fs::read_dir(&workspace_base) else { return Vec::new(); };
-                    entries
-                        .filter_map(|entry| entry.ok())
-                        .filter(|entry| entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
-                        .map(|entry| entry.path())
-                        .collect()
-                })
-                .collect();
-
-            global_paths.into_iter().chain(workspace_paths)
-        })
-        .collect()
+    let mut paths = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        if !entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false) || is_cleaner_artifact(&entry.path()) {
+            continue;
+        }
+        paths.extend(storage_paths_in(&entry.path(), global_patterns, workspace_patterns, strict)?);
+    }
+
+    Ok(paths)
+}
+
+/// Candidate storage paths inside a single editor data directory
+fn storage_paths_in(
+    path: &Path,
+    global_patterns: &[&[&str]],
+    workspace_patterns: &[&[&str]],
+    strict: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = global_patterns.iter()
+        .map(|pattern| pattern.iter().fold(path.to_path_buf(), |p, seg| p.join(seg)))
+        .collect();
+
+    for pattern in workspace_patterns {
+        let workspace_base = pattern.iter().fold(path.to_path_buf(), |p, seg| p.join(seg));
+        if !workspace_base.exists() { continue; }
+
+        let Some(workspaces) = read_dir_checked(&workspace_base, strict)? else { continue; };
+        paths.extend(workspaces
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
+            .map(|entry| entry.path())
+            .filter(|path| !is_cleaner_artifact(path)));
+    }
+
+    Ok(paths)
+}
+
+/// The discovered directories this run may clean: the ones not unticked in the interface, then at most --limit of them
+pub fn choose_directories(mut directories: Vec<PathBuf>, args: &CliArgs) -> (Vec<PathBuf>, usize) {
+    directories.retain(|dir| !args.deselected.contains(dir));
+    limit_directories(directories, args.limit, args.prefer_global)
+}
+
+/// Share one --limit between the modules of a run: of all their `directories` not unticked, the least recently
+/// cleaned N stay and the rest are deselected in `args`, returning how many were left for the next run
+pub fn share_limit(args: &mut CliArgs, directories: &[PathBuf]) -> usize {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    for dir in directories {
+        if !args.deselected.contains(dir) && !candidates.contains(dir) {
+            candidates.push(dir.clone());
+        }
+    }
+
+    let (chosen, remaining) = limit_directories(candidates.clone(), args.limit, args.prefer_global);
+    args.deselected.extend(candidates.into_iter().filter(|dir| !chosen.contains(dir)));
+    remaining
+}
+
+/// Run `work` on every directory, a few at a time, with the results in the order of `directories`.
+///
+/// `pause` holds each worker before its next directory, the same place the sequential loops waited. Once it is
+/// cancelled no further directory is started, and only the results of the directories already reached are returned.
+pub fn for_each_directory<T: Send>(directories: &[PathBuf], pause: &crate::events::PauseFlag, work: impl Fn(&Path) -> T + Sync) -> Vec<T> {
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(directories.len()));
+
+    std::thread::scope(|scope| {
+        for _ in 0..DIRECTORY_JOBS.min(directories.len()) {
+            scope.spawn(|| {
+                while let Some((index, directory)) = pause_then_next(&next, directories, pause) {
+                    let result = work(directory);
+                    results.lock().unwrap().push((index, result));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+fn pause_then_next<'a>(next: &std::sync::atomic::AtomicUsize, directories: &'a [PathBuf], pause: &crate::events::PauseFlag) -> Option<(usize, &'a PathBuf)> {
+    pause.wait();
+    if pause.is_cancelled() {
+        return None;
+    }
+    let index = next.fetch_add(1, Ordering::SeqCst);
+    directories.get(index).map(|directory| (index, directory))
+}
+
+/// Keep at most `limit` directories, least recently cleaned first, returning how many were left out.
+///
+/// Global storage breaks ties with workspaces, or comes before all of them with `prefer_global`.
+pub fn limit_directories(mut directories: Vec<PathBuf>, limit: Option<usize>, prefer_global: bool) -> (Vec<PathBuf>, usize) {
+    let Some(limit) = limit else { return (directories, 0); };
+
+    // the journal is what lets the next limited run pick up where this one stopped
+    directories.sort_by_key(|dir| {
+        let workspace = dir.components().any(|c| c.as_os_str() == "workspaceStorage");
+        (prefer_global && workspace, last_cleaned(dir), workspace)
+    });
+
+    let remaining = directories.len().saturating_sub(limit);
+    directories.truncate(limit);
+    (directories, remaining)
+}
+
+/// When a limited run last cleaned `target`, from its journal or an older run's --mark, 0 when never
+fn last_cleaned(target: &Path) -> u64 {
+    let journaled = fs::read_to_string(journal_path(target)).ok().and_then(|stamp| stamp.trim().parse().ok());
+    let marked = fs::read_to_string(target.join("storage.json")).ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|data| data.get(MARKER_KEY).and_then(serde_json::Value::as_u64));
+    journaled.max(marked).unwrap_or(0)
+}
+
+/// The journal entry of a storage directory or machine id file, kept in its backups folder
+fn journal_path(target: &Path) -> PathBuf {
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    crate::backup::backup_dir_for(target).join(format!("{}{}", name, JOURNAL_SUFFIX))
+}
+
+/// Note in the journal that `target` was cleaned just now, so the next limited run takes the ones it hasn't reached first
+pub fn record_cleaned(target: &Path) -> Result<()> {
+    let path = journal_path(target);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    fs::write(path, now.to_string())?;
+    Ok(())
+}
+
+/// Where `path` points when it is itself a symlink, so writes through it can be refused
+pub fn symlink_target(path: &Path) -> Option<PathBuf> {
+    let metadata = fs::symlink_metadata(path).ok()?;
+    if !metadata.file_type().is_symlink() {
+        return None;
+    }
+    Some(fs::read_link(path).unwrap_or_default())
+}
+
+/// Name of the editor install a storage path belongs to, e.g. "Code" or "Cursor"
+pub fn install_name(path: &Path) -> String {
+    let names: Vec<_> = path.iter().map(|c| c.to_string_lossy()).collect();
+
+    // storage lives under <install>/User and machine ids directly in <install>; portable installs add a data/ level
+    let anchor = names.iter().rposition(|c| c == "User").unwrap_or(names.len().saturating_sub(1));
+    let mut idx = anchor.checked_sub(1);
+    if let Some(i) = idx.filter(|&i| names[i] == "data") {
+        idx = i.checked_sub(1);
+    }
+
+    idx.map(|i| names[i].to_string()).unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn for_each_directory_keeps_the_directory_order() {
+        let directories: Vec<PathBuf> = (0..23).map(|n| PathBuf::from(format!("/grove/{}", n))).collect();
+        let results = for_each_directory(&directories, &crate::events::PauseFlag::default(), |directory| {
+            // the later directories finish first, so the order has to come from sorting
+            let n: u64 = directory.file_name().unwrap().to_string_lossy().parse().unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(23 - n));
+            directory.to_path_buf()
+        });
+        assert_eq!(results, directories);
+    }
+
+    #[test]
+    fn expand_env_vars_keeps_unknown_references() {
+        assert_eq!(expand_env_vars(r"%CLEANER_NO_SUCH_VAR%\AppData"), std::ffi::OsString::from(r"%CLEANER_NO_SUCH_VAR%\AppData"));
+        assert_eq!(expand_env_vars("100% sure"), std::ffi::OsString::from("100% sure"));
+        assert_eq!(expand_env_vars("%%"), std::ffi::OsString::from("%%"));
+    }
+
+    #[test]
+    fn expand_env_vars_substitutes_known_variables() {
+        let Some(path) = std::env::var_os("PATH") else { return; };
+        let mut expected = std::ffi::OsString::from("before ");
+        expected.push(&path);
+        expected.push(" after");
+        assert_eq!(expand_env_vars("before %PATH% after"), expected);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn expand_env_vars_keeps_a_non_ascii_profile() {
+        use std::os::windows::ffi::OsStringExt;
+        // a lone surrogate after the name, which a String could not carry through
+        let mut wide: Vec<u16> = r"C:\Users\Jürgen".encode_utf16().collect();
+        wide.push(0xD800);
+        let profile = std::ffi::OsString::from_wide(&wide);
+        std::env::set_var("CLEANER_TEST_PROFILE", &profile);
+
+        let mut expected = profile.clone();
+        expected.push(r"\AppData\Roaming");
+        assert_eq!(expand_env_vars(r"%CLEANER_TEST_PROFILE%\AppData\Roaming"), expected);
+    }
+
+    #[test]
+    fn for_each_directory_stops_once_cancelled() {
+        let directories: Vec<PathBuf> = (0..23).map(|n| PathBuf::from(format!("/grove/{}", n))).collect();
+        // a paused run that is cancelled is released without starting anything
+        let pause = crate::events::PauseFlag::default();
+        pause.set(true);
+        pause.cancel();
+        let results = for_each_directory(&directories, &pause, |directory| directory.to_path_buf());
+        assert!(results.is_empty());
+
+        let pause = crate::events::PauseFlag::default();
+        let results = for_each_directory(&directories, &pause, |directory| {
+            pause.cancel();
+            directory.to_path_buf()
+        });
+        // the workers finish the directory they hold, and nothing after it
+        assert!(!results.is_empty() && results.len() <= DIRECTORY_JOBS);
+        assert_eq!(results, directories[..results.len()]);
+    }
+
+    #[test]
+    fn for_each_directory_handles_no_directories() {
+        let results: Vec<()> = for_each_directory(&[], &crate::events::PauseFlag::default(), |_| ());
+        assert!(results.is_empty());
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cleaner-filesystem-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn sorted(mut paths: Vec<PathBuf>) -> Vec<PathBuf> {
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn discovery_finds_global_and_workspace_storage_under_a_base_dir() {
+        let base = scratch_dir("base");
+        let user = base.join("Code").join("User");
+        fs::create_dir_all(user.join("globalStorage")).unwrap();
+        fs::create_dir_all(user.join("workspaceStorage").join("a1b2")).unwrap();
+        fs::create_dir_all(user.join("workspaceStorage").join(BACKUP_DIR_NAME)).unwrap();
+        fs::write(base.join("Code").join(MACHINE_ID), "id").unwrap();
+        // a backups folder beside the editors is never taken for one
+        fs::create_dir_all(base.join(BACKUP_DIR_NAME).join("User").join("globalStorage")).unwrap();
+
+        let roots = DiscoveryRoots { base_dirs: vec![base.clone()], ..Default::default() };
+        let (directories, warnings) = discover_storage_directories_in(&roots, true).unwrap();
+
+        assert_eq!(sorted(directories), sorted(vec![
+            user.join("globalStorage"),
+            user.join("workspaceStorage").join("a1b2"),
+            base.join("Code").join(MACHINE_ID),
+        ]));
+        assert!(warnings.is_empty());
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn discovery_finds_remote_servers_under_home() {
+        let home = scratch_dir("remote");
+        let server = home.join(".vscode-server").join("data");
+        fs::create_dir_all(server.join("User").join("globalStorage")).unwrap();
+        fs::create_dir_all(server.join("User").join("workspaceStorage").join("c3d4")).unwrap();
+        let insiders = home.join(".vscode-server-insiders").join("data");
+        fs::create_dir_all(&insiders).unwrap();
+        fs::write(insiders.join(MACHINE_ID), "id").unwrap();
+
+        let roots = DiscoveryRoots { base_dirs: vec![home.clone()], ..Default::default() };
+        let (directories, _) = discover_storage_directories_in(&roots, true).unwrap();
+
+        assert_eq!(sorted(directories), sorted(vec![
+            server.join("User").join("globalStorage"),
+            server.join("User").join("workspaceStorage").join("c3d4"),
+            insiders.join(MACHINE_ID),
+        ]));
+        let _ = fs::remove_dir_all(&home);
+    }
+
+    #[test]
+    fn redirected_folders_keep_a_non_ascii_profile() {
+        std::env::set_var("CLEANER_TEST_SHELL_PROFILE", r"C:\Users\Jürgen Ødegård");
+        let output = "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\CurrentVersion\\Explorer\\User Shell Folders\r\n\
+            \x20   AppData    REG_EXPAND_SZ    %CLEANER_TEST_SHELL_PROFILE%\\OneDrive\\AppData\\Roaming\r\n\
+            \x20   Local AppData    REG_EXPAND_SZ    C:\\Users\\Jürgen Ødegård\\AppData\\Local\r\n\
+            \x20   Desktop    REG_EXPAND_SZ    C:\\Users\\Jürgen Ødegård\\Desktop\r\n";
+        let mut stdout = output.as_bytes().to_vec();
+        // a line that isn't utf-8 is skipped rather than decoded into the wrong folder
+        stdout.extend(b"    AppData    REG_SZ    C:\\Users\\J\xfcrgen\\AppData\r\n");
+
+        let usual = vec![PathBuf::from(r"C:\Users\Jürgen Ødegård\AppData\Local")];
+        assert_eq!(shell_folders_from_reg(&stdout, &usual), vec![PathBuf::from(r"C:\Users\Jürgen Ødegård\OneDrive\AppData\Roaming")]);
+    }
+
+    #[test]
+    fn editor_storage_is_found_under_a_non_ascii_folder() {
+        let root = scratch_dir("non-ascii");
+        let appdata = root.join("Jürgen Ødegård").join("AppData").join("Roaming");
+        let user = appdata.join("Cursor").join("User");
+        fs::create_dir_all(user.join("globalStorage")).unwrap();
+        fs::create_dir_all(user.join("workspaceStorage").join("e5f6")).unwrap();
+
+        let roots = DiscoveryRoots { config_dirs: vec![appdata], ..Default::default() };
+        let directories = editor_storage_directories(&roots, "Cursor", true).unwrap();
+
+        assert_eq!(sorted(directories), sorted(vec![user.join("globalStorage"), user.join("workspaceStorage").join("e5f6")]));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn one_limit_is_shared_by_every_module() {
+        let root = scratch_dir("shared-limit");
+        let augment: Vec<PathBuf> = ["a", "b"].iter().map(|name| root.join("Code").join(name)).collect();
+        let cursor: Vec<PathBuf> = ["c", "d"].iter().map(|name| root.join("Cursor").join(name)).collect();
+        for dir in augment.iter().chain(&cursor) {
+            fs::create_dir_all(dir).unwrap();
+        }
+        // a is reached by two modules but counts once against the limit
+        let mut directories = augment.clone();
+        directories.extend(cursor.iter().cloned());
+        directories.push(augment[0].clone());
+
+        let mut args = CliArgs::parse_from(["cleaner", "--limit", "3"]);
+        assert_eq!(share_limit(&mut args, &directories), 1);
+        let kept: Vec<&PathBuf> = augment.iter().chain(&cursor).filter(|dir| !args.deselected.contains(dir)).collect();
+        assert_eq!(kept.len(), 3);
+
+        let mut unlimited = CliArgs::parse_from(["cleaner"]);
+        assert_eq!(share_limit(&mut unlimited, &directories), 0);
+        assert!(unlimited.deselected.is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn discovery_finds_a_folder_reached_twice_once() {
+        let root = scratch_dir("twice");
+        let portable = root.join("portable");
+        fs::create_dir_all(portable.join("data").join("User").join("globalStorage")).unwrap();
+
+        // the portable install is both scanned directly and found one level under root
+        let roots = DiscoveryRoots { base_dirs: vec![root.clone()], editor_dirs: vec![portable.join("..").join("portable")], ..Default::default() };
+        let (directories, _) = discover_storage_directories_in(&roots, false).unwrap();
+
+        assert_eq!(directories, vec![portable.join("data").join("User").join("globalStorage")]);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn discovery_of_missing_roots_finds_nothing_even_when_strict() {
+        let root = scratch_dir("missing");
+        let roots = DiscoveryRoots { base_dirs: vec![root.join("absent")], editor_dirs: vec![root.join("gone")], ..Default::default() };
+        let (directories, warnings) = discover_storage_directories_in(&roots, true).unwrap();
+        assert!(directories.is_empty() && warnings.is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn editor_storage_comes_from_config_dirs_and_search_paths() {
+        let root = scratch_dir("editor");
+        let config = root.join("config");
+        fs::create_dir_all(config.join("Windsurf").join("User").join("globalStorage")).unwrap();
+        fs::create_dir_all(config.join("Cursor").join("User").join("globalStorage")).unwrap();
+        let portable = root.join("Windsurf");
+        fs::create_dir_all(portable.join("data").join("User").join("workspaceStorage").join("w1")).unwrap();
+
+        let roots = DiscoveryRoots { config_dirs: vec![config.clone()], search_paths: vec![portable.clone()], ..Default::default() };
+
+        assert_eq!(editor_storage_directories(&roots, "Windsurf", true).unwrap(), vec![
+            config.join("Windsurf").join("User").join("globalStorage"),
+            portable.join("data").join("User").join("workspaceStorage").join("w1"),
+        ]);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn system_roots_take_the_search_paths_from_the_arguments() {
+        let root = scratch_dir("system");
+        let mut args = CliArgs::parse_from(["cleaner"]);
+        args.paths = vec![root.clone(), root.join("absent")];
+
+        let roots = DiscoveryRoots::system(&args);
+        assert_eq!(roots.search_paths, vec![root.clone()]);
+        assert!(roots.editor_dirs.contains(&root));
+        assert_eq!(check_search_paths(&args.paths).len(), 1);
+
+        // roots set on the arguments replace this machine's entirely
+        args.discovery_roots = Some(DiscoveryRoots { base_dirs: vec![root.clone()], ..Default::default() });
+        assert_eq!(DiscoveryRoots::of(&args).base_dirs, vec![root.clone()]);
+        assert!(DiscoveryRoots::of(&args).search_paths.is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn limited_runs_move_on_to_the_directories_not_yet_reached() {
+        let root = scratch_dir("limit");
+        let directories: Vec<PathBuf> = ["a", "b", "c"].iter().map(|name| root.join(name)).collect();
+        for directory in &directories {
+            fs::create_dir_all(directory).unwrap();
+        }
+
+        let mut reached = Vec::new();
+        for _ in 0..3 {
+            let (chosen, remaining) = limit_directories(directories.clone(), Some(1), false);
+            assert_eq!(remaining, 2);
+            assert!(!reached.contains(&chosen[0]), "{} was taken twice", chosen[0].display());
+            record_cleaned(&chosen[0]).unwrap();
+            reached.push(chosen[0].clone());
+        }
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn prefer_global_takes_global_storage_before_older_workspaces() {
+        let root = scratch_dir("prefer-global");
+        let global = root.join("Code").join("User").join("globalStorage");
+        let workspace = root.join("Code").join("User").join("workspaceStorage").join("w1");
+        fs::create_dir_all(&global).unwrap();
+        fs::create_dir_all(&workspace).unwrap();
+        record_cleaned(&global).unwrap();
+
+        let directories = vec![global.clone(), workspace.clone()];
+        assert_eq!(limit_directories(directories.clone(), Some(1), false).0, vec![workspace]);
+        assert_eq!(limit_directories(directories, Some(1), true).0, vec![global]);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn strict_discovery_fails_on_an_unreadable_workspace_folder_in_every_flow() {
+        let root = scratch_dir("strict");
+        for editor in ["Code", "Windsurf"] {
+            let user = root.join(editor).join("User");
+            fs::create_dir_all(user.join("globalStorage")).unwrap();
+            // a file where the folder should be can't be listed, the same as a folder without permission
+            fs::write(user.join("workspaceStorage"), "").unwrap();
+        }
+
+        let mut args = CliArgs::parse_from(["cleaner"]);
+        args.discovery_roots = Some(DiscoveryRoots { base_dirs: vec![root.clone()], config_dirs: vec![root.clone()], ..Default::default() });
+        assert_eq!(find_editor_storage_directories(&args, "Windsurf").unwrap(), vec![root.join("Windsurf").join("User").join("globalStorage")]);
+        assert_eq!(find_vscode_storage_directories(&args).unwrap().len(), 2);
+
+        args.strict_discovery = true;
+        assert!(find_editor_storage_directories(&args, "Windsurf").is_err());
+        assert!(find_vscode_storage_directories(&args).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
 }
@@ -0,0 +1,69 @@
+use serde::Serialize;
+use crate::augment::{clean_augment_extension, AugmentCleaningResult};
+use crate::cli::CliArgs;
+use crate::cursor::{clean_cursor_ide, CursorCleaningResult};
+use crate::events::NoProgress;
+use crate::plan::{build_plan, CleaningPlan};
+use crate::vscodium::{clean_vscodium, VscodiumCleaningResult};
+use crate::windsurf::{clean_windsurf_ide, WindsurfCleaningResult};
+use crate::utils::Result;
+
+/// Everything a `--format json` run found or did, printed as one document
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JsonRun {
+    pub dry_run: bool,
+    /// What would have been done, present only for a dry run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<CleaningPlan>,
+    pub augment: Option<AugmentCleaningResult>,
+    pub cursor: Option<CursorCleaningResult>,
+    pub windsurf: Option<WindsurfCleaningResult>,
+    pub vscodium: Option<VscodiumCleaningResult>,
+    /// Failures that stopped a module before it produced a result
+    pub errors: Vec<String>,
+}
+
+/// Clean the modules picked by flags without the interface and print the results as json
+pub async fn run_json(args: &CliArgs) -> Result<()> {
+    args.require_module_flag()?;
+
+    let mut run = JsonRun { dry_run: args.dry_run, ..JsonRun::default() };
+
+    if args.dry_run {
+        run.plan = Some(build_plan(args)?);
+    } else {
+        // the modules share one --limit, so the directories past it are deselected up front
+        let mut args = args.clone();
+        let found = crate::plan::discover_targets(&args, args.augment, args.cursor, args.windsurf, args.vscodium)?;
+        crate::filesystem::share_limit(&mut args, &found);
+        let args = &args;
+
+        if args.augment {
+            match clean_augment_extension(args, &NoProgress).await {
+                Ok(result) => run.augment = Some(result),
+                Err(e) => run.errors.push(format!("augment cleaning failed: {}", e)),
+            }
+        }
+        if args.cursor {
+            match clean_cursor_ide(args, &NoProgress).await {
+                Ok(result) => run.cursor = Some(result),
+                Err(e) => run.errors.push(format!("cursor cleaning failed: {}", e)),
+            }
+        }
+        if args.windsurf {
+            match clean_windsurf_ide(args, &NoProgress).await {
+                Ok(result) => run.windsurf = Some(result),
+                Err(e) => run.errors.push(format!("windsurf cleaning failed: {}", e)),
+            }
+        }
+        if args.vscodium {
+            match clean_vscodium(args, &NoProgress).await {
+                Ok(result) => run.vscodium = Some(result),
+                Err(e) => run.errors.push(format!("vscodium cleaning failed: {}", e)),
+            }
+        }
+    }
+
+    println!("{}", serde_json::to_string_pretty(&run)?);
+    Ok(())
+}
@@ -1,21 +1,53 @@
 pub mod augment;
+pub mod backup;
+pub mod cache;
 pub mod cli;
+pub mod config;
 pub mod cursor;
 pub mod database;
+pub mod doctor;
+pub mod editor_clean;
+pub mod editors;
+pub mod events;
+pub mod extensions;
 pub mod filesystem;
+pub mod headless;
+pub mod next_steps;
+pub mod plan;
+pub mod preflight;
 pub mod process;
+pub mod product;
+pub mod report;
+pub mod run_log;
 pub mod storage;
 pub mod utils;
+pub mod vscodium;
+pub mod windsurf;
+pub mod workspaces;
+#[cfg(feature = "tui")]
 pub mod zen_garden;
 
 pub use augment::{find_augment_storage_directories, clean_augment_extension, AugmentCleaningResult};
+pub use backup::run_restore;
 pub use cli::CliArgs;
-pub use cursor::{find_cursor_directories, clean_cursor_ide, generate_cursor_ids, CursorCleaningResult};
+pub use config::apply_config;
+pub use cursor::{find_cursor_directories, clean_cursor_ide, generate_cursor_ids, run_cursor_signout, CursorCleaningResult};
 pub use database::clean_vscode_databases;
-pub use filesystem::find_vscode_storage_directories;
-pub use process::terminate_vscode_processes;
-pub use storage::{update_vscode_storage, lock_file_permissions};
+pub use doctor::{run_doctor, run_count_only, run_dump_schema, run_list};
+pub use editor_clean::{clean_editor, EditorCleaningResult};
+pub use editors::{supported_editors, EditorInfo};
+pub use events::{NoProgress, PauseFlag, Progress, ZenEvent};
+pub use filesystem::{check_search_paths, discover_storage_directories_in, find_vscode_storage_directories, is_wsl, DiscoveryRoots};
+pub use headless::run_json;
+pub use next_steps::next_steps;
+pub use plan::{build_plan, CleaningPlan, PlannedTarget};
+pub use process::{terminate_vscode_processes, wait_for_editor_exit};
+pub use report::{run_diff_reports, RunReport};
+pub use storage::{update_vscode_storage, clean_storage_json, lock_file_permissions, run_unmark, ChangedKeys, KeyDiff, RewriteOpts, RewriteResult, UnlockLedger, UnlockedFile};
 pub use utils::{Result, pause_for_user_input};
+pub use vscodium::{find_vscodium_storage_directories, clean_vscodium, VscodiumCleaningResult};
+pub use windsurf::{find_windsurf_storage_directories, clean_windsurf_ide, WindsurfCleaningResult};
+#[cfg(feature = "tui")]
 pub use zen_garden::ZenGarden;
 
 // Legacy function removed - zen garden is now the default interface
@@ -1,15 +1,134 @@
-use cleaner::{CliArgs, ZenGarden};
+use cleaner::cli::{Command, OutputFormat};
+use cleaner::{apply_config, check_search_paths, is_wsl, run_count_only, run_cursor_signout, run_diff_reports, run_doctor, run_dump_schema, run_json, run_list, run_restore, run_unmark, wait_for_editor_exit, CliArgs, ZenGarden};
+use std::io::IsTerminal;
+use std::time::Duration;
 use clap::Parser;
 use color_eyre::Result;
 
+/// Failed cleans in a row after which --watch gives up instead of failing silently on every close
+const WATCH_MAX_FAILURES: u32 = 3;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
-    let args = CliArgs::parse();
+    let mut args = CliArgs::parse();
+    if let Err(e) = apply_config(&mut args) {
+        return Err(color_eyre::eyre::eyre!("{}", e));
+    }
+    if args.wsl_windows && !is_wsl() {
+        eprintln!("warning: --wsl-windows ignored - this is not running under WSL");
+    }
+    for warning in check_search_paths(&args.paths) {
+        eprintln!("warning: {}", warning);
+    }
+
+    if args.command == Some(Command::Restore) {
+        if let Err(e) = run_restore(&args) {
+            return Err(color_eyre::eyre::eyre!("{}", e));
+        }
+        return Ok(());
+    }
+
+    if args.doctor {
+        run_doctor(&args);
+        return Ok(());
+    }
+
+    if let Some(reports) = &args.diff_reports {
+        if let Err(e) = run_diff_reports(&reports[0], &reports[1], &args) {
+            return Err(color_eyre::eyre::eyre!("{}", e));
+        }
+        return Ok(());
+    }
+
+    if args.dump_schema {
+        if let Err(e) = run_dump_schema(&args) {
+            return Err(color_eyre::eyre::eyre!("{}", e));
+        }
+        return Ok(());
+    }
+
+    if args.unmark {
+        if let Err(e) = run_unmark(&args) {
+            return Err(color_eyre::eyre::eyre!("{}", e));
+        }
+        return Ok(());
+    }
+
+    if args.cursor_signout {
+        if let Err(e) = run_cursor_signout(&args) {
+            return Err(color_eyre::eyre::eyre!("{}", e));
+        }
+        return Ok(());
+    }
+
+    if args.list {
+        if let Err(e) = run_list(&args) {
+            return Err(color_eyre::eyre::eyre!("{}", e));
+        }
+        return Ok(());
+    }
+
+    if args.count_only {
+        if let Err(e) = run_count_only(&args) {
+            return Err(color_eyre::eyre::eyre!("{}", e));
+        }
+        return Ok(());
+    }
+
+    if args.format == OutputFormat::Json {
+        if let Err(e) = run_json(&args).await {
+            return Err(color_eyre::eyre::eyre!("{}", e));
+        }
+        return Ok(());
+    }
+
+    // without a terminal the card selection screen would wait forever, and --quiet asks to skip it
+    let headless = args.quiet || !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal();
+    if headless {
+        if let Err(e) = args.require_module_flag() {
+            return Err(color_eyre::eyre::eyre!("{}", e));
+        }
+    }
+
+    if args.watch || args.watch_once {
+        let interval = Duration::from_millis(args.watch_interval.max(100));
+        let mut failures = 0;
+        loop {
+            println!("🍃 waiting for the editor to close (checking every {}ms)", interval.as_millis());
+            tokio::task::spawn_blocking(move || wait_for_editor_exit(interval)).await?;
+
+            let mut garden = ZenGarden::new(&args);
+            let outcome = if headless {
+                garden.run_headless(args.clone()).await?
+            } else {
+                garden.run(args.clone()).await?;
+                garden.outcome()
+            };
+
+            failures = if outcome.exit_code() == 0 { 0 } else { failures + 1 };
+            if failures >= WATCH_MAX_FAILURES {
+                eprintln!("error: the last {} cleans failed - stopping --watch", failures);
+            }
+            if args.watch_once || failures >= WATCH_MAX_FAILURES {
+                if outcome.exit_code() != 0 {
+                    std::process::exit(outcome.exit_code());
+                }
+                return Ok(());
+            }
+        }
+    }
 
     // zen garden is now the default interface
     let mut garden = ZenGarden::new(&args);
-    garden.run(args).await?;
+    if headless {
+        let outcome = garden.run_headless(args).await?;
+        if outcome.exit_code() != 0 {
+            std::process::exit(outcome.exit_code());
+        }
+    } else {
+        garden.run(args).await?;
+    }
 
     Ok(())
 }
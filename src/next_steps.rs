@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::cli::{CliArgs, LockLevel};
+
+/// Advice on what the editor will do on its next launch, tailored to the options used
+pub fn next_steps(args: &CliArgs, directories: &[PathBuf]) -> Vec<String> {
+    let mut steps = Vec::new();
+
+    if directories.iter().any(|dir| dir.is_file()) {
+        steps.push(match args.lock_level {
+            LockLevel::Readonly => "machine id files are read-only, but the editor can still replace them - --lock-level immutable stops that on linux".to_string(),
+            LockLevel::Immutable => "machine id files are sealed where chattr succeeded - undo with sudo chattr -i <file>".to_string(),
+        });
+    }
+
+    if directories.iter().any(|dir| dir.join("storage.json").exists()) {
+        steps.push("storage.json stays writable, so the editor writes fresh ids on its next launch".to_string());
+    }
+
+    let settings = settings_files(directories);
+    if !settings.is_empty() {
+        if settings.iter().all(|path| telemetry_disabled(path)) {
+            steps.push("telemetry is turned off in settings.json - new ids won't be reported".to_string());
+        } else {
+            steps.push("telemetry is still on in settings.json - set \"telemetry.telemetryLevel\": \"off\" to stop reporting new ids".to_string());
+        }
+    }
+
+    if args.no_signout {
+        steps.push("databases were left alone (--no-signout) - extension sessions are still active".to_string());
+    }
+
+    match &args.export_ids {
+        Some(path) => steps.push(format!("after the next launch, re-run with --export-ids {} to see which ids came back", path.display())),
+        None => steps.push("re-run with --export-ids <file> to find out which ids the editor regenerates".to_string()),
+    }
+
+    steps
+}
+
+/// The user settings.json of every install the directories belong to
+fn settings_files(directories: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for dir in directories {
+        let user_dir = if dir.is_file() {
+            dir.parent().map(|install| install.join("User"))
+        } else {
+            dir.ancestors().find(|a| a.file_name().is_some_and(|name| name == "User")).map(Path::to_path_buf)
+        };
+
+        if let Some(settings) = user_dir.map(|user| user.join("settings.json")) {
+            if settings.exists() && !files.contains(&settings) {
+                files.push(settings);
+            }
+        }
+    }
+
+    files
+}
+
+// settings.json allows comments, so look for the setting textually rather than parsing it
+fn telemetry_disabled(settings: &Path) -> bool {
+    let content: String = fs::read_to_string(settings)
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    content.contains("\"telemetry.telemetryLevel\":\"off\"")
+}
@@ -0,0 +1,114 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use crate::backup::files_to_modify;
+use crate::cli::CliArgs;
+use crate::database::database_files;
+use crate::doctor::count_database_rows;
+use crate::filesystem::install_name;
+use crate::process::running_editors;
+use crate::report::planned_changes;
+use crate::utils::{resolve_telemetry_keys, Result};
+
+/// Everything a clean would do, found with read-only discovery and counting
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleaningPlan {
+    pub targets: Vec<PlannedTarget>,
+    /// Running editors that would be terminated, as "name (pid)"
+    pub processes: Vec<String>,
+}
+
+/// What a clean would do to one storage directory or machine id file
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedTarget {
+    pub path: PathBuf,
+    pub editor: String,
+    pub keys_to_rotate: Vec<String>,
+    pub rows_to_delete: i64,
+    pub files_to_back_up: Vec<PathBuf>,
+    /// The same steps as readable sentences, in the order they would be applied
+    pub changes: Vec<String>,
+}
+
+impl CleaningPlan {
+    pub fn directories(&self) -> Vec<PathBuf> {
+        self.targets.iter().map(|target| target.path.clone()).collect()
+    }
+
+    pub fn target(&self, path: &Path) -> Option<&PlannedTarget> {
+        self.targets.iter().find(|target| target.path == path)
+    }
+}
+
+impl PlannedTarget {
+    /// One compact line, e.g. "would rotate 4 keys + delete 2 rows in <path>"
+    pub fn summary(&self) -> String {
+        if self.path.is_file() {
+            return format!("would replace the machine id in {}", self.path.display());
+        }
+        format!("would rotate {} keys + delete {} rows in {}", self.keys_to_rotate.len(), self.rows_to_delete, self.path.display())
+    }
+}
+
+/// The storage directories the augment, cursor, windsurf and vscodium modules would clean, in discovery order
+pub fn discover_targets(args: &CliArgs, augment: bool, cursor: bool, windsurf: bool, vscodium: bool) -> Result<Vec<PathBuf>> {
+    let mut directories = Vec::new();
+    let mut found = Vec::new();
+    if augment {
+        found.extend(crate::augment::find_augment_storage_directories(args)?);
+    }
+    if cursor {
+        found.extend(crate::cursor::find_cursor_storage_directories(args)?);
+    }
+    if windsurf {
+        found.extend(crate::windsurf::find_windsurf_storage_directories(args)?);
+    }
+    if vscodium {
+        found.extend(crate::vscodium::find_vscodium_storage_directories(args)?);
+    }
+    for dir in found {
+        if !directories.contains(&dir) {
+            directories.push(dir);
+        }
+    }
+    Ok(directories)
+}
+
+/// Plan a clean of the modules picked by flags
+pub fn build_plan(args: &CliArgs) -> Result<CleaningPlan> {
+    let mut limited = args.clone();
+    let directories = discover_targets(args, args.augment, args.cursor, args.windsurf, args.vscodium)?;
+    crate::filesystem::share_limit(&mut limited, &directories);
+    let directories: Vec<PathBuf> = directories.into_iter().filter(|dir| !limited.deselected.contains(dir)).collect();
+    Ok(build_plan_for(args, &directories))
+}
+
+/// Plan a clean of already discovered directories without modifying anything
+pub fn build_plan_for(args: &CliArgs, directories: &[PathBuf]) -> CleaningPlan {
+    let (keys, _) = resolve_telemetry_keys(&args.telemetry_keys, &args.extra_keys, args.allow_unknown_keys, &args.preserve_keys);
+
+    let targets = directories.iter()
+        .map(|directory| {
+            let keys_to_rotate = if directory.is_dir() && directory.join("storage.json").exists() { keys.clone() } else { Vec::new() };
+            let rows_to_delete = if args.no_signout || directory.is_file() {
+                0
+            } else {
+                database_files(directory, &args.db_files).iter()
+                    .filter_map(|db| count_database_rows(&directory.join(db), &args.extensions))
+                    .sum()
+            };
+
+            PlannedTarget {
+                path: directory.clone(),
+                editor: install_name(directory),
+                keys_to_rotate,
+                rows_to_delete,
+                files_to_back_up: files_to_modify(directory, !args.no_signout, &args.db_files),
+                changes: planned_changes(directory, &keys, args),
+            }
+        })
+        .collect();
+
+    let processes = if args.no_terminate { Vec::new() } else { running_editors() };
+
+    CleaningPlan { targets, processes }
+}
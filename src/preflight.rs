@@ -0,0 +1,62 @@
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use crate::backup::{backup_dir_for, files_to_modify};
+
+/// A file the clean will need to touch that isn't plainly writable right now
+#[derive(Debug, Clone)]
+pub struct PermissionIssue {
+    pub path: PathBuf,
+    pub problem: String,
+    /// Whether the cleaner can lift the restriction itself, as it does for read-only files
+    pub fixable: bool,
+}
+
+/// Check every file the clean would modify for read and write access, without changing anything
+pub fn check_permissions(directories: &[PathBuf], clean_databases: bool, db_files: &[String]) -> Vec<PermissionIssue> {
+    let mut issues = Vec::new();
+
+    for directory in directories {
+        for file in files_to_modify(directory, clean_databases, db_files) {
+            if let Some(issue) = check_file(&file) {
+                issues.push(issue);
+            }
+        }
+
+        // backups and atomic replacements both need to create files next to the targets
+        let backups = backup_dir_for(directory);
+        let parent = if backups.exists() { backups } else { backups.parent().map(Path::to_path_buf).unwrap_or_default() };
+        if fs::metadata(&parent).is_ok_and(|metadata| metadata.permissions().readonly()) {
+            issues.push(PermissionIssue {
+                path: parent,
+                problem: "directory is read-only, backups will fall back or fail".to_string(),
+                fixable: true,
+            });
+        }
+    }
+
+    issues
+}
+
+fn check_file(file: &Path) -> Option<PermissionIssue> {
+    let issue = |problem: String, fixable: bool| Some(PermissionIssue { path: file.to_path_buf(), problem, fixable });
+
+    let metadata = match fs::metadata(file) {
+        Ok(metadata) => metadata,
+        Err(e) => return issue(format!("can't be inspected: {}", e), false),
+    };
+
+    if let Err(e) = fs::File::open(file) {
+        return issue(format!("can't be read: {}", e), false);
+    }
+
+    if metadata.permissions().readonly() {
+        return issue("read-only, permissions will be lifted during the clean and restored after".to_string(), true);
+    }
+
+    // opening for write without truncating leaves the contents alone
+    if let Err(e) = OpenOptions::new().write(true).open(file) {
+        return issue(format!("can't be written: {}", e), false);
+    }
+
+    None
+}
@@ -1,42 +1,406 @@
 use kill_tree::blocking::kill_tree;
-use sysinfo::System;
-use crate::utils::VSCODE_PROCESSES;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, Process, ProcessStatus, ProcessesToUpdate, System};
+use crate::cli::CliArgs;
+use crate::editors::editor_process_names;
+use crate::utils::ErrorCollector;
 use tokio::sync::mpsc;
-use crate::zen_garden::ZenEvent;
+use crate::events::ZenEvent;
 
-pub fn terminate_vscode_processes(tx: &mpsc::UnboundedSender<ZenEvent>) {
+/// App bundles and install folders whose executables all belong to an editor, lowercased
+const EDITOR_FOLDERS: [&str; 8] = [
+    "visual studio code.app", "visual studio code - insiders.app", "cursor.app", "windsurf.app",
+    "vscodium.app", "trae.app", "microsoft vs code", "microsoft vs code insiders",
+];
+
+/// Whether a process looks like one of the supported editors
+pub fn is_editor_process(process: &Process) -> bool {
+    let name = process.name().to_string_lossy();
+    is_editor_name(&name) || process.exe().is_some_and(is_editor_exe)
+}
+
+/// Whether a process name is an editor or one of its helpers, matching whole words so "xcode" or "ncode" stay out
+pub fn is_editor_name(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    let stem = lower.strip_suffix(".exe").unwrap_or(&lower);
+
+    editor_process_names()
+        .any(|known| {
+            // macos helpers are named like "Code Helper (Renderer)"
+            stem == known || stem.strip_prefix(known).is_some_and(|rest| rest.starts_with(" helper"))
+        })
+}
+
+/// Whether an executable is an editor binary or lives inside an editor's app bundle or install folder
+pub fn is_editor_exe(exe: &std::path::Path) -> bool {
+    let in_editor_folder = exe.components().any(|component| {
+        let component = component.as_os_str().to_string_lossy().to_lowercase();
+        EDITOR_FOLDERS.contains(&component.as_str())
+    });
+
+    in_editor_folder || exe.file_name().is_some_and(|file| is_editor_name(&file.to_string_lossy()))
+}
+
+/// Editors running right now, as "name (pid)"
+pub fn running_editors() -> Vec<String> {
+    System::new_all().processes().iter()
+        .filter(|(_, process)| is_editor_process(process))
+        .map(|(pid, process)| format!("{} ({})", process.name().to_string_lossy(), pid.as_u32()))
+        .collect()
+}
+
+/// The `directories` a running editor holds, each with that editor as "name (pid)".
+///
+/// An editor started with `--user-data-dir` owns only what is under that folder, any other owns its install's default folders.
+pub fn directories_in_use(directories: &[std::path::PathBuf]) -> Vec<(std::path::PathBuf, String)> {
+    let system = System::new_all();
+    let editors: Vec<(String, String, Vec<std::path::PathBuf>)> = system.processes().iter()
+        .filter(|(_, process)| is_editor_process(process))
+        .map(|(pid, process)| {
+            let name = process.name().to_string_lossy().to_string();
+            let exe = process.exe().map(|p| p.to_string_lossy().to_lowercase()).unwrap_or_default();
+            let data_dirs = user_data_dirs(process);
+            (format!("{} ({})", name, pid.as_u32()), format!("{} {}", name.to_lowercase(), exe), data_dirs)
+        })
+        .collect();
+
+    directories.iter()
+        .filter_map(|directory| {
+            let install = crate::filesystem::install_name(directory).to_lowercase();
+            editors.iter()
+                .find(|(_, names, data_dirs)| if data_dirs.is_empty() {
+                    names.contains(&install)
+                } else {
+                    data_dirs.iter().any(|data_dir| directory.starts_with(data_dir))
+                })
+                .map(|(editor, _, _)| (directory.clone(), editor.clone()))
+        })
+        .collect()
+}
+
+/// The folders a process was pointed at with `--user-data-dir`, in either `--flag value` or `--flag=value` form
+fn user_data_dirs(process: &Process) -> Vec<std::path::PathBuf> {
+    let args: Vec<String> = process.cmd().iter().map(|arg| arg.to_string_lossy().to_string()).collect();
+    args.iter().enumerate()
+        .filter_map(|(index, arg)| match arg.strip_prefix("--user-data-dir") {
+            Some("") => args.get(index + 1).cloned(),
+            Some(rest) => rest.strip_prefix('=').map(str::to_string),
+            None => None,
+        })
+        .map(std::path::PathBuf::from)
+        .collect()
+}
+
+/// Every process below `root` in `system`, depth first, with each one's depth under the root starting at 1
+pub fn process_tree(system: &System, root: Pid) -> Vec<(usize, String)> {
+    let mut tree = Vec::new();
+    let mut pending = vec![(root, 0)];
+
+    while let Some((parent, depth)) = pending.pop() {
+        let mut children: Vec<_> = system.processes().iter()
+            .filter(|(_, process)| process.parent() == Some(parent))
+            .collect();
+        children.sort_by_key(|(pid, _)| std::cmp::Reverse(**pid));
+
+        for (pid, _) in children {
+            pending.push((*pid, depth + 1));
+        }
+        if depth > 0 {
+            if let Some(process) = system.process(parent) {
+                tree.push((depth, format!("{} ({})", process.name().to_string_lossy(), parent)));
+            }
+        }
+    }
+
+    tree
+}
+
+/// Block until an editor that was running has exited, polling every `interval`
+pub fn wait_for_editor_exit(interval: Duration) {
+    let mut system = System::new();
+    let mut seen_running = false;
+
+    loop {
+        system.refresh_processes(ProcessesToUpdate::All, true);
+        let running = system.processes().values().any(is_editor_process);
+
+        if seen_running && !running {
+            return;
+        }
+        seen_running |= running;
+
+        std::thread::sleep(interval);
+    }
+}
+
+pub fn terminate_vscode_processes(tx: &mpsc::UnboundedSender<ZenEvent>, dry_run: bool, grace: Duration) {
+    let mut pids = Vec::new();
     for (pid, process) in System::new_all().processes() {
-        let cmd = process.cmd().join(" ".as_ref()).to_string_lossy().to_string();
+        if !is_editor_process(process) { continue; }
+
         let name = process.name().to_string_lossy();
-        let exe = process.exe().map(|p| p.to_string_lossy().to_lowercase()).unwrap_or_default();
-
-        let name_lower = name.to_lowercase();
-        let cmd_lower = cmd.to_lowercase();
-        let exe_lower = exe.to_lowercase();
-
-        let is_vscode = VSCODE_PROCESSES.iter().any(|&vs| name.eq_ignore_ascii_case(vs))
-            || cmd_lower.contains("vscode")
-            || exe_lower.contains("microsoft vs code")
-            || exe_lower.contains("visual studio code")
-            || name_lower.contains("cursor")
-            || name_lower.contains("code-insiders")
-            || name_lower.contains("windsurf")
-            || name_lower.contains("trae")
-            || name_lower.contains("vscodium")
-            || exe_lower.contains("/code")
-            || exe_lower.contains("\\code.exe")
-            || exe_lower.contains("/cursor")
-            || exe_lower.contains("\\cursor.exe")
-            || (exe_lower.contains("code") && exe_lower.contains("electron"))
-            || exe_lower.contains(".app/contents/macos/electron");
-
-        if !is_vscode { continue; }
+
+        if dry_run {
+            let _ = tx.send(ZenEvent::LogMessage(format!("would terminate {} ({})", name, pid)));
+            continue;
+        }
 
         let _ = tx.send(ZenEvent::LogMessage(format!("gently guiding {} ({}) to peaceful rest", name, pid)));
+        pids.push(pid.as_u32());
+    }
 
-        if let Some(parent_pid) = process.parent() {
-            let _ = kill_tree(parent_pid.as_u32());
+    let forced = terminate_gracefully(&pids, grace);
+    if !forced.is_empty() {
+        let _ = tx.send(ZenEvent::Warning(format!("{} ignored the request to close for {}s and were stopped forcefully", forced.len(), grace.as_secs())));
+    }
+}
+
+/// Processes named one of `names` running right now, as "name (pid)"
+pub fn running_processes_named(names: &[&str]) -> Vec<String> {
+    let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+    matching_processes(&System::new_all(), &names).into_iter()
+        .map(|(pid, name)| format!("{} ({})", name, pid))
+        .collect()
+}
+
+/// Kill every process named one of `names` along with its children, returning the names that were running
+pub fn terminate_named_processes(names: &[String], grace: Duration) -> Vec<String> {
+    let mut terminated = Vec::new();
+    let mut pids = Vec::new();
+    for (pid, name) in matching_processes(&System::new_all(), names) {
+        pids.push(pid);
+        if !terminated.contains(&name) {
+            terminated.push(name);
         }
-        let _ = kill_tree(pid.as_u32());
+    }
+    terminate_gracefully(&pids, grace);
+    terminated
+}
+
+/// How long a terminated editor gets to save and exit without `--kill-timeout`
+pub const DEFAULT_KILL_TIMEOUT: u64 = 5;
+
+/// How long a terminated editor gets to save and exit before it is killed, from `--kill-timeout`
+pub fn grace_period(args: &CliArgs) -> Duration {
+    Duration::from_secs(args.kill_timeout.unwrap_or(DEFAULT_KILL_TIMEOUT))
+}
+
+/// Ask the editors among `pids` and their children to close, then kill whatever is still alive after `grace`, returning the pids that had to be killed
+pub fn terminate_gracefully(pids: &[u32], grace: Duration) -> Vec<u32> {
+    if pids.is_empty() {
+        return Vec::new();
+    }
+
+    // the tree is taken up front, children outlive their parent once it exits and would be lost
+    let mut system = System::new_all();
+    let table: Vec<(u32, Option<u32>, bool)> = system.processes().iter()
+        .map(|(pid, process)| (pid.as_u32(), process.parent().map(|parent| parent.as_u32()), is_editor_process(process)))
+        .collect();
+    let roots: Vec<u32> = pids.iter().copied().filter(|pid| table.iter().any(|(known, _, editor)| known == pid && *editor)).collect();
+    let mut targets = editor_tree(&table, &roots, std::process::id());
+
+    for &pid in roots.iter().filter(|pid| targets.contains(pid)) {
+        request_close(pid);
+    }
+
+    let deadline = Instant::now() + grace;
+    loop {
+        system.refresh_processes(ProcessesToUpdate::All, true);
+        targets.retain(|&pid| system.process(Pid::from_u32(pid)).is_some_and(|process| process.status() != ProcessStatus::Zombie));
+        if targets.is_empty() || Instant::now() >= deadline {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+
+    for &pid in &targets {
+        force_kill(pid);
+    }
+    targets
+}
+
+/// The editor `roots` and every process below them in `table` of (pid, parent, is an editor).
+///
+/// Only editors are expanded from, and init, pid 0 and `own` (this process) are never included,
+/// so a stray root can't take the session or the cleaner down with it.
+fn editor_tree(table: &[(u32, Option<u32>, bool)], roots: &[u32], own: u32) -> Vec<u32> {
+    let allowed = |pid: u32| pid > 1 && pid != own;
+    let mut targets: Vec<u32> = Vec::new();
+    let mut pending: Vec<u32> = roots.iter().copied()
+        .filter(|&root| allowed(root) && table.iter().any(|&(pid, _, editor)| pid == root && editor))
+        .collect();
+
+    while let Some(parent) = pending.pop() {
+        if targets.contains(&parent) {
+            continue;
+        }
+        targets.push(parent);
+        pending.extend(table.iter()
+            .filter(|&&(pid, ppid, _)| ppid == Some(parent) && allowed(pid) && !targets.contains(&pid))
+            .map(|&(pid, _, _)| pid));
+    }
+    targets
+}
+
+/// Ask a process tree to close the way a logout would, sigterm on unix and a window close on windows
+fn request_close(pid: u32) {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = std::process::Command::new("taskkill").args(["/PID", &pid.to_string(), "/T"]).output();
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = kill_tree(pid);
+    }
+}
+
+/// Kill a process tree without giving it a chance to clean up
+fn force_kill(pid: u32) {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = kill_tree(pid);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let config = kill_tree::Config { signal: "SIGKILL".to_string(), ..Default::default() };
+        let _ = kill_tree::blocking::kill_tree_with_config(pid, &config);
+    }
+}
+
+/// How long after termination a reappearing process counts as respawned
+pub const RESPAWN_WINDOW: Duration = Duration::from_secs(2);
+
+/// Processes that came back after being terminated, typically relaunched by an update agent
+#[derive(Debug, Clone, Default)]
+pub struct RespawnReport {
+    pub respawned: Vec<String>,
+    pub still_running: Vec<String>,
+}
+
+/// Watch `names` for a short window after termination, killing them again until `kill_timeout` when given
+pub fn wait_for_termination(names: &[String], kill_timeout: Option<Duration>) -> RespawnReport {
+    let mut report = RespawnReport::default();
+    if names.is_empty() {
+        return report;
+    }
+
+    let start = Instant::now();
+    let deadline = start + kill_timeout.unwrap_or_default().max(RESPAWN_WINDOW);
+    let mut system = System::new();
+
+    loop {
+        std::thread::sleep(Duration::from_millis(250));
+        system.refresh_processes(ProcessesToUpdate::All, true);
+
+        let running = matching_processes(&system, names);
+        for (_, name) in &running {
+            if !report.respawned.contains(name) {
+                report.respawned.push(name.clone());
+            }
+        }
+
+        let elapsed = start.elapsed();
+        // without a retry budget a respawn is only reported, never fought
+        if Instant::now() >= deadline || (elapsed >= RESPAWN_WINDOW && (report.respawned.is_empty() || kill_timeout.is_none())) {
+            report.still_running = running.into_iter().map(|(_, name)| name).collect();
+            report.still_running.sort();
+            report.still_running.dedup();
+            return report;
+        }
+
+        if kill_timeout.is_some() {
+            for (pid, _) in running {
+                let _ = kill_tree(pid);
+            }
+        }
+    }
+}
+
+/// Give terminated processes time to exit and warn about any that an update agent brought back
+pub fn report_respawns(terminated: &[String], args: &CliArgs, errors: &mut ErrorCollector) {
+    let kill_timeout = args.kill_timeout.map(Duration::from_secs);
+    let report = wait_for_termination(terminated, kill_timeout);
+
+    for name in &report.respawned {
+        errors.add_warning(format!("{} reappeared after termination - an update agent may be relaunching it", name));
+    }
+    for name in &report.still_running {
+        let hint = if kill_timeout.is_some() { "close its update service and run again" } else { "retry with --kill-timeout" };
+        errors.add_warning(format!("{} kept respawning and is still running - {}", name, hint));
+    }
+}
+
+fn matching_processes(system: &System, names: &[String]) -> Vec<(u32, String)> {
+    system.processes().iter()
+        .filter_map(|(pid, process)| {
+            let name = process.name().to_string_lossy();
+            names.iter()
+                .find(|target| name.eq_ignore_ascii_case(target))
+                .map(|target| (pid.as_u32(), target.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_editor_name_matches_whole_names_only() {
+        for name in ["code", "Code.exe", "CODE", "cursor", "Cursor.exe", "windsurf", "codium", "VSCodium.exe", "code-insiders", "Code Helper (Renderer)", "Cursor Helper", "code helper"] {
+            assert!(is_editor_name(name), "{} should match", name);
+        }
+        for name in ["xcode", "Xcode.exe", "ncode", "codex", "code2", "encoder", "vscode-telemetry-cleaner", "cursorless", "Codehelper", "helper"] {
+            assert!(!is_editor_name(name), "{} should not match", name);
+        }
+    }
+
+    #[test]
+    fn is_editor_exe_trusts_the_install_folder_over_the_file_name() {
+        assert!(is_editor_exe(std::path::Path::new("/Applications/Visual Studio Code.app/Contents/Frameworks/node")));
+        assert!(is_editor_exe(std::path::Path::new("/usr/share/code/code")));
+        assert!(!is_editor_exe(std::path::Path::new("/Applications/Xcode.app/Contents/MacOS/Xcode")));
+        assert!(!is_editor_exe(std::path::Path::new("/usr/bin/codex")));
+    }
+
+    #[test]
+    fn editor_tree_takes_the_editor_and_its_helpers() {
+        let table = [(1, None, false), (50, Some(1), false), (100, Some(50), true), (101, Some(100), false), (102, Some(101), false), (200, Some(50), false)];
+        let mut tree = editor_tree(&table, &[100], 999);
+        tree.sort();
+        assert_eq!(tree, vec![100, 101, 102]);
+    }
+
+    #[test]
+    fn editor_tree_never_expands_from_a_non_editor() {
+        // the shell or launchd an editor was started from must not be taken along
+        let table = [(1, None, false), (50, Some(1), false), (100, Some(50), true), (200, Some(50), false)];
+        assert!(editor_tree(&table, &[50], 999).is_empty());
+        assert!(editor_tree(&table, &[1], 999).is_empty());
+    }
+
+    #[test]
+    fn editor_tree_skips_init_and_the_cleaner_itself() {
+        let table = [(0, None, true), (1, Some(0), true), (100, Some(1), true), (101, Some(100), false), (102, Some(100), false)];
+        let mut tree = editor_tree(&table, &[0, 1, 100], 101);
+        tree.sort();
+        assert_eq!(tree, vec![100, 102]);
+    }
+
+    #[test]
+    fn editor_tree_handles_a_cycle() {
+        let table = [(100, Some(101), true), (101, Some(100), false)];
+        let mut tree = editor_tree(&table, &[100], 999);
+        tree.sort();
+        assert_eq!(tree, vec![100, 101]);
+    }
+
+    #[test]
+    fn the_close_wait_comes_from_kill_timeout() {
+        use clap::Parser;
+        assert_eq!(grace_period(&CliArgs::parse_from(["cleaner"])), Duration::from_secs(DEFAULT_KILL_TIMEOUT));
+        assert_eq!(grace_period(&CliArgs::parse_from(["cleaner", "--kill-timeout", "9"])), Duration::from_secs(9));
     }
 }
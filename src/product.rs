@@ -0,0 +1,89 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The parts of an editor's product.json that decide where it keeps its data
+#[derive(Debug, Clone)]
+pub struct ProductInfo {
+    /// The install folder holding resources/app/product.json, where portable installs keep data/
+    pub install_dir: PathBuf,
+    /// Folder name under the platform config directory, e.g. "Code" or "Cursor"
+    pub name_short: String,
+    /// Folder name under home for extensions, e.g. ".vscode"
+    pub data_folder_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProductJson {
+    name_short: Option<String>,
+    data_folder_name: Option<String>,
+}
+
+/// Read product.json from every editor install found in the usual install locations
+pub fn find_products() -> Vec<ProductInfo> {
+    let mut products = Vec::new();
+
+    for root in install_roots() {
+        let Ok(entries) = fs::read_dir(&root) else { continue; };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let install_dir = entry.path();
+            if let Some(product) = read_product(&install_dir) {
+                products.push(product);
+            }
+        }
+    }
+
+    products
+}
+
+fn read_product(install_dir: &Path) -> Option<ProductInfo> {
+    let product_path = ["resources/app/product.json", "Contents/Resources/app/product.json"].iter()
+        .map(|relative| install_dir.join(relative))
+        .find(|path| path.is_file())?;
+
+    let product: ProductJson = serde_json::from_str(&fs::read_to_string(product_path).ok()?).ok()?;
+
+    // a product.json without a name isn't an editor we know how to find data for
+    let name_short = product.name_short.filter(|name| !name.trim().is_empty())?;
+
+    Some(ProductInfo {
+        install_dir: install_dir.to_path_buf(),
+        name_short,
+        data_folder_name: product.data_folder_name.filter(|name| !name.trim().is_empty()),
+    })
+}
+
+fn install_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+
+    #[cfg(target_os = "windows")]
+    {
+        if let Some(local_appdata) = std::env::var_os("LOCALAPPDATA") {
+            roots.push(PathBuf::from(local_appdata).join("Programs"));
+        }
+        for var in ["ProgramFiles", "ProgramFiles(x86)"] {
+            if let Some(program_files) = std::env::var_os(var) {
+                roots.push(PathBuf::from(program_files));
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        roots.push(PathBuf::from("/Applications"));
+        if let Some(home) = dirs::home_dir() {
+            roots.push(home.join("Applications"));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        roots.extend(["/usr/share", "/usr/lib", "/opt"].iter().map(PathBuf::from));
+        if let Some(home) = dirs::home_dir() {
+            roots.push(home.join(".local/share"));
+        }
+    }
+
+    roots
+}
@@ -0,0 +1,488 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::cli::{CliArgs, GroupBy, LockLevel, OutputFormat};
+use crate::database::database_files;
+use crate::doctor::{count_database_rows, mask_value, read_storage_json};
+use crate::filesystem::install_name;
+use crate::storage::UnlockedFile;
+use crate::utils::{ErrorCollector, Result};
+
+/// Telemetry values written by the last clean, keyed by directory then key
+pub type ExportedIds = BTreeMap<String, BTreeMap<String, String>>;
+
+/// A snapshot of what a run found, written with `--report`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunReport {
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub editors: BTreeMap<String, EditorSummary>,
+    /// Directory paths grouped as requested with `--group-by`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, Vec<String>>,
+    pub directories: Vec<DirectoryReport>,
+    /// Read-only files that had to be unlocked to clean them, and whether they were locked again
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unlocked: Vec<UnlockedFile>,
+    /// Telemetry values found in more than one storage.json before cleaning
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shared_ids: Vec<SharedIdentity>,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// One telemetry value that several discovered storage.json files have in common
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedIdentity {
+    pub key: String,
+    pub directories: Vec<String>,
+    /// Whether some of the storage.json files are one file reached through symlinks rather than copies
+    pub symlinked: bool,
+}
+
+impl SharedIdentity {
+    /// e.g. "telemetry.machineId is shared by 2 locations (partly one file through symlinks): a, b"
+    pub fn describe(&self) -> String {
+        let how = if self.symlinked { " (partly one file through symlinks)" } else { "" };
+        format!("{} is shared by {} locations{}: {}", self.key, self.directories.len(), how, self.directories.join(", "))
+    }
+}
+
+/// Subtotals for one editor install, e.g. "Code" or "Cursor"
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EditorSummary {
+    pub directories: Vec<String>,
+    pub telemetry_keys: usize,
+    pub matching_rows: i64,
+}
+
+/// Telemetry state of a single storage directory as found before cleaning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryReport {
+    pub path: String,
+    #[serde(default)]
+    pub editor: String,
+    pub telemetry: BTreeMap<String, String>,
+    pub matching_rows: i64,
+    pub cleaned: bool,
+    #[serde(default)]
+    pub failed: bool,
+    /// Changes a dry run would have made, in the order they would be applied
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub planned: Vec<String>,
+}
+
+impl DirectoryReport {
+    /// Read the current telemetry values and matching database rows without modifying anything
+    pub fn snapshot(directory: &Path, keys: &[String], args: &CliArgs) -> Self {
+        let telemetry = read_telemetry(directory, keys);
+
+        let matching_rows = database_files(directory, &args.db_files).iter()
+            .filter_map(|db| count_database_rows(&directory.join(db), &args.extensions))
+            .sum();
+
+        Self {
+            path: directory.display().to_string(),
+            editor: install_name(directory),
+            telemetry,
+            matching_rows,
+            cleaned: false,
+            failed: false,
+            planned: Vec::new(),
+        }
+    }
+}
+
+/// Describe what cleaning `directory` would change, without touching it
+pub fn planned_changes(directory: &Path, keys: &[String], args: &CliArgs) -> Vec<String> {
+    let mut planned = Vec::new();
+
+    if directory.is_file() {
+        let lock = match args.lock_level {
+            LockLevel::Readonly => "read-only",
+            LockLevel::Immutable => "immutable",
+        };
+        planned.push(format!("regenerate machine id and lock it {}", lock));
+        return planned;
+    }
+
+    if directory.join("storage.json").exists() {
+        planned.extend(keys.iter().map(|key| format!("rotate {} in storage.json", key)));
+    }
+
+    if !args.no_signout {
+        for db in database_files(directory, &args.db_files) {
+            if let Some(rows) = count_database_rows(&directory.join(&db), &args.extensions) {
+                planned.push(format!("delete {} matching rows from {}", rows, db));
+            }
+        }
+    }
+
+    planned
+}
+
+/// Aggregate counts for a run, printed on one line with `--summary-json-line`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunTotals {
+    pub completed: bool,
+    pub dry_run: bool,
+    pub directories: usize,
+    pub cleaned: usize,
+    pub failed: usize,
+    /// Existing telemetry values replaced in cleaned directories
+    pub keys_rotated: usize,
+    /// Matching database rows found in cleaned directories before they were deleted
+    pub rows_deleted: i64,
+    /// Read-only files unlocked during the clean, and how many of those stayed writable
+    pub files_unlocked: usize,
+    pub files_not_relocked: usize,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+/// How a run ended overall, for the final banner and the exit code
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Success,
+    Warnings,
+    Errors,
+}
+
+impl Outcome {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Outcome::Success | Outcome::Warnings => 0,
+            Outcome::Errors => 1,
+        }
+    }
+
+    /// A single result line, colored green, yellow or red unless `color` is off
+    pub fn banner(self, color: bool) -> String {
+        let (text, code) = match self {
+            Outcome::Success => ("CLEAN SUCCESSFUL", "32"),
+            Outcome::Warnings => ("COMPLETED WITH WARNINGS", "33"),
+            Outcome::Errors => ("COMPLETED WITH ERRORS", "31"),
+        };
+        if color { format!("\x1b[1;{}m{}\x1b[0m", code, text) } else { text.to_string() }
+    }
+}
+
+impl RunTotals {
+    pub fn outcome(&self) -> Outcome {
+        if self.errors > 0 {
+            Outcome::Errors
+        } else if self.warnings > 0 {
+            Outcome::Warnings
+        } else {
+            Outcome::Success
+        }
+    }
+
+    /// A finished run that replaced no ids and deleted no rows
+    pub fn is_noop(&self) -> bool {
+        self.completed && !self.dry_run && self.keys_rotated == 0 && self.rows_deleted == 0
+    }
+}
+
+impl DirectoryReport {
+    pub fn status(&self) -> &'static str {
+        if self.failed {
+            "error"
+        } else if self.cleaned {
+            "cleaned"
+        } else if !self.planned.is_empty() {
+            "planned"
+        } else {
+            "skipped"
+        }
+    }
+}
+
+impl RunReport {
+    pub fn record_errors(&mut self, errors: &ErrorCollector) {
+        self.errors.extend(errors.errors.iter().map(|e| e.to_string()));
+        self.warnings.extend(errors.warnings.iter().cloned());
+
+        for path in errors.errors.iter().filter_map(|e| e.path()) {
+            for directory in self.directories.iter_mut().filter(|d| path.starts_with(&d.path)) {
+                directory.failed = true;
+            }
+        }
+    }
+
+    /// Directory counts for the one-line summary, leaving error totals to whoever saw every event
+    pub fn totals(&self) -> RunTotals {
+        RunTotals {
+            dry_run: self.dry_run,
+            directories: self.directories.len(),
+            cleaned: self.directories.iter().filter(|d| d.cleaned).count(),
+            failed: self.directories.iter().filter(|d| d.failed).count(),
+            keys_rotated: self.directories.iter().filter(|d| d.cleaned).map(|d| d.telemetry.len()).sum(),
+            rows_deleted: self.directories.iter().filter(|d| d.cleaned).map(|d| d.matching_rows).sum(),
+            files_unlocked: self.unlocked.len(),
+            files_not_relocked: self.unlocked.iter().filter(|file| !file.restored).count(),
+            ..RunTotals::default()
+        }
+    }
+
+    /// Fill `groups` according to `--group-by`, leaving it empty for a flat report
+    pub fn group_directories(&mut self, group_by: GroupBy) {
+        self.groups.clear();
+        if group_by == GroupBy::None {
+            return;
+        }
+
+        for directory in &self.directories {
+            let group = match group_by {
+                GroupBy::Status => directory.status().to_string(),
+                _ => directory.editor.clone(),
+            };
+            self.groups.entry(group).or_default().push(directory.path.clone());
+        }
+    }
+
+    /// One line per group describing the outcome, for the end-of-run summary
+    pub fn summary_lines(&self, group_by: GroupBy) -> Vec<String> {
+        let tally = |dirs: &mut dyn Iterator<Item = &str>| {
+            let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for name in dirs {
+                *counts.entry(name).or_default() += 1;
+            }
+            counts.iter().map(|(name, count)| format!("{} {}", count, name)).collect::<Vec<_>>().join(", ")
+        };
+
+        let mut groups: BTreeMap<String, Vec<&DirectoryReport>> = BTreeMap::new();
+        for directory in &self.directories {
+            match group_by {
+                GroupBy::Editor => groups.entry(directory.editor.clone()).or_default().push(directory),
+                GroupBy::Status => groups.entry(directory.status().to_string()).or_default().push(directory),
+                GroupBy::None => groups.entry(directory.path.clone()).or_default().push(directory),
+            }
+        }
+
+        let mut lines: Vec<String> = groups.iter()
+            .map(|(group, dirs)| match group_by {
+                GroupBy::Editor => format!("{} - {}", group, tally(&mut dirs.iter().map(|d| d.status()))),
+                GroupBy::Status => format!("{} - {}", group, tally(&mut dirs.iter().map(|d| d.editor.as_str()))),
+                GroupBy::None => format!("{} - {}", group, dirs[0].status()),
+            })
+            .collect();
+
+        if !self.unlocked.is_empty() {
+            let relocked = self.unlocked.iter().filter(|file| file.restored).count();
+            lines.push(format!("{} read-only files unlocked, {} locked again", self.unlocked.len(), relocked));
+        }
+        lines.extend(self.unlocked.iter()
+            .filter(|file| !file.restored)
+            .map(|file| format!("{} - left writable", file.path)));
+        lines
+    }
+
+    pub fn mark_cleaned(&mut self, path: &str) {
+        if let Some(directory) = self.directories.iter_mut().find(|d| d.path == path) {
+            directory.cleaned = true;
+        }
+    }
+
+    /// Group the directories by the editor they belong to and total their keys and rows
+    pub fn summarize_editors(&mut self) {
+        self.editors.clear();
+        for directory in &self.directories {
+            let summary = self.editors.entry(directory.editor.clone()).or_default();
+            summary.directories.push(directory.path.clone());
+            summary.telemetry_keys += directory.telemetry.len();
+            summary.matching_rows += directory.matching_rows;
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read report '{}': {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("failed to parse report '{}': {}", path.display(), e).into())
+    }
+}
+
+fn read_telemetry(directory: &Path, keys: &[String]) -> BTreeMap<String, String> {
+    read_storage_json(directory)
+        .map(|data| {
+            keys.iter()
+                .filter_map(|key| data.get(key).and_then(|v| v.as_str()).map(|v| (key.clone(), v.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Read the telemetry values currently present in each directory
+pub fn collect_ids(directories: &[PathBuf], keys: &[String]) -> ExportedIds {
+    directories.iter()
+        .map(|dir| (dir.display().to_string(), read_telemetry(dir, keys)))
+        .filter(|(_, ids)| !ids.is_empty())
+        .collect()
+}
+
+/// Telemetry values that more than one directory holds, a sign of copied or symlinked editor identity
+pub fn shared_identities(directories: &[PathBuf], keys: &[String]) -> Vec<SharedIdentity> {
+    let mut holders: BTreeMap<(String, String), Vec<&PathBuf>> = BTreeMap::new();
+    for directory in directories {
+        for (key, value) in read_telemetry(directory, keys) {
+            holders.entry((key, value)).or_default().push(directory);
+        }
+    }
+
+    holders.into_iter()
+        .filter(|(_, dirs)| dirs.len() > 1)
+        .map(|((key, _), dirs)| {
+            let files: Vec<PathBuf> = dirs.iter().filter_map(|dir| fs::canonicalize(dir.join("storage.json")).ok()).collect();
+            SharedIdentity {
+                key,
+                directories: dirs.iter().map(|dir| dir.display().to_string()).collect(),
+                symlinked: files.iter().enumerate().any(|(i, file)| files[..i].contains(file)),
+            }
+        })
+        .collect()
+}
+
+pub fn export_ids(path: &Path, directories: &[PathBuf], keys: &[String]) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(&collect_ids(directories, keys))?)?;
+    Ok(())
+}
+
+pub fn load_exported_ids(path: &Path) -> Result<ExportedIds> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read exported ids '{}': {}", path.display(), e))?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("failed to parse exported ids '{}': {}", path.display(), e).into())
+}
+
+/// List the (directory, key) pairs whose value no longer matches what the last clean wrote
+pub fn regenerated_ids(previous: &ExportedIds, current: &ExportedIds) -> Vec<(String, String)> {
+    previous.iter()
+        .flat_map(|(dir, ids)| {
+            ids.iter()
+                .filter(move |(key, value)| current.get(dir).and_then(|now| now.get(*key)) != Some(*value))
+                .map(move |(key, _)| (dir.clone(), key.clone()))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReportDiff {
+    pub new_directories: Vec<String>,
+    pub missing_directories: Vec<String>,
+    pub newly_cleaned: Vec<String>,
+    pub changed_keys: Vec<KeyChange>,
+    pub row_deltas: Vec<RowDelta>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyChange {
+    pub path: String,
+    pub key: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RowDelta {
+    pub path: String,
+    pub before: i64,
+    pub after: i64,
+}
+
+impl ReportDiff {
+    pub fn between(before: &RunReport, after: &RunReport) -> Self {
+        let mut diff = Self::default();
+
+        for new in &after.directories {
+            let Some(old) = before.directories.iter().find(|d| d.path == new.path) else {
+                diff.new_directories.push(new.path.clone());
+                if new.cleaned {
+                    diff.newly_cleaned.push(new.path.clone());
+                }
+                continue;
+            };
+
+            if new.cleaned && !old.cleaned {
+                diff.newly_cleaned.push(new.path.clone());
+            }
+
+            let keys: std::collections::BTreeSet<&String> = old.telemetry.keys().chain(new.telemetry.keys()).collect();
+            for key in keys {
+                let (was, now) = (old.telemetry.get(key), new.telemetry.get(key));
+                if was != now {
+                    diff.changed_keys.push(KeyChange {
+                        path: new.path.clone(),
+                        key: key.clone(),
+                        before: was.cloned(),
+                        after: now.cloned(),
+                    });
+                }
+            }
+
+            if old.matching_rows != new.matching_rows {
+                diff.row_deltas.push(RowDelta {
+                    path: new.path.clone(),
+                    before: old.matching_rows,
+                    after: new.matching_rows,
+                });
+            }
+        }
+
+        diff.missing_directories = before.directories.iter()
+            .filter(|old| !after.directories.iter().any(|new| new.path == old.path))
+            .map(|old| old.path.clone())
+            .collect();
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.new_directories.is_empty()
+            && self.missing_directories.is_empty()
+            && self.newly_cleaned.is_empty()
+            && self.changed_keys.is_empty()
+            && self.row_deltas.is_empty()
+    }
+}
+
+/// Compare two `--report` files and print what changed between them
+pub fn run_diff_reports(before: &Path, after: &Path, args: &CliArgs) -> Result<()> {
+    let diff = ReportDiff::between(&RunReport::load(before)?, &RunReport::load(after)?);
+
+    if args.format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+
+    if diff.is_empty() {
+        println!("no differences - the second run found nothing new");
+        return Ok(());
+    }
+
+    for path in &diff.new_directories {
+        println!("+ {}", path);
+    }
+    for path in &diff.missing_directories {
+        println!("- {}", path);
+    }
+    for path in &diff.newly_cleaned {
+        println!("newly cleaned: {}", path);
+    }
+    for change in &diff.changed_keys {
+        let show = |v: &Option<String>| v.as_deref().map(|v| mask_value(v, args.anonymize)).unwrap_or_else(|| "not set".to_string());
+        println!("~ {} {}: {} -> {}", change.path, change.key, show(&change.before), show(&change.after));
+    }
+    for delta in &diff.row_deltas {
+        println!("~ {} matching rows: {} -> {}", delta.path, delta.before, delta.after);
+    }
+
+    Ok(())
+}
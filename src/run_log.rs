@@ -0,0 +1,196 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::cli::LogFormat;
+use crate::events::ZenEvent;
+use crate::utils::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+impl LogLevel {
+    fn label(self) -> &'static str {
+        match self {
+            LogLevel::Info => "info",
+            LogLevel::Warning => "warning",
+            LogLevel::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub phase: String,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+/// Something the run changed, taken from the structured change events
+#[derive(Debug, Clone)]
+pub struct LoggedChange {
+    pub kind: &'static str,
+    pub target: String,
+}
+
+/// Every message and change of a run, grouped by the phase it happened in
+#[derive(Debug, Default)]
+pub struct RunLog {
+    pub entries: Vec<LogEntry>,
+    pub changes: Vec<LoggedChange>,
+    phase: String,
+    /// Plain --log-file output, appended to as each event is handled
+    sink: Option<File>,
+}
+
+impl RunLog {
+    /// Append every following message to `path` as it is recorded, one timestamped line each
+    pub fn stream_to(&mut self, path: &Path) -> Result<()> {
+        let file = OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|e| format!("could not open log '{}': {}", path.display(), e))?;
+        self.sink = Some(file);
+        Ok(())
+    }
+
+    fn stream(&mut self, level: &str, phase: &str, message: &str) {
+        if let Some(sink) = &mut self.sink {
+            // a log that stops accepting lines must not take the clean down with it
+            if writeln!(sink, "{} [{}] {}: {}", timestamp(), level, phase, message).is_err() {
+                self.sink = None;
+            }
+        }
+    }
+
+    pub fn record(&mut self, event: &ZenEvent) {
+        let (level, message) = match event {
+            ZenEvent::StartScanning => {
+                self.phase = "scanning".to_string();
+                return;
+            }
+            ZenEvent::PhaseStarted(phase) => {
+                self.phase = phase.clone();
+                return;
+            }
+            ZenEvent::ProcessTerminated(process) => {
+                self.change("process terminated", process);
+                return;
+            }
+            ZenEvent::StorageUpdated(location) => {
+                self.change("storage updated", location);
+                return;
+            }
+            ZenEvent::DatabaseCleaned(location) => {
+                self.change("database cleaned", location);
+                return;
+            }
+            ZenEvent::KeysChanged(location, changed) => {
+                for (key, _, _) in changed {
+                    self.change("key rotated", &format!("{} in {}", key, location));
+                }
+                return;
+            }
+            ZenEvent::LocationFound(location) => (LogLevel::Info, format!("found {}", location)),
+            ZenEvent::LogMessage(message) => (LogLevel::Info, message.clone()),
+            ZenEvent::Warning(warning) => (LogLevel::Warning, warning.clone()),
+            ZenEvent::Error(error) => (LogLevel::Error, error.clone()),
+            ZenEvent::DetailedError(error) => (LogLevel::Error, error.to_string()),
+            // its errors and warnings were logged as they arrived on their own
+            ZenEvent::ErrorSummary(_) => return,
+            _ => return,
+        };
+        self.push(level, message);
+    }
+
+    fn push(&mut self, level: LogLevel, message: String) {
+        let phase = if self.phase.is_empty() { "setup".to_string() } else { self.phase.clone() };
+        self.stream(level.label(), &phase, &message);
+        self.entries.push(LogEntry { phase, level, message });
+    }
+
+    fn change(&mut self, kind: &'static str, target: &str) {
+        let phase = self.phase.clone();
+        self.stream("change", &phase, &format!("{} {}", kind, target));
+        self.changes.push(LoggedChange { kind, target: target.to_string() });
+    }
+
+    /// A section per phase and a results table of every change
+    pub fn render_markdown(&self) -> String {
+        let mut out = String::from("# Cleaner run log\n");
+
+        let mut phase = None;
+        for entry in &self.entries {
+            if phase != Some(&entry.phase) {
+                out.push_str(&format!("\n## {}\n\n", entry.phase));
+                phase = Some(&entry.phase);
+            }
+            let marker = match entry.level {
+                LogLevel::Info => "",
+                LogLevel::Warning => "**warning:** ",
+                LogLevel::Error => "**error:** ",
+            };
+            out.push_str(&format!("- {}{}\n", marker, escape_markdown(&entry.message)));
+        }
+
+        out.push_str("\n## Results\n\n");
+        if self.changes.is_empty() {
+            out.push_str("Nothing was changed.\n");
+        } else {
+            out.push_str("| Change | Target |\n|---|---|\n");
+            for change in &self.changes {
+                out.push_str(&format!("| {} | `{}` |\n", change.kind, change.target.replace('|', "\\|")));
+            }
+        }
+
+        let count = |level| self.entries.iter().filter(|entry| entry.level == level).count();
+        out.push_str(&format!("\n{} changes, {} warnings, {} errors\n", self.changes.len(), count(LogLevel::Warning), count(LogLevel::Error)));
+        out
+    }
+
+    /// Finish --log-file: plain lines were already streamed, markdown is written in one go
+    pub fn write(&mut self, path: &Path, format: LogFormat) -> Result<()> {
+        match format {
+            LogFormat::Plain => {
+                if let Some(sink) = &mut self.sink {
+                    sink.flush()?;
+                }
+                Ok(())
+            }
+            LogFormat::Markdown => fs::write(path, self.render_markdown())
+                .map_err(|e| format!("could not write log '{}': {}", path.display(), e).into()),
+        }
+    }
+}
+
+/// Keep paths and ids from being read as emphasis or table syntax
+fn escape_markdown(text: &str) -> String {
+    text.chars().fold(String::new(), |mut out, c| {
+        if matches!(c, '*' | '_' | '`' | '|' | '[' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+        out
+    })
+}
+
+/// The current UTC time as e.g. "2026-10-14T08:03:12Z", without pulling in a date crate
+fn timestamp() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (days, rest) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // civil-from-days, counting from 0000-03-01 so leap days fall at the end of each year
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rest / 3_600, rest % 3_600 / 60, rest % 60)
+}
@@ -1,21 +1,66 @@
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
+use std::collections::HashMap;
 use sha2::{Sha256, Digest};
 use std::fs;
 use std::path::Path;
 use std::process::Command;
+use std::sync::{Arc, Mutex};
 use uuid::Uuid;
-use crate::utils::{Result, CleanerError, ErrorCollector, TELEMETRY_KEYS};
-use tokio::sync::mpsc;
-use crate::zen_garden::ZenEvent;
+use crate::backup::backup_files;
+use crate::cache::format_size;
+use crate::cli::{CliArgs, IdFormat, LockLevel};
+use crate::filesystem::{find_vscode_storage_directories, symlink_target};
+use crate::utils::{Result, CleanerError, ErrorCollector, resolve_id_formats, resolve_telemetry_keys, MARKER_KEY};
+use crate::events::Progress;
+
+/// A read-only file the clean had to unlock, and whether the flag was put back afterwards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlockedFile {
+    pub path: String,
+    pub restored: bool,
+}
+
+/// The read-only files one run had to unlock, shared by its modules so the report can account for them whichever module did it
+#[derive(Debug, Clone, Default)]
+pub struct UnlockLedger(Arc<Mutex<Vec<UnlockedFile>>>);
+
+impl UnlockLedger {
+    /// The files unlocked so far, in the order they were unlocked
+    pub fn files(&self) -> Vec<UnlockedFile> {
+        self.0.lock().map(|files| files.clone()).unwrap_or_default()
+    }
+
+    fn record(&self, file_path: &Path, restored: bool) {
+        let path = file_path.display().to_string();
+        if let Ok(mut files) = self.0.lock() {
+            match files.iter_mut().find(|file| file.path == path) {
+                Some(file) => file.restored = restored,
+                None => files.push(UnlockedFile { path, restored }),
+            }
+        }
+    }
+
+    fn relocked(&self, file_path: &Path) {
+        let path = file_path.display().to_string();
+        if let Ok(mut files) = self.0.lock() {
+            for file in files.iter_mut().filter(|file| file.path == path) {
+                file.restored = true;
+            }
+        }
+    }
+}
 
 pub struct FilePermissions {
     was_readonly: bool,
+    unlocked: UnlockLedger,
     #[cfg(unix)]
     original_mode: u32,
 }
 
 impl FilePermissions {
-    pub fn backup_and_make_writable(file_path: &Path) -> Result<Self> {
+    /// Make `file_path` writable, noting it in `unlocked` when it was read-only until `restore` locks it again
+    pub fn backup_and_make_writable(file_path: &Path, unlocked: &UnlockLedger) -> Result<Self> {
         let metadata = fs::metadata(file_path)?;
         let was_readonly = metadata.permissions().readonly();
 
@@ -32,181 +77,599 @@ impl FilePermissions {
                 }
             }
 
-            let mut permissions = metadata.permissions();
-            permissions.set_readonly(false);
-            fs::set_permissions(file_path, permissions)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(file_path, fs::Permissions::from_mode(original_mode | 0o200))?;
+            }
+
+            #[cfg(not(unix))]
+            {
+                let mut permissions = metadata.permissions();
+                permissions.set_readonly(false);
+                fs::set_permissions(file_path, permissions)?;
+            }
+
+            unlocked.record(file_path, false);
         }
 
         Ok(FilePermissions {
             was_readonly,
+            unlocked: unlocked.clone(),
             #[cfg(unix)]
             original_mode,
         })
     }
 
     pub fn restore(&self, file_path: &Path) -> Result<()> {
-        if self.was_readonly {
-            #[cfg(windows)]
-            {
-                if Command::new("attrib").args(["+R", &file_path.to_string_lossy()]).status().is_err() {
-                }
-            }
+        if !self.was_readonly {
+            return Ok(());
+        }
 
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                let permissions = fs::Permissions::from_mode(self.original_mode);
-                if fs::set_permissions(file_path, permissions).is_err() {
-                }
-            }
+        let restored = self.make_readonly_again(file_path);
+        self.unlocked.record(file_path, restored.is_ok());
+        restored
+    }
 
-            #[cfg(not(unix))]
-            {
-                if let Ok(metadata) = fs::metadata(file_path) {
-                    let mut permissions = metadata.permissions();
-                    permissions.set_readonly(true);
-                    if fs::set_permissions(file_path, permissions).is_err() {
-                    }
-                }
+    fn make_readonly_again(&self, file_path: &Path) -> Result<()> {
+        #[cfg(windows)]
+        {
+            if Command::new("attrib").args(["+R", &file_path.to_string_lossy()]).status().is_err() {
             }
         }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(file_path, fs::Permissions::from_mode(self.original_mode))?;
+        }
+
+        #[cfg(not(unix))]
+        {
+            let mut permissions = fs::metadata(file_path)?.permissions();
+            permissions.set_readonly(true);
+            fs::set_permissions(file_path, permissions)?;
+        }
+
         Ok(())
     }
 }
 
-pub fn update_vscode_storage(directory: &Path, tx: &mpsc::UnboundedSender<ZenEvent>) -> Result<()> {
+/// A rewritten storage.json key as (key, old value, new value), the old value empty when the key was added
+pub type KeyDiff = (String, String, String);
+
+/// Key diffs keyed by the storage directory they were made in
+pub type ChangedKeys = std::collections::BTreeMap<String, Vec<KeyDiff>>;
+
+/// Rotate the telemetry ids in `directory`, returning each key that was given a new value
+pub fn update_vscode_storage(directory: &Path, args: &CliArgs, progress: &impl Progress) -> Result<Vec<KeyDiff>> {
     let mut error_collector = ErrorCollector::new();
+    let (keys, _) = resolve_telemetry_keys(&args.telemetry_keys, &args.extra_keys, args.allow_unknown_keys, &args.preserve_keys);
+    let (formats, _) = resolve_id_formats(&args.id_formats, &keys);
 
     // try to update storage.json
-    if let Err(e) = update_storage_json(directory, tx) {
-        let error = CleanerError::FileSystem {
-            operation: "updating storage.json".to_string(),
-            path: directory.join("storage.json").display().to_string(),
-            source: e.to_string(),
-        };
-        error_collector.add_error(error.clone());
-        let _ = tx.send(ZenEvent::DetailedError(error));
-    }
+    let changed = match update_storage_json(directory, &keys, &formats, args, progress) {
+        Ok(changed) => changed,
+        Err(e) => {
+            let error = CleanerError::FileSystem {
+                operation: "updating storage.json".to_string(),
+                path: directory.join("storage.json").display().to_string(),
+                source: e.to_string(),
+            };
+            error_collector.add_error(error.clone());
+            progress.error(error);
+            Vec::new()
+        }
+    };
 
     // try to update machine id file if it's a file
     if directory.is_file() {
-        if let Err(e) = update_machine_id_file(directory, tx) {
+        if args.dry_run {
+            progress.log(format!("would regenerate the machine id in {}", directory.display()));
+        } else if let Err(e) = update_machine_id_file(directory, args.lock_level, &args.unlocked, progress) {
             let error = CleanerError::FileSystem {
                 operation: "updating machine id file".to_string(),
                 path: directory.display().to_string(),
                 source: e.to_string(),
             };
             error_collector.add_error(error.clone());
-            let _ = tx.send(ZenEvent::DetailedError(error));
+            progress.error(error);
+        }
+    }
+
+    // a directory that failed counts as reached too, or a limited run would keep retrying it instead of moving on
+    if args.limit.is_some() && !args.dry_run {
+        if let Err(e) = crate::filesystem::record_cleaned(directory) {
+            progress.warn(format!("could not note {} in the --limit journal: {}", directory.display(), e));
         }
     }
 
     // send error summary if there were any errors
     if error_collector.has_errors() {
-        let _ = tx.send(ZenEvent::ErrorSummary(error_collector));
+        progress.error_summary(error_collector);
         return Err("storage update encountered errors".into());
     }
 
-    Ok(())
+    Ok(changed)
 }
 
-fn update_storage_json(directory: &Path, tx: &mpsc::UnboundedSender<ZenEvent>) -> Result<()> {
+fn update_storage_json(directory: &Path, keys: &[String], formats: &HashMap<String, IdFormat>, args: &CliArgs, progress: &impl Progress) -> Result<Vec<KeyDiff>> {
     let storage_path = directory.join("storage.json");
     if !storage_path.exists() {
-        let _ = tx.send(ZenEvent::LogMessage(format!("storage.json not found in {} - already pure", directory.display())));
-        return Ok(());
+        progress.log(format!("storage.json not found in {} - already pure", directory.display()));
+        return Ok(Vec::new());
     }
 
-    let _ = tx.send(ZenEvent::LogMessage(format!("harmonizing energy patterns in: {}", storage_path.display())));
+    if let Some(target) = symlink_target(&storage_path).filter(|_| !args.follow_symlinks) {
+        progress.warn(format!("{} is a symlink to {} - left untouched, pass --follow-symlinks to write through it", storage_path.display(), target.display()));
+        return Ok(Vec::new());
+    }
 
-    let _permissions = match FilePermissions::backup_and_make_writable(&storage_path) {
-        Ok(perms) => Some(perms),
-        Err(e) => {
-            let _ = tx.send(ZenEvent::Warning(format!("could not modify permissions for storage.json: {}", e)));
-            None
+    // storage.json is a few kilobytes, so an enormous one is corrupt and reading it could exhaust memory
+    let size = fs::metadata(&storage_path).map(|m| m.len()).unwrap_or(0);
+    if size > args.max_file_size.saturating_mul(1024 * 1024) {
+        progress.warn(format!("{} is {}, over --max-file-size {} mb - likely corrupt, left unread", storage_path.display(), format_size(size), args.max_file_size));
+        return Ok(Vec::new());
+    }
+
+    if args.dry_run {
+        let existing = fs::read_to_string(&storage_path).map(|content| serde_json::from_str::<Map<String, Value>>(&content));
+        let Ok(Ok(existing)) = existing else {
+            progress.warn(format!("would leave {} alone - it is not a readable json object", storage_path.display()));
+            return Ok(Vec::new());
+        };
+        for key in keys {
+            let action = if existing.contains_key(key) { "replace existing" } else { "add new" };
+            progress.log(format!("would {} {} in {}", action, key, storage_path.display()));
         }
-    };
+        return Ok(Vec::new());
+    }
 
-    let content = match fs::read_to_string(&storage_path) {
-        Ok(content) => content,
-        Err(e) => {
-            return Err(format!("failed to read storage.json: {}", e).into());
+    // rewriting without a copy to fall back on is only done when asked for, and a file another module already changed keeps its first copy
+    if !args.no_backup && args.backups.first_backup(&storage_path) {
+        let mut warnings = ErrorCollector::new();
+        match backup_files(directory, std::slice::from_ref(&storage_path), &mut warnings) {
+            Ok(files) => {
+                for file in files {
+                    progress.log(format!("preserved original storage.json at {}", file.backup.display()));
+                }
+            }
+            Err(e) => {
+                return Err(CleanerError::FileSystem {
+                    operation: "backing up storage.json".to_string(),
+                    path: storage_path.display().to_string(),
+                    source: e.to_string(),
+                }.into());
+            }
         }
-    };
+        for warning in warnings.warnings {
+            progress.warn(warning);
+        }
+    }
+
+    progress.log(format!("harmonizing energy patterns in: {}", storage_path.display()));
+
+    // a file that doesn't parse may still hold the user's settings, so it is kept for them to repair rather than replaced
+    let mut opts = RewriteOpts { formats: formats.clone(), unlocked: args.unlocked.clone(), ..RewriteOpts::default() };
+    if args.mark {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        opts.values.insert(MARKER_KEY.to_string(), Value::from(timestamp));
+    }
+
+    let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+    let result = clean_storage_json(&storage_path, &keys, &opts, progress)?;
+
+    progress.log(format!("energy patterns successfully harmonized in storage ({} keys updated)", result.rotated.len()));
+    Ok(result.rotated.into_iter()
+        .map(|rotated| (rotated.key, rotated.old.unwrap_or_default(), rotated.new))
+        .collect())
+}
+
+/// Give `keys` fresh ids in one storage.json file, keeping every other key.
+///
+/// Keys without a format in `opts` get their usual one, a uuid for `telemetry.devDeviceId`
+/// and a sha256 for the rest. Nothing is backed up and no process is touched, so callers
+/// that embed the cleaner decide that for themselves. Pass `&NoProgress` to stay silent.
+pub fn clean_storage_json(path: &Path, keys: &[&str], opts: &RewriteOpts, progress: &impl Progress) -> Result<RewriteResult> {
+    if !path.is_file() {
+        return Err(format!("{} is not a file", path.display()).into());
+    }
+
+    let keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+    let (mut formats, _) = resolve_id_formats(&[], &keys);
+    formats.extend(opts.formats.iter().map(|(key, format)| (key.clone(), *format)));
+    let opts = RewriteOpts { formats, ..opts.clone() };
+
+    let result = rewrite_json_keys(path, &keys, IdFormat::Sha256, &opts)?;
+
+    for rotated in &result.rotated {
+        if rotated.collided {
+            progress.warn(format!("new {} matched the old one, generated another", rotated.key));
+        }
+        match &rotated.old {
+            Some(old) => progress.log(format!("replaced existing {}: {} -> {}", rotated.key, old, rotated.new)),
+            None => progress.log(format!("added new {}: {}", rotated.key, rotated.new)),
+        }
+    }
+    for warning in &result.warnings {
+        progress.warn(warning.clone());
+    }
+
+    Ok(result)
+}
+
+/// How `rewrite_json_keys` treats the file beyond regenerating the requested keys
+#[derive(Debug, Clone, Default)]
+pub struct RewriteOpts {
+    /// Per-key id formats, overriding the default format
+    pub formats: HashMap<String, IdFormat>,
+    /// Values written as given after the keys are regenerated, e.g. precomputed ids or a marker
+    pub values: Map<String, Value>,
+    /// Start from an empty object when the file isn't a json object instead of failing
+    pub reset_invalid: bool,
+    /// Where the run notes the file if it has to be unlocked for the write
+    pub unlocked: UnlockLedger,
+}
+
+/// One key given a new id by `rewrite_json_keys`
+#[derive(Debug, Clone)]
+pub struct RotatedKey {
+    pub key: String,
+    pub old: Option<String>,
+    pub new: String,
+    /// The first id drawn matched the old one and was replaced
+    pub collided: bool,
+}
+
+/// What `rewrite_json_keys` changed
+#[derive(Debug, Clone, Default)]
+pub struct RewriteResult {
+    pub rotated: Vec<RotatedKey>,
+    /// Why the original content was discarded, when `reset_invalid` had to be used
+    pub reset_reason: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Give each of `keys` in a json object file a fresh id of the right format, keeping every other key
+pub fn rewrite_json_keys(path: &Path, keys: &[String], format: IdFormat, opts: &RewriteOpts) -> Result<RewriteResult> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let mut result = RewriteResult::default();
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", name, e))?;
 
     let mut data: Map<String, Value> = match serde_json::from_str(&content) {
         Ok(data) => data,
-        Err(e) => {
-            let _ = tx.send(ZenEvent::Warning(format!("storage.json contains invalid json, creating new structure: {}", e)));
+        Err(e) if opts.reset_invalid => {
+            result.reset_reason = Some(e.to_string());
             Map::new()
         }
+        Err(e) => return Err(format!("{} is not a json object ({}) - left as it is", name, e).into()),
     };
 
-    let mut updated_keys = 0;
-    for &key in &TELEMETRY_KEYS {
-        if let Some(old_value) = data.get(key) {
-            let _ = tx.send(ZenEvent::LogMessage(format!("releasing old {}: {}", key, old_value.as_str().unwrap_or_default())));
-        }
+    // every value written, so the original text can be edited in place around them
+    let mut written = Map::new();
+    for key in keys {
+        let old = data.get(key).and_then(Value::as_str).map(str::to_string);
+        let key_format = opts.formats.get(key).copied().unwrap_or(format);
+        let (new, collided) = fresh_id(old.as_deref(), || generate_id(key_format))
+            .map_err(|e| format!("could not replace {}: {}", key, e))?;
+
+        data.insert(key.clone(), Value::String(new.clone()));
+        written.insert(key.clone(), Value::String(new.clone()));
+        result.rotated.push(RotatedKey { key: key.clone(), old, new, collided });
+    }
 
-        let new_value = if key == "telemetry.devDeviceId" {
-            Uuid::new_v4().to_string()
-        } else {
-            format!("{:x}", Sha256::digest(Uuid::new_v4().as_bytes()))
-        };
-        let _ = tx.send(ZenEvent::LogMessage(format!("manifesting new {}: {}", key, new_value)));
-        data.insert(key.to_string(), Value::String(new_value));
-        updated_keys += 1;
+    for (key, value) in &opts.values {
+        data.insert(key.clone(), value.clone());
+        written.insert(key.clone(), value.clone());
     }
 
-    let json_content = match serde_json::to_string_pretty(&data) {
-        Ok(content) => content,
+    // editing only the written values keeps the editor's formatting, key order and unknown keys byte for byte
+    let spliced = if result.reset_reason.is_none() { splice_json_values(&content, &written, &[], &data) } else { None };
+    let json_content = match spliced {
+        Some(spliced) => spliced,
+        None => serde_json::to_string_pretty(&data)
+            .map_err(|e| format!("failed to serialize updated {}: {}", name, e))?,
+    };
+
+    // the file is only unlocked for the write itself, and locked again whether or not the write went through
+    let permissions = match FilePermissions::backup_and_make_writable(path, &opts.unlocked) {
+        Ok(perms) => Some(perms),
         Err(e) => {
-            return Err(format!("failed to serialize updated storage.json: {}", e).into());
+            result.warnings.push(format!("could not modify permissions for {}: {}", name, e));
+            None
         }
     };
 
-    if let Err(e) = fs::write(&storage_path, json_content) {
-        return Err(format!("failed to write updated storage.json: {}", e).into());
+    let outcome = fs::write(path, json_content)
+        .map_err(|e| format!("failed to write updated {}: {}", name, e));
+
+    if let Some(permissions) = permissions {
+        if let Err(e) = permissions.restore(path) {
+            result.warnings.push(format!("could not restore permissions for {}: {}", name, e));
+        }
     }
 
-    if let Some(permissions) = _permissions {
-        if let Err(e) = permissions.restore(&storage_path) {
-            let _ = tx.send(ZenEvent::Warning(format!("could not restore permissions for storage.json: {}", e)));
+    outcome?;
+    Ok(result)
+}
+
+/// Remove `keys` from a json object file, editing its text in place where it can, returning the keys it held
+pub fn remove_json_keys(path: &Path, keys: &[String], unlocked: &UnlockLedger) -> Result<Vec<String>> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("failed to read {}: {}", name, e))?;
+    let mut data: Map<String, Value> = serde_json::from_str(&content)
+        .map_err(|e| format!("{} is not a json object ({}) - left as it is", name, e))?;
+
+    let removed: Vec<String> = keys.iter().filter(|key| data.remove(key.as_str()).is_some()).cloned().collect();
+    if removed.is_empty() {
+        return Ok(removed);
+    }
+
+    let json_content = match splice_json_values(&content, &Map::new(), &removed, &data) {
+        Some(spliced) => spliced,
+        None => serde_json::to_string_pretty(&data)?,
+    };
+
+    let permissions = FilePermissions::backup_and_make_writable(path, unlocked)?;
+    let outcome = fs::write(path, json_content);
+    let restored = permissions.restore(path);
+    outcome?;
+    restored?;
+    Ok(removed)
+}
+
+/// `content` with the top-level values of `written` replaced or appended and the members named in `removed` taken out,
+/// in place, or None when its layout can't be followed. The edit is only trusted when it parses back to `expected`.
+fn splice_json_values(content: &str, written: &Map<String, Value>, removed: &[String], expected: &Map<String, Value>) -> Option<String> {
+    let members = top_level_members(content)?;
+    let first = members.first()?;
+    let last = members.last()?;
+
+    // new keys copy the indentation and separator of the first member
+    let indent = &content[content[..first.key.start].rfind([',', '{'])? + 1..first.key.start];
+    let separator = &content[first.key.end..first.value.start];
+
+    let mut edits: Vec<(std::ops::Range<usize>, String)> = Vec::new();
+    let mut appended = String::new();
+    for (key, value) in written {
+        let value_text = serde_json::to_string(value).ok()?;
+        let existing: Vec<_> = members.iter().filter(|member| member.name == *key).collect();
+        if existing.is_empty() {
+            appended.push_str(&format!(",{}{}{}{}", indent, serde_json::to_string(key).ok()?, separator, value_text));
+        }
+        for member in existing {
+            edits.push((member.value.clone(), value_text.clone()));
         }
     }
+    edits.push((last.value.end..last.value.end, appended));
+
+    // a removed member goes with the comma before it, or with the one after it when nothing is kept before it
+    let is_removed = |member: &JsonMember| removed.contains(&member.name);
+    match members.iter().position(|member| !is_removed(member)) {
+        Some(kept) => {
+            if kept > 0 {
+                edits.push((first.key.start..members[kept].key.start, String::new()));
+            }
+            for (index, member) in members.iter().enumerate().skip(kept + 1).filter(|(_, member)| is_removed(member)) {
+                edits.push((members[index - 1].value.end..member.value.end, String::new()));
+            }
+        }
+        None => edits.push((first.key.start..last.value.end, String::new())),
+    }
 
-    let _ = tx.send(ZenEvent::LogMessage(format!("energy patterns successfully harmonized in storage ({} keys updated)", updated_keys)));
-    Ok(())
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    let mut spliced = content.to_string();
+    for (range, text) in edits {
+        spliced.replace_range(range, &text);
+    }
+
+    let reparsed: Map<String, Value> = serde_json::from_str(&spliced).ok()?;
+    (reparsed == *expected).then_some(spliced)
 }
 
-fn update_machine_id_file(file_path: &Path, tx: &mpsc::UnboundedSender<ZenEvent>) -> Result<()> {
-    let _ = tx.send(ZenEvent::LogMessage(format!("harmonizing essence in: {}", file_path.display())));
+/// A key and its value in a json object, as byte ranges of the text
+struct JsonMember {
+    name: String,
+    key: std::ops::Range<usize>,
+    value: std::ops::Range<usize>,
+}
 
-    let _permissions = if file_path.exists() {
-        Some(FilePermissions::backup_and_make_writable(file_path)?)
-    } else {
+/// The members of the object making up `content`, in file order
+fn top_level_members(content: &str) -> Option<Vec<JsonMember>> {
+    let bytes = content.as_bytes();
+    let skip_space = |mut at: usize| {
+        while at < bytes.len() && bytes[at].is_ascii_whitespace() {
+            at += 1;
+        }
+        at
+    };
+    let string_end = |start: usize| {
+        let mut at = start + 1;
+        while at < bytes.len() {
+            match bytes[at] {
+                b'\\' => at += 2,
+                b'"' => return Some(at + 1),
+                _ => at += 1,
+            }
+        }
         None
     };
 
+    let mut at = skip_space(0);
+    if bytes.get(at) != Some(&b'{') {
+        return None;
+    }
+    at += 1;
+
+    let mut members = Vec::new();
+    loop {
+        at = skip_space(at);
+        match bytes.get(at)? {
+            b'}' => return Some(members),
+            b',' if !members.is_empty() => at = skip_space(at + 1),
+            _ if members.is_empty() => {}
+            _ => return None,
+        }
+
+        if bytes.get(at) != Some(&b'"') {
+            return None;
+        }
+        let key = at..string_end(at)?;
+        let name: String = serde_json::from_str(&content[key.clone()]).ok()?;
+        at = skip_space(key.end);
+        if bytes.get(at) != Some(&b':') {
+            return None;
+        }
+
+        let value_start = skip_space(at + 1);
+        let mut depth = 0usize;
+        at = value_start;
+        while at < bytes.len() {
+            match bytes[at] {
+                b'"' => {
+                    at = string_end(at)?;
+                    continue;
+                }
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' if depth == 0 => break,
+                b'}' | b']' => depth -= 1,
+                b',' if depth == 0 => break,
+                _ => {}
+            }
+            at += 1;
+        }
+        let value_end = value_start + content[value_start..at].trim_end().len();
+        if value_end == value_start {
+            return None;
+        }
+        members.push(JsonMember { name, key, value: value_start..value_end });
+    }
+}
+
+/// Generate a fresh random id in the given format
+pub fn generate_id(format: IdFormat) -> String {
+    match format {
+        IdFormat::Uuid => Uuid::new_v4().to_string(),
+        IdFormat::Sha256 => format!("{:x}", Sha256::digest(Uuid::new_v4().as_bytes())),
+        IdFormat::Hex64 => format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()),
+        IdFormat::Guid => format!("{{{}}}", Uuid::new_v4().to_string().to_uppercase()),
+    }
+}
+
+const MACHINE_ID_ATTEMPTS: u32 = 3;
+
+/// How many times a generator may repeat the old id before it is treated as broken
+const FRESH_ID_ATTEMPTS: u32 = 8;
+
+/// Generate ids until one differs from `old`, reporting whether a repeat had to be thrown away
+pub fn fresh_id(old: Option<&str>, mut generate: impl FnMut() -> String) -> Result<(String, bool)> {
+    for attempt in 0..FRESH_ID_ATTEMPTS {
+        let value = generate();
+        if old != Some(value.as_str()) {
+            return Ok((value, attempt > 0));
+        }
+    }
+    Err(format!("id generator returned the old value {} times in a row", FRESH_ID_ATTEMPTS).into())
+}
+
+/// Write through a temporary sibling and rename it into place, so readers never see a missing or partial file
+fn write_atomically(file_path: &Path, content: &str) -> Result<()> {
+    let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = file_path.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4().simple()));
+
+    fs::write(&temp_path, content)?;
+    if let Err(e) = fs::rename(&temp_path, file_path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("failed to replace {}: {}", file_path.display(), e).into());
+    }
+
+    Ok(())
+}
+
+fn update_machine_id_file(file_path: &Path, lock_level: LockLevel, unlocked: &UnlockLedger, progress: &impl Progress) -> Result<()> {
+    progress.log(format!("harmonizing essence in: {}", file_path.display()));
+
+    // an immutable file from a previous run can't be replaced until the attribute is cleared
+    if lock_level == LockLevel::Immutable && file_path.exists() {
+        let _ = set_immutable(file_path, false);
+    }
+
     if file_path.exists() {
+        FilePermissions::backup_and_make_writable(file_path, unlocked)?;
         let old_uuid = fs::read_to_string(file_path).unwrap_or_default();
         if !old_uuid.is_empty() {
-            let _ = tx.send(ZenEvent::LogMessage(format!("releasing old essence: {}", old_uuid.trim())));
+            progress.log(format!("releasing old essence: {}", old_uuid.trim()));
         }
-        let _ = fs::remove_file(file_path);
     }
 
     let new_uuid = Uuid::new_v4().to_string();
-    let _ = tx.send(ZenEvent::LogMessage(format!("manifesting new essence: {}", new_uuid)));
+    progress.log(format!("manifesting new essence: {}", new_uuid));
+
+    // the editor may rewrite the id between our write and the lock, so verify and try again
+    let mut attempt = 1;
+    loop {
+        write_atomically(file_path, &new_uuid)?;
+        lock_file_permissions(file_path, unlocked)?;
+
+        if fs::read_to_string(file_path)?.trim() == new_uuid {
+            break;
+        }
+        if attempt == MACHINE_ID_ATTEMPTS {
+            return Err(format!("machine id kept being rewritten after {} attempts", MACHINE_ID_ATTEMPTS).into());
+        }
+
+        progress.warn(format!("{} was rewritten during regeneration, retrying", file_path.display()));
+        FilePermissions::backup_and_make_writable(file_path, unlocked)?;
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        attempt += 1;
+    }
+
+    if lock_level == LockLevel::Immutable {
+        match set_immutable(file_path, true) {
+            Ok(_) => {
+                progress.log(format!("essence sealed immutable - unlocking requires root: chattr -i {}", file_path.display()));
+            }
+            Err(e) => {
+                progress.warn(format!("could not make {} immutable, left read-only: {}", file_path.display(), e));
+            }
+        }
+    }
+
+    progress.log("essence successfully harmonized and protected".to_string());
+    Ok(())
+}
+
+/// Remove the `--mark` key from every discovered storage.json, leaving everything else untouched
+pub fn run_unmark(args: &CliArgs) -> Result<()> {
+    let mut unmarked = 0;
+
+    for directory in find_vscode_storage_directories(args)? {
+        let storage_path = directory.join("storage.json");
+        let Ok(content) = fs::read_to_string(&storage_path) else { continue; };
+        let Ok(data) = serde_json::from_str::<Map<String, Value>>(&content) else { continue; };
+
+        if !data.contains_key(MARKER_KEY) {
+            continue;
+        }
+
+        remove_json_keys(&storage_path, &[MARKER_KEY.to_string()], &args.unlocked)?;
 
-    fs::write(file_path, &new_uuid)?;
-    lock_file_permissions(file_path)?;
+        println!("removed marker from {}", storage_path.display());
+        unmarked += 1;
+    }
 
-    let _ = tx.send(ZenEvent::LogMessage("essence successfully harmonized and protected".to_string()));
+    println!("{} storage files unmarked", unmarked);
     Ok(())
 }
 
-pub fn lock_file_permissions(file_path: &Path) -> Result<()> {
+/// Make `file_path` read-only, counting it as locked again in `unlocked`
+pub fn lock_file_permissions(file_path: &Path, unlocked: &UnlockLedger) -> Result<()> {
     if !file_path.exists() {
         return Err(format!("File doesn't exist, can't lock: {}", file_path.display()).into());
     }
@@ -230,7 +693,246 @@ pub fn lock_file_permissions(file_path: &Path) -> Result<()> {
         }
     }
 
+    unlocked.relocked(file_path);
     Ok(())
 }
 
+/// Set or clear the linux immutable attribute, which stops even the owner from rewriting the file
+pub fn set_immutable(file_path: &Path, immutable: bool) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        let flag = if immutable { "+i" } else { "-i" };
+        let status = Command::new("chattr").arg(flag).arg(file_path).status()?;
+        if !status.success() {
+            return Err(format!("chattr {} failed (usually requires root)", flag).into());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (file_path, immutable);
+        Err("immutable locking is only supported on linux".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_file(name: &str, content: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("cleaner-storage-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("storage.json");
+        fs::write(&file, content).unwrap();
+        file
+    }
+
+    #[cfg(unix)]
+    fn mode(path: &Path) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[cfg(unix)]
+    fn make_readonly(path: &Path) {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o444)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_file_that_is_not_json_keeps_its_permissions() {
+        let file = scratch_file("invalid", "{ not json");
+        make_readonly(&file);
+
+        assert!(rewrite_json_keys(&file, &["telemetry.machineId".to_string()], IdFormat::Sha256, &RewriteOpts::default()).is_err());
+        assert_eq!(mode(&file), 0o444);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "{ not json");
+        let _ = fs::remove_dir_all(file.parent().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_readonly_file_is_locked_again_after_the_rewrite() {
+        let file = scratch_file("readonly", "{\"telemetry.machineId\": \"old\"}");
+        make_readonly(&file);
+
+        let result = rewrite_json_keys(&file, &["telemetry.machineId".to_string()], IdFormat::Sha256, &RewriteOpts::default()).unwrap();
+        assert_eq!(mode(&file), 0o444);
+        assert_eq!(result.rotated[0].old.as_deref(), Some("old"));
+        assert!(fs::read_to_string(&file).unwrap().contains(&result.rotated[0].new));
+        let _ = fs::remove_dir_all(file.parent().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_run_ledger_holds_only_the_files_it_unlocked() {
+        let locked = scratch_file("ledger-locked", "{\"telemetry.machineId\": \"old\"}");
+        let open = scratch_file("ledger-open", "{\"telemetry.machineId\": \"old\"}");
+        make_readonly(&locked);
+
+        let opts = RewriteOpts::default();
+        for file in [&locked, &open] {
+            rewrite_json_keys(file, &["telemetry.machineId".to_string()], IdFormat::Sha256, &opts).unwrap();
+        }
+
+        let files = opts.unlocked.files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, locked.display().to_string());
+        assert!(files[0].restored);
+        // another run's ledger starts empty
+        assert!(UnlockLedger::default().files().is_empty());
+        for file in [&locked, &open] {
+            let _ = fs::remove_dir_all(file.parent().unwrap());
+        }
+    }
+
+    fn removing(content: &str, keys: &[&str]) -> Option<String> {
+        let removed: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+        let mut expected: Map<String, Value> = serde_json::from_str(content).unwrap();
+        expected.retain(|key, _| !removed.contains(key));
+        splice_json_values(content, &Map::new(), &removed, &expected)
+    }
+
+    #[test]
+    fn removing_keys_keeps_the_rest_of_the_text() {
+        let content = "{\n\t\"a\": 1,\n\t\"b\": [1, 2],\n\t\"c\": \"x\",\n\t\"d\": {}\n}";
+        assert_eq!(removing(content, &["b"]).unwrap(), "{\n\t\"a\": 1,\n\t\"c\": \"x\",\n\t\"d\": {}\n}");
+        assert_eq!(removing(content, &["a"]).unwrap(), "{\n\t\"b\": [1, 2],\n\t\"c\": \"x\",\n\t\"d\": {}\n}");
+        assert_eq!(removing(content, &["d"]).unwrap(), "{\n\t\"a\": 1,\n\t\"b\": [1, 2],\n\t\"c\": \"x\"\n}");
+        assert_eq!(removing(content, &["a", "b", "d"]).unwrap(), "{\n\t\"c\": \"x\"\n}");
+        assert_eq!(removing(content, &["a", "b", "c", "d"]).unwrap(), "{\n\t\n}");
+    }
+
+    #[test]
+    fn remove_json_keys_only_touches_the_named_keys() {
+        let file = scratch_file("remove", "{\"cursorAuth/token\":\"t\",  \"telemetry.machineId\" : \"m\"}");
+        let removed = remove_json_keys(&file, &["cursorAuth/token".to_string(), "missing".to_string()], &UnlockLedger::default()).unwrap();
+        assert_eq!(removed, vec!["cursorAuth/token".to_string()]);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "{\"telemetry.machineId\" : \"m\"}");
+        let _ = fs::remove_dir_all(file.parent().unwrap());
+    }
+
+    #[test]
+    fn fresh_id_draws_again_when_the_new_id_repeats_the_old_one() {
+        let mut draws = vec!["new", "old"];
+        let (value, collided) = fresh_id(Some("old"), || draws.pop().unwrap().to_string()).unwrap();
+        assert_eq!(value, "new");
+        assert!(collided);
+    }
+
+    #[test]
+    fn fresh_id_keeps_the_first_draw_that_differs() {
+        assert_eq!(fresh_id(Some("old"), || "new".to_string()).unwrap(), ("new".to_string(), false));
+        assert_eq!(fresh_id(None, || "first".to_string()).unwrap(), ("first".to_string(), false));
+    }
+
+    #[test]
+    fn fresh_id_gives_up_on_a_generator_stuck_on_the_old_value() {
+        let mut calls = 0;
+        assert!(fresh_id(Some("old"), || { calls += 1; "old".to_string() }).is_err());
+        assert_eq!(calls, FRESH_ID_ATTEMPTS);
+    }
+
+    #[test]
+    fn rewrite_json_keys_changes_only_the_rotated_value() {
+        let content = "{\n\t\"zeta\": [1, 2],\n\t\"telemetry.machineId\": \"old\",\n\t\"alpha\": {\"nested\": true}\n}\n";
+        let file = scratch_file("rewrite-layout", content);
+
+        let result = rewrite_json_keys(&file, &["telemetry.machineId".to_string()], IdFormat::Sha256, &RewriteOpts::default()).unwrap();
+        let new = &result.rotated[0].new;
+        assert!(new.len() == 64 && new.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(fs::read_to_string(&file).unwrap(), content.replace("\"old\"", &format!("\"{}\"", new)));
+        let _ = fs::remove_dir_all(file.parent().unwrap());
+    }
+
+    #[test]
+    fn rewrite_json_keys_adds_missing_keys_in_their_own_format() {
+        let file = scratch_file("rewrite-formats", "{\"other\": 1}");
+        let mut opts = RewriteOpts::default();
+        opts.formats.insert("telemetry.devDeviceId".to_string(), IdFormat::Uuid);
+        opts.values.insert(MARKER_KEY.to_string(), Value::from(7));
+
+        let keys = ["telemetry.devDeviceId".to_string(), "telemetry.machineId".to_string()];
+        let result = rewrite_json_keys(&file, &keys, IdFormat::Hex64, &opts).unwrap();
+        let data: Map<String, Value> = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+
+        assert!(result.rotated.iter().all(|rotated| rotated.old.is_none()));
+        assert!(Uuid::parse_str(data["telemetry.devDeviceId"].as_str().unwrap()).is_ok());
+        assert_eq!(data["telemetry.machineId"].as_str().unwrap().len(), 64);
+        assert_eq!(data[MARKER_KEY], Value::from(7));
+        assert_eq!(data["other"], Value::from(1));
+        let _ = fs::remove_dir_all(file.parent().unwrap());
+    }
+
+    #[test]
+    fn rewrite_json_keys_resets_an_invalid_file_only_when_asked() {
+        let file = scratch_file("rewrite-reset", "[1, 2]");
+        let keys = ["telemetry.machineId".to_string()];
+        assert!(rewrite_json_keys(&file, &keys, IdFormat::Sha256, &RewriteOpts::default()).is_err());
+
+        let opts = RewriteOpts { reset_invalid: true, ..RewriteOpts::default() };
+        let result = rewrite_json_keys(&file, &keys, IdFormat::Sha256, &opts).unwrap();
+        let data: Map<String, Value> = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+        assert!(result.reset_reason.is_some());
+        assert_eq!(data.keys().collect::<Vec<_>>(), vec!["telemetry.machineId"]);
+        let _ = fs::remove_dir_all(file.parent().unwrap());
+    }
+
+    #[test]
+    fn clean_storage_json_gives_each_key_its_usual_format() {
+        let file = scratch_file("clean-standalone", "{\"telemetry.machineId\": \"old\", \"keep\": \"me\"}");
+        let keys = ["telemetry.machineId", "telemetry.devDeviceId"];
+        let result = clean_storage_json(&file, &keys, &RewriteOpts::default(), &crate::events::NoProgress).unwrap();
+        let data: Map<String, Value> = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+
+        assert_eq!(result.rotated.len(), 2);
+        assert_eq!(data["telemetry.machineId"].as_str().unwrap().len(), 64);
+        assert!(Uuid::parse_str(data["telemetry.devDeviceId"].as_str().unwrap()).is_ok());
+        assert_eq!(data["keep"], Value::from("me"));
+        let _ = fs::remove_dir_all(file.parent().unwrap());
+    }
+
+    #[test]
+    fn clean_storage_json_lets_the_caller_pick_formats() {
+        let file = scratch_file("clean-formats", "{}");
+        let mut opts = RewriteOpts::default();
+        opts.formats.insert("telemetry.machineId".to_string(), IdFormat::Guid);
 
+        let result = clean_storage_json(&file, &["telemetry.machineId"], &opts, &crate::events::NoProgress).unwrap();
+        let guid = &result.rotated[0].new;
+        assert!(guid.starts_with('{') && guid.ends_with('}') && guid.len() == 38);
+        let _ = fs::remove_dir_all(file.parent().unwrap());
+    }
+
+    #[test]
+    fn clean_storage_json_refuses_anything_but_a_file() {
+        let file = scratch_file("clean-directory", "{}");
+        let dir = file.parent().unwrap();
+        assert!(clean_storage_json(dir, &["telemetry.machineId"], &RewriteOpts::default(), &crate::events::NoProgress).is_err());
+        assert!(clean_storage_json(&dir.join("absent.json"), &["telemetry.machineId"], &RewriteOpts::default(), &crate::events::NoProgress).is_err());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_symlinked_storage_json_is_only_written_through_with_follow_symlinks() {
+        use clap::Parser;
+        let real = scratch_file("symlink-target", "{\"telemetry.machineId\": \"old\"}");
+        let storage = real.parent().unwrap().join("grove");
+        fs::create_dir_all(&storage).unwrap();
+        std::os::unix::fs::symlink(&real, storage.join("storage.json")).unwrap();
+
+        let args = CliArgs::parse_from(["cleaner", "--no-backup", "--telemetry-key", "telemetry.machineId"]);
+        assert!(update_vscode_storage(&storage, &args, &crate::events::NoProgress).unwrap().is_empty());
+        assert_eq!(fs::read_to_string(&real).unwrap(), "{\"telemetry.machineId\": \"old\"}");
+
+        let args = CliArgs { follow_symlinks: true, ..args };
+        assert_eq!(update_vscode_storage(&storage, &args, &crate::events::NoProgress).unwrap().len(), 1);
+        assert!(!fs::read_to_string(&real).unwrap().contains("\"old\""));
+        assert!(fs::symlink_metadata(storage.join("storage.json")).unwrap().file_type().is_symlink());
+        let _ = fs::remove_dir_all(real.parent().unwrap());
+    }
+}
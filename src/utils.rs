@@ -1,8 +1,12 @@
+use serde::Serialize;
+use std::collections::HashMap;
 use std::io::{self, Write};
 use std::fmt;
+use crate::cli::{IdFormat, IdFormatSpec};
 
 // enhanced error types for better error handling and user feedback
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum CleanerError {
     FileSystem {
         operation: String,
@@ -69,8 +73,21 @@ impl fmt::Display for CleanerError {
 
 impl std::error::Error for CleanerError {}
 
+impl CleanerError {
+    /// The file or directory the error is about, when it has one
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            CleanerError::FileSystem { path, .. }
+            | CleanerError::Database { path, .. }
+            | CleanerError::Permission { path, .. }
+            | CleanerError::Json { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
 // error collection for continuing operations despite failures
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct ErrorCollector {
     pub errors: Vec<CleanerError>,
     pub warnings: Vec<String>,
@@ -89,6 +106,11 @@ impl ErrorCollector {
         self.warnings.push(warning);
     }
 
+    pub fn extend(&mut self, other: ErrorCollector) {
+        self.errors.extend(other.errors);
+        self.warnings.extend(other.warnings);
+    }
+
     pub fn has_errors(&self) -> bool {
         !self.errors.is_empty()
     }
@@ -120,6 +142,9 @@ pub type CleanerResult<T> = std::result::Result<T, CleanerError>;
 
 pub const MACHINE_ID: &str = "machineId";
 
+/// Namespaced storage.json key written by `--mark`, well clear of anything the editor uses
+pub const MARKER_KEY: &str = "_cleaner.lastRun";
+
 pub const TELEMETRY_KEYS: [&str; 4] = [
     "telemetry.machineId",
     "telemetry.devDeviceId",
@@ -127,16 +152,155 @@ pub const TELEMETRY_KEYS: [&str; 4] = [
     "storage.serviceMachineId"
 ];
 
-pub const COUNT_QUERY: &str = "SELECT COUNT(*) FROM ItemTable WHERE key LIKE '%augment%';";
-pub const DELETE_QUERY: &str = "DELETE FROM ItemTable WHERE key LIKE '%augment%';";
+/// Normalize `--telemetry-key` values against the known and `--extra-keys` keys, returning the keys to rotate and any warnings.
+///
+/// Keys named in `preserve` are never returned, whatever else asked for them.
+pub fn resolve_telemetry_keys(requested: &[String], extra: &[String], allow_unknown: bool, preserve: &[String]) -> (Vec<String>, Vec<String>) {
+    let (mut keys, warnings) = requested_telemetry_keys(requested, extra, allow_unknown);
+    keys.retain(|key| !is_preserved(key, preserve));
+    (keys, warnings)
+}
+
+/// Whether `key` is one of the `--preserve-key` keys, which are compared case-insensitively like the others
+pub fn is_preserved(key: &str, preserve: &[String]) -> bool {
+    preserve.iter().any(|preserved| preserved.trim().eq_ignore_ascii_case(key))
+}
+
+fn requested_telemetry_keys(requested: &[String], extra: &[String], allow_unknown: bool) -> (Vec<String>, Vec<String>) {
+    let mut known: Vec<String> = TELEMETRY_KEYS.iter().map(|k| k.to_string()).collect();
+    for key in extra.iter().map(|k| k.trim()).filter(|k| !k.is_empty()) {
+        if !known.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+            known.push(key.to_string());
+        }
+    }
+
+    if requested.is_empty() {
+        return (known, Vec::new());
+    }
+
+    let mut keys: Vec<String> = Vec::new();
+    let mut warnings = Vec::new();
+
+    for raw in requested {
+        let trimmed = raw.trim();
+        if trimmed.is_empty() {
+            warnings.push("ignoring empty telemetry key".to_string());
+            continue;
+        }
+
+        let key = match known.iter().find(|known| known.eq_ignore_ascii_case(trimmed)) {
+            Some(known) => known.to_string(),
+            None if allow_unknown => {
+                warnings.push(format!("using unrecognized telemetry key '{}'", trimmed));
+                trimmed.to_string()
+            }
+            None => {
+                warnings.push(format!("ignoring unrecognized telemetry key '{}' (use --allow-unknown-keys to keep it)", trimmed));
+                continue;
+            }
+        };
+
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+
+    (keys, warnings)
+}
+
+/// The format each rotated key is regenerated in, along with warnings about unusual or conflicting choices
+pub fn resolve_id_formats(specs: &[IdFormatSpec], keys: &[String]) -> (HashMap<String, IdFormat>, Vec<String>) {
+    let mut warnings = Vec::new();
+
+    let mut default_format = None;
+    for spec in specs.iter().filter(|spec| spec.key.is_none()) {
+        if default_format.is_some_and(|format| format != spec.format) {
+            warnings.push(format!("conflicting --id-format defaults, using {}", spec.format));
+        }
+        default_format = Some(spec.format);
+    }
+
+    let mut formats: HashMap<String, IdFormat> = keys.iter()
+        .map(|key| {
+            let builtin = if key == "telemetry.devDeviceId" { IdFormat::Uuid } else { IdFormat::Sha256 };
+            (key.clone(), default_format.unwrap_or(builtin))
+        })
+        .collect();
+
+    let mut scoped: HashMap<String, IdFormat> = HashMap::new();
+    for spec in specs {
+        let Some(raw) = &spec.key else { continue; };
+        let Some(key) = keys.iter().find(|key| key.eq_ignore_ascii_case(raw)) else {
+            warnings.push(format!("ignoring --id-format for '{}' - it is not a rotated key", raw));
+            continue;
+        };
+
+        if let Some(previous) = scoped.insert(key.clone(), spec.format) {
+            if previous != spec.format {
+                warnings.push(format!("conflicting --id-format for {}, using {}", key, spec.format));
+            }
+        }
+        formats.insert(key.clone(), spec.format);
+    }
+
+    // the editor parses devDeviceId as a uuid, so anything else is likely to be thrown away
+    if let Some(format) = formats.get("telemetry.devDeviceId").filter(|f| !matches!(f, IdFormat::Uuid | IdFormat::Guid)) {
+        warnings.push(format!("telemetry.devDeviceId is normally a uuid, {} may be rejected by the editor", format));
+    }
 
-pub const VSCODE_PROCESSES: [&str; 14] = [
-    "code", "code.exe", "Code", "Code.exe",
-    "code-insiders", "code-insiders.exe",
-    "cursor", "cursor.exe", "Cursor", "Cursor.exe",
-    "windsurf", "windsurf.exe", "trae", "trae.exe"
+    (formats, warnings)
+}
+
+/// Matches every key mentioning augment, what is cleaned when no `--extension` is given
+pub const DEFAULT_ROW_PATTERN: &str = "%augment%";
+
+/// The ItemTable condition selecting the rows to delete, with the values bound to its placeholders.
+///
+/// Each `--extension` id matches its own state key, keys namespaced under it and the secrets it stored.
+pub fn extension_row_filter(extensions: &[String]) -> (String, Vec<String>) {
+    let ids: Vec<&str> = extensions.iter().map(|id| id.trim()).filter(|id| !id.is_empty()).collect();
+    if ids.is_empty() {
+        return ("key LIKE ?1".to_string(), vec![DEFAULT_ROW_PATTERN.to_string()]);
+    }
+
+    // like is case-insensitive, which suits extension ids; % and _ in an id must not act as wildcards
+    let patterns: Vec<String> = ids.iter()
+        .flat_map(|id| {
+            let id = id.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            [id.clone(), format!("{}.%", id), format!("secret://%\"extensionId\":\"{}\"%", id)]
+        })
+        .collect();
+
+    let condition = (1..=patterns.len())
+        .map(|n| format!("key LIKE ?{} ESCAPE '\\'", n))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+
+    (format!("({})", condition), patterns)
+}
+
+/// ItemTable keys that hold user-entered history, cleared with `--clean-history-keys`
+pub const HISTORY_KEY_PATTERNS: [&str; 4] = [
+    "workbench.%.history",
+    "terminal.history.%",
+    "commandPalette.mru.%",
+    "history.recentlyOpenedPathsList",
 ];
 
+/// The history key patterns to clear, falling back to the defaults when none were given
+pub fn history_key_patterns(requested: &[String]) -> Vec<String> {
+    let requested: Vec<String> = requested.iter()
+        .map(|pattern| pattern.trim().to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .collect();
+
+    if requested.is_empty() {
+        HISTORY_KEY_PATTERNS.iter().map(|p| p.to_string()).collect()
+    } else {
+        requested
+    }
+}
+
 pub fn pause_for_user_input(no_pause: bool) {
     if no_pause { return; }
     print!("\nPress Enter to exit...");
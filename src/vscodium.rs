@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+use crate::editor_clean::{clean_editor, EditorCleaningResult};
+use crate::events::Progress;
+use crate::filesystem::find_editor_storage_directories;
+use crate::utils::Result;
+
+/// Process names VSCodium runs under on each platform
+pub const VSCODIUM_PROCESSES: [&str; 4] = ["codium", "VSCodium", "codium.exe", "VSCodium.exe"];
+
+pub type VscodiumCleaningResult = EditorCleaningResult;
+
+/// Find VSCodium storage directories, kept under VSCodium/User rather than Code/User
+pub fn find_vscodium_storage_directories(args: &crate::cli::CliArgs) -> Result<Vec<PathBuf>> {
+    find_editor_storage_directories(args, "VSCodium")
+}
+
+/// Clean VSCodium the same way as VSCode, against its own directories
+pub async fn clean_vscodium(args: &crate::cli::CliArgs, progress: &(impl Progress + Sync)) -> Result<VscodiumCleaningResult> {
+    clean_editor("VSCodium", &VSCODIUM_PROCESSES, args, progress).await
+}
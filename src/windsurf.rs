@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+use crate::editor_clean::{clean_editor, EditorCleaningResult};
+use crate::events::Progress;
+use crate::filesystem::find_editor_storage_directories;
+use crate::utils::Result;
+
+/// Process names Windsurf runs under on each platform
+pub const WINDSURF_PROCESSES: [&str; 4] = ["Windsurf", "windsurf", "Windsurf.exe", "windsurf.exe"];
+
+pub type WindsurfCleaningResult = EditorCleaningResult;
+
+/// Find Windsurf storage directories, kept under Windsurf/User like Cursor's
+pub fn find_windsurf_storage_directories(args: &crate::cli::CliArgs) -> Result<Vec<PathBuf>> {
+    find_editor_storage_directories(args, "Windsurf")
+}
+
+/// Perform complete Windsurf IDE cleaning
+pub async fn clean_windsurf_ide(args: &crate::cli::CliArgs, progress: &(impl Progress + Sync)) -> Result<WindsurfCleaningResult> {
+    clean_editor("Windsurf", &WINDSURF_PROCESSES, args, progress).await
+}
@@ -0,0 +1,80 @@
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::backup::backup_files;
+use crate::filesystem::is_cleaner_artifact;
+use crate::storage::{FilePermissions, UnlockLedger};
+use crate::utils::{ErrorCollector, Result, TELEMETRY_KEYS};
+
+/// Folders never worth descending into when looking for project settings
+const SKIPPED_DIRS: [&str; 3] = ["node_modules", ".git", "target"];
+
+/// Find every `.vscode/settings.json` under `root`, descending at most `max_depth` levels
+pub fn find_workspace_settings(root: &Path, max_depth: usize) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+
+    while let Some((dir, depth)) = pending.pop() {
+        let settings = dir.join(".vscode").join("settings.json");
+        if settings.is_file() {
+            found.push(settings);
+        }
+
+        if depth >= max_depth {
+            continue;
+        }
+
+        let Ok(entries) = fs::read_dir(&dir) else { continue; };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let is_dir = entry.file_type().map(|ft| ft.is_dir()).unwrap_or(false);
+            let name = entry.file_name();
+            if is_dir && !SKIPPED_DIRS.iter().any(|skipped| name == *skipped) && !is_cleaner_artifact(&entry.path()) {
+                pending.push((entry.path(), depth + 1));
+            }
+        }
+    }
+
+    found.sort();
+    found
+}
+
+/// Identifier keys in a project settings file, as opposed to preferences like telemetry.telemetryLevel
+pub fn identifying_keys(settings: &Map<String, Value>) -> Vec<String> {
+    settings.keys()
+        .filter(|key| {
+            let lower = key.to_lowercase();
+            TELEMETRY_KEYS.iter().any(|known| known.eq_ignore_ascii_case(key))
+                || ["machineid", "deviceid", "sqmid"].iter().any(|id| lower.ends_with(id))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Read a project settings file as plain json, which fails for files with comments or trailing commas
+pub fn read_settings(path: &Path) -> Result<Map<String, Value>> {
+    let content = fs::read_to_string(path)?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("not plain json, left untouched ({})", e).into())
+}
+
+/// Back up a project settings file and remove its identifier keys, returning the keys removed
+pub fn sanitize_workspace_settings(path: &Path, unlocked: &UnlockLedger, warnings: &mut ErrorCollector) -> Result<Vec<String>> {
+    let mut settings = read_settings(path)?;
+    let keys = identifying_keys(&settings);
+    if keys.is_empty() {
+        return Ok(keys);
+    }
+
+    let vscode_dir = path.parent().unwrap_or(path);
+    backup_files(vscode_dir, &[path.to_path_buf()], warnings)?;
+
+    for key in &keys {
+        settings.remove(key);
+    }
+
+    let permissions = FilePermissions::backup_and_make_writable(path, unlocked)?;
+    fs::write(path, serde_json::to_string_pretty(&settings)?)?;
+    permissions.restore(path)?;
+
+    Ok(keys)
+}
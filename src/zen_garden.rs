@@ -10,59 +10,31 @@ use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
     widgets::{
-        Block, Borders, Gauge, List, ListItem, Paragraph,
+        Block, Borders, Clear, Gauge, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
     },
     Frame, Terminal,
 };
 use std::{
-    io,
+    cell::Cell,
+    io::{self, IsTerminal},
     time::{Duration, Instant},
 };
 use tokio::sync::mpsc;
 
-use crate::{
-    cli::CliArgs,
-    database::clean_vscode_databases,
-    filesystem::find_vscode_storage_directories,
-    storage::update_vscode_storage,
-};
-
-#[derive(Debug, Clone)]
-pub enum ZenEvent {
-    StartScanning,
-    ProcessFound(ProcessStone),
-    LocationFound(String),
-    ProcessTerminated(String),
-    StorageUpdated(String),
-    DatabaseCleaned(String),
-    OperationComplete,
-    Error(String),
-    DetailedError(crate::utils::CleanerError),
-    Warning(String),
-    LogMessage(String),
-    SetTotalOperations(usize),
-    ErrorSummary(crate::utils::ErrorCollector),
-}
+pub use crate::events::{ProcessStone, ZenEvent};
 
-#[derive(Debug, Clone)]
-pub struct ProcessStone {
-    pub name: String,
-    pub pid: u32,
-    pub path: String,
-    pub is_selected: bool,
-    pub is_terminated: bool,
-}
+use crate::cli::CliArgs;
 
-impl std::fmt::Display for ProcessStone {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} ({})", self.name, self.pid)
-    }
-}
+/// How long a pressed stone waits for the second press that closes it
+const STONE_CONFIRM_WINDOW: Duration = Duration::from_secs(3);
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ZenState {
     Welcome,
     CardSelection,
+    Overview,
+    Preflight,
+    DirectorySelection,
     Scanning,
     Processing,
     Complete,
@@ -81,6 +53,17 @@ pub struct CleaningCard {
 pub enum CardType {
     Augment,
     Cursor,
+    Windsurf,
+    VSCodium,
+}
+
+/// One row of the pre-clean overview tree: an install, a storage directory or a file in it
+#[derive(Debug, Clone)]
+pub struct OverviewNode {
+    pub depth: usize,
+    pub label: String,
+    pub expanded: bool,
+    pub has_children: bool,
 }
 
 pub struct ZenGarden {
@@ -102,6 +85,39 @@ pub struct ZenGarden {
     warnings: Vec<String>,
     cards: Vec<CleaningCard>,
     selected_card: usize,
+    overview: Vec<OverviewNode>,
+    overview_cursor: usize,
+    next_steps: Vec<String>,
+    run_summary: Vec<String>,
+    preflight_issues: Vec<crate::preflight::PermissionIssue>,
+    pending_cards: Vec<CardType>,
+    hold: Option<Duration>,
+    finished_at: Option<Instant>,
+    totals: crate::report::RunTotals,
+    cleaning_started: bool,
+    /// Storage and databases already changed, for the summary printed after an early quit
+    touched: Vec<String>,
+    run_log: crate::run_log::RunLog,
+    /// Discovered directories and whether each is ticked, for the checklist shown before cleaning
+    directory_choices: Vec<(std::path::PathBuf, bool)>,
+    directory_cursor: usize,
+    /// Set once the checklist was confirmed, or skipped because the run has no one to ask
+    directories_chosen: bool,
+    preflight_seen: bool,
+    /// A stone pressed once and when, closed only by a second press within STONE_CONFIRM_WINDOW
+    armed_stone: Option<(usize, Instant)>,
+    /// First log line shown once scrolled back, none while the log follows the newest events
+    log_top: Option<usize>,
+    /// Line count and height of the log pane at the last draw, for paging through it
+    log_view: Cell<(usize, usize)>,
+    show_help: bool,
+    /// Keys rewritten per directory, shown in place of the garden on the completion screen with d
+    key_diff: Vec<(String, Vec<crate::storage::KeyDiff>)>,
+    show_diff: bool,
+    /// Shared with the operations task, which stops at the next directory while it is set
+    pause: crate::events::PauseFlag,
+    /// How long a stone's process gets to close before it is killed, from --kill-timeout
+    grace: Duration,
 }
 
 impl ZenGarden {
@@ -119,10 +135,22 @@ impl ZenGarden {
                 is_selected: args.cursor,
                 card_type: CardType::Cursor,
             },
+            CleaningCard {
+                name: "Windsurf IDE".to_string(),
+                description: "Clean Windsurf IDE telemetry and state".to_string(),
+                is_selected: args.windsurf,
+                card_type: CardType::Windsurf,
+            },
+            CleaningCard {
+                name: "VSCodium".to_string(),
+                description: "Clean VSCodium telemetry and state".to_string(),
+                is_selected: args.vscodium,
+                card_type: CardType::VSCodium,
+            },
         ];
 
         // determine initial state based on CLI flags
-        let initial_state = if args.augment || args.cursor {
+        let initial_state = if args.has_module_flag() {
             ZenState::Scanning  // skip card selection and start immediately
         } else {
             ZenState::CardSelection  // show card selection screen
@@ -147,35 +175,56 @@ impl ZenGarden {
             warnings: Vec::new(),
             cards,
             selected_card: 0,
+            overview: Vec::new(),
+            overview_cursor: 0,
+            next_steps: Vec::new(),
+            run_summary: Vec::new(),
+            preflight_issues: Vec::new(),
+            pending_cards: Vec::new(),
+            hold: (args.hold > 0).then(|| Duration::from_secs(args.hold)),
+            finished_at: None,
+            totals: crate::report::RunTotals::default(),
+            cleaning_started: false,
+            touched: Vec::new(),
+            run_log: crate::run_log::RunLog::default(),
+            directory_choices: Vec::new(),
+            directory_cursor: 0,
+            directories_chosen: false,
+            preflight_seen: false,
+            armed_stone: None,
+            log_top: None,
+            log_view: Cell::new((0, 0)),
+            show_help: false,
+            key_diff: Vec::new(),
+            show_diff: false,
+            pause: crate::events::PauseFlag::default(),
+            grace: crate::process::grace_period(args),
         }
     }
 
+    /// How the last run went, from the errors and warnings it counted
+    pub fn outcome(&self) -> crate::report::Outcome {
+        self.totals.outcome()
+    }
+
     pub async fn run(&mut self, args: CliArgs) -> Result<()> {
         enable_raw_mode()?;
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+        execute!(stdout, EnterAlternateScreen)?;
+        if !args.no_mouse {
+            execute!(stdout, EnableMouseCapture)?;
+        }
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
 
         let (tx, mut rx) = mpsc::unbounded_channel();
+        self.open_log(&args);
 
         // spawn background task for operations if CLI flags are provided
-        if args.augment || args.cursor {
-            let tx_clone = tx.clone();
-            let args_clone = args.clone();
-
-            // determine selected cards based on CLI flags
-            let mut selected_cards = Vec::new();
-            if args.augment {
-                selected_cards.push(CardType::Augment);
-            }
-            if args.cursor {
-                selected_cards.push(CardType::Cursor);
-            }
-
-            tokio::spawn(async move {
-                zen_operations_with_cards(tx_clone, args_clone, selected_cards).await;
-            });
+        if args.has_module_flag() {
+            // module flags skip selection, the directory checklist included
+            self.directories_chosen = true;
+            self.begin_cleaning(flag_cards(&args), &args, &tx);
         }
 
         let mut last_tick = Instant::now();
@@ -187,43 +236,54 @@ impl ZenGarden {
             let timeout = tick_rate.saturating_sub(last_tick.elapsed());
             if crossterm::event::poll(timeout)? {
                 if let Event::Key(key) = event::read()? {
-                    if key.kind == KeyEventKind::Press {
+                    if key.kind == KeyEventKind::Press && self.show_help {
+                        // the overlay swallows keys until it is closed, the operation keeps going behind it
+                        if matches!(key.code, KeyCode::Char('?') | KeyCode::Esc) {
+                            self.show_help = false;
+                        }
+                    } else if key.kind == KeyEventKind::Press {
+                        // any key but a confirming one disarms a stone waiting to be closed
+                        if !matches!(key.code, KeyCode::Enter | KeyCode::Char(' ')) {
+                            self.armed_stone = None;
+                        }
+
                         match key.code {
                             KeyCode::Char('q') | KeyCode::Esc => {
                                 self.should_quit = true;
                                 break;
                             }
+                            KeyCode::Char('?') => {
+                                self.show_help = true;
+                            }
                             KeyCode::Enter => {
                                 if self.state == ZenState::Welcome {
                                     self.state = ZenState::CardSelection;
                                 } else if self.state == ZenState::CardSelection {
+                                    // show what was found before anything is touched
+                                    let selected_cards = self.selected_card_types();
+                                    if !selected_cards.is_empty() {
+                                        self.overview = build_overview(&selected_cards, &args);
+                                        self.overview_cursor = 0;
+                                        self.state = ZenState::Overview;
+                                    }
+                                } else if self.state == ZenState::Overview {
                                     // start cleaning with selected cards
-                                    let selected_cards: Vec<_> = self.cards.iter()
-                                        .filter(|c| c.is_selected)
-                                        .map(|c| c.card_type.clone())
-                                        .collect();
-
+                                    let selected_cards = self.selected_card_types();
                                     if !selected_cards.is_empty() {
-                                        self.state = ZenState::Scanning;
-
-                                        // spawn new background task with selected cards
-                                        let tx_ops = tx.clone();
-                                        let args_ops = args.clone();
-                                        tokio::spawn(async move {
-                                            zen_operations_with_cards(tx_ops, args_ops, selected_cards).await;
-                                        });
+                                        self.begin_cleaning(selected_cards, &args, &tx);
                                     }
-                                } else if self.state == ZenState::Scanning || self.state == ZenState::Processing {
-                                    // terminate selected process
-                                    if let Some(stone) = self.processes.get_mut(self.selected_stone) {
-                                        if !stone.is_terminated {
-                                            let pid = stone.pid;
-                                            let name = stone.name.clone();
-                                            stone.is_terminated = true;
-                                            let _ = tx.send(ZenEvent::ProcessTerminated(name));
-                                            self.terminate_process(pid);
-                                        }
+                                } else if self.state == ZenState::Preflight {
+                                    // the user has seen the permission problems and wants to go ahead
+                                    let pending = std::mem::take(&mut self.pending_cards);
+                                    self.begin_cleaning(pending, &args, &tx);
+                                } else if self.state == ZenState::DirectorySelection {
+                                    if self.directory_choices.iter().any(|(_, ticked)| *ticked) {
+                                        self.directories_chosen = true;
+                                        let pending = std::mem::take(&mut self.pending_cards);
+                                        self.begin_cleaning(pending, &args, &tx);
                                     }
+                                } else if self.state == ZenState::Scanning || self.state == ZenState::Processing {
+                                    self.press_stone(&tx);
                                 }
                             }
                             KeyCode::Char(' ') => {
@@ -232,25 +292,57 @@ impl ZenGarden {
                                     if let Some(card) = self.cards.get_mut(self.selected_card) {
                                         card.is_selected = !card.is_selected;
                                     }
-                                } else if self.state == ZenState::Scanning || self.state == ZenState::Processing {
-                                    // terminate selected process (same as Enter)
-                                    if let Some(stone) = self.processes.get_mut(self.selected_stone) {
-                                        if !stone.is_terminated {
-                                            let pid = stone.pid;
-                                            let name = stone.name.clone();
-                                            stone.is_terminated = true;
-                                            let _ = tx.send(ZenEvent::ProcessTerminated(name));
-                                            self.terminate_process(pid);
-                                        }
+                                } else if self.state == ZenState::Overview {
+                                    if let Some(node) = self.focused_overview_node() {
+                                        node.expanded = !node.expanded;
+                                    }
+                                } else if self.state == ZenState::DirectorySelection {
+                                    if let Some((_, ticked)) = self.directory_choices.get_mut(self.directory_cursor) {
+                                        *ticked = !*ticked;
                                     }
+                                } else if self.state == ZenState::Scanning || self.state == ZenState::Processing {
+                                    // same as Enter
+                                    self.press_stone(&tx);
                                 }
                             }
-                            KeyCode::Tab => {
-                                if self.state == ZenState::CardSelection {
-                                    // move to next card
-                                    self.selected_card = (self.selected_card + 1) % self.cards.len();
+                            KeyCode::Char('d') if self.state == ZenState::Complete && !self.key_diff.is_empty() => {
+                                self.show_diff = !self.show_diff;
+                            }
+                            KeyCode::Char('p') if matches!(self.state, ZenState::Scanning | ZenState::Processing) => {
+                                self.toggle_pause();
+                            }
+                            KeyCode::Char('r') if matches!(self.state, ZenState::Scanning | ZenState::Processing) => {
+                                self.rescan_stones();
+                            }
+                            KeyCode::PageUp if matches!(self.state, ZenState::Scanning | ZenState::Processing) => {
+                                let page = self.log_view.get().1.max(1) as isize;
+                                self.scroll_log(-page);
+                            }
+                            KeyCode::PageDown if matches!(self.state, ZenState::Scanning | ZenState::Processing) => {
+                                let page = self.log_view.get().1.max(1) as isize;
+                                self.scroll_log(page);
+                            }
+                            KeyCode::Char('k') if matches!(self.state, ZenState::Scanning | ZenState::Processing) => {
+                                self.scroll_log(-1);
+                            }
+                            KeyCode::Char('j') if matches!(self.state, ZenState::Scanning | ZenState::Processing) => {
+                                self.scroll_log(1);
+                            }
+                            KeyCode::Backspace if self.state == ZenState::Overview => {
+                                self.state = ZenState::CardSelection;
+                            }
+                            KeyCode::Left if self.state == ZenState::Overview => {
+                                self.collapse_overview_node();
+                            }
+                            KeyCode::Right if self.state == ZenState::Overview => {
+                                if let Some(node) = self.focused_overview_node() {
+                                    node.expanded = true;
                                 }
                             }
+                            KeyCode::Tab if self.state == ZenState::CardSelection => {
+                                // move to next card
+                                self.selected_card = (self.selected_card + 1) % self.cards.len();
+                            }
                             KeyCode::Up => {
                                 if self.state == ZenState::CardSelection {
                                     // move to previous card
@@ -259,6 +351,10 @@ impl ZenGarden {
                                     } else {
                                         self.selected_card = self.cards.len() - 1;
                                     }
+                                } else if self.state == ZenState::Overview {
+                                    self.overview_cursor = self.overview_cursor.saturating_sub(1);
+                                } else if self.state == ZenState::DirectorySelection {
+                                    self.directory_cursor = self.directory_cursor.saturating_sub(1);
                                 } else if !self.processes.is_empty() && self.selected_stone > 0 {
                                     self.selected_stone -= 1;
                                 }
@@ -267,6 +363,15 @@ impl ZenGarden {
                                 if self.state == ZenState::CardSelection {
                                     // move to next card
                                     self.selected_card = (self.selected_card + 1) % self.cards.len();
+                                } else if self.state == ZenState::Overview {
+                                    let visible = self.visible_overview().len();
+                                    if self.overview_cursor + 1 < visible {
+                                        self.overview_cursor += 1;
+                                    }
+                                } else if self.state == ZenState::DirectorySelection {
+                                    if self.directory_cursor + 1 < self.directory_choices.len() {
+                                        self.directory_cursor += 1;
+                                    }
                                 } else if !self.processes.is_empty() && self.selected_stone < self.processes.len() - 1 {
                                     self.selected_stone += 1;
                                 }
@@ -278,37 +383,196 @@ impl ZenGarden {
             }
 
             // handle zen events
+            let was_error = self.state == ZenState::Error;
             while let Ok(event) = rx.try_recv() {
                 self.handle_event(event);
             }
+            // a panic report may have been printed over the garden, so redraw it whole
+            if !was_error && self.state == ZenState::Error {
+                terminal.clear()?;
+            }
 
             if last_tick.elapsed() >= tick_rate {
+                self.update_progress();
                 self.update_animations();
                 last_tick = Instant::now();
             }
 
+            // auto-exit once the final screen has been held long enough
+            if let (Some(hold), Some(finished_at)) = (self.hold, self.finished_at) {
+                if finished_at.elapsed() >= hold {
+                    self.should_quit = true;
+                }
+            }
+
             if self.should_quit {
                 break;
             }
         }
 
+        // the modules stop at their next directory, paused or not, and at their next phase once the receiver is closed
+        self.pause.cancel();
+        drop(rx);
+
         disable_raw_mode()?;
-        execute!(
-            terminal.backend_mut(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        )?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        if !args.no_mouse {
+            execute!(terminal.backend_mut(), DisableMouseCapture)?;
+        }
         terminal.show_cursor()?;
 
+        // quitting mid-run leaves a partial clean, so say what was already changed
+        if self.cleaning_started && matches!(self.state, ZenState::Scanning | ZenState::Processing) {
+            println!("cleaning stopped early - {} of {} steps finished", self.completed_operations, self.total_operations);
+            if self.touched.is_empty() {
+                println!("nothing had been changed yet");
+            }
+            for location in &self.touched {
+                println!("already cleaned: {}", location);
+            }
+        }
+
+        if args.summary_json_line {
+            println!("{}", serde_json::to_string(&self.totals)?);
+        }
+        self.write_log(&args);
+
         Ok(())
     }
 
+    /// Start streaming plain --log-file lines, carrying on without the file when it cannot be opened
+    fn open_log(&mut self, args: &CliArgs) {
+        if let Some(path) = args.log_file.as_ref().filter(|_| args.log_format == crate::cli::LogFormat::Plain) {
+            if let Err(e) = self.run_log.stream_to(path) {
+                self.handle_event(ZenEvent::Warning(format!("{} - continuing without a log file", e)));
+            }
+        }
+    }
+
+    /// Save the event log to --log-file, warning instead of failing since the clean itself is done
+    fn write_log(&mut self, args: &CliArgs) {
+        if let Some(path) = &args.log_file {
+            if let Err(e) = self.run_log.write(path, args.log_format) {
+                eprintln!("warning: {}", e);
+            }
+        }
+    }
+
+    /// Clean the cards picked by flags without the interface, printing the log as plain lines
+    pub async fn run_headless(&mut self, args: CliArgs) -> Result<crate::report::Outcome> {
+        let cards = flag_cards(&args);
+
+        // --quiet keeps stdout for the result alone, so scripts can read it while progress goes to stderr
+        let progress = |line: &dyn std::fmt::Display| {
+            if args.quiet { eprintln!("{}", line) } else { println!("{}", line) }
+        };
+
+        // the same read-only look the overview screen gives, before anything is touched
+        let plan = crate::plan::build_plan_for(&args, &discover_targets(&cards, &args).map_err(|e| color_eyre::eyre::eyre!("{}", e))?);
+        for target in &plan.targets {
+            progress(&target.summary());
+        }
+        for process in &plan.processes {
+            progress(&format!("would terminate {}", process));
+        }
+
+        // a dry run goes through the same run and reporting below, the operations only record what they would do
+        // there is no one to tick directories without the interface
+        self.directories_chosen = true;
+        self.open_log(&args);
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        self.begin_cleaning(cards, &args, &tx);
+        drop(tx);
+
+        if self.state == ZenState::Preflight {
+            for issue in &self.preflight_issues {
+                eprintln!("{}: {}", issue.path.display(), issue.problem);
+            }
+            return Err(color_eyre::eyre::eyre!("preflight found {} permission problems", self.preflight_issues.len()));
+        }
+
+        // a bar on stderr shows how far along the run is, unless stderr is piped or --quiet asks for bare lines
+        let bar = (!args.quiet && io::stderr().is_terminal()).then(|| {
+            let bar = indicatif::ProgressBar::no_length();
+            bar.set_style(indicatif::ProgressStyle::with_template("{spinner} [{bar:30}] {pos}/{len} {percent:>3}% {prefix} {wide_msg}")
+                .unwrap_or_else(|_| indicatif::ProgressStyle::default_bar())
+                .progress_chars("=> "));
+            bar.enable_steady_tick(Duration::from_millis(120));
+            bar
+        });
+        // a piped stderr gets a plain progress line now and then instead
+        let progress_lines = !args.quiet && bar.is_none();
+        let started = Instant::now();
+        let mut last_progress_line: Option<(Instant, usize)> = None;
+
+        let mut printed = 0;
+        while let Some(event) = rx.recv().await {
+            let done = matches!(event, ZenEvent::OperationComplete);
+            self.handle_event(event);
+
+            for line in &self.events[printed..] {
+                match &bar {
+                    Some(bar) => bar.suspend(|| progress(line)),
+                    None => progress(line),
+                }
+            }
+            printed = self.events.len();
+
+            let remaining = time_left(started.elapsed(), self.completed_operations, self.total_operations)
+                .map(|left| format!("about {} left", format_time_left(left)))
+                .unwrap_or_default();
+            if let Some(bar) = &bar {
+                // a bar of length 0 reads 100%, so it stays without one until the steps are counted
+                if self.total_operations > 0 {
+                    bar.set_length(self.total_operations as u64);
+                }
+                bar.set_position(self.completed_operations as u64);
+                bar.set_prefix(remaining.clone());
+                bar.set_message(self.events.last().cloned().unwrap_or_else(|| self.current_operation.clone()));
+            }
+            let due = last_progress_line.is_none_or(|(at, completed)| completed != self.completed_operations && at.elapsed() >= PROGRESS_LINE_EVERY);
+            if progress_lines && self.total_operations > 0 && due {
+                let percent = self.completed_operations * 100 / self.total_operations;
+                eprintln!("progress: {}/{} ({}%) {}", self.completed_operations, self.total_operations, percent, remaining);
+                last_progress_line = Some((Instant::now(), self.completed_operations));
+            }
+
+            if done {
+                break;
+            }
+        }
+        if let Some(bar) = bar {
+            bar.finish_and_clear();
+        }
+
+        for line in self.run_summary.iter().chain(&self.next_steps) {
+            progress(&format!("• {}", line));
+        }
+        if args.summary_json_line {
+            println!("{}", serde_json::to_string(&self.totals)?);
+        }
+        self.write_log(&args);
+
+        let outcome = self.totals.outcome();
+        let color = !args.no_color && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal();
+        println!("{}", outcome.banner(color));
+
+        Ok(outcome)
+    }
+
     fn handle_event(&mut self, event: ZenEvent) {
+        self.run_log.record(&event);
+
         match event {
             ZenEvent::StartScanning => {
                 self.state = ZenState::Scanning;
                 self.current_operation = "scanning digital landscape...".to_string();
             }
+            ZenEvent::PhaseStarted(_) => {}
+            ZenEvent::KeysChanged(location, changed) => {
+                self.key_diff.push((location, changed));
+            }
             ZenEvent::ProcessFound(process) => {
                 self.processes.push(process.clone());
                 self.events.push(format!("discovered restless spirit: {}", process));
@@ -319,41 +583,46 @@ impl ZenGarden {
             }
             ZenEvent::ProcessTerminated(process) => {
                 self.events.push(format!("gently guided {} to peaceful rest", process));
-                self.completed_operations += 1;
-                self.update_progress();
             }
             ZenEvent::StorageUpdated(location) => {
+                if !self.touched.contains(&location) {
+                    self.touched.push(location.clone());
+                }
                 self.events.push(format!("cleansed energy patterns in {}", location));
-                self.completed_operations += 1;
-                self.update_progress();
             }
             ZenEvent::DatabaseCleaned(location) => {
+                if !self.touched.contains(&location) {
+                    self.touched.push(location.clone());
+                }
                 self.events.push(format!("purified data streams in {}", location));
-                self.completed_operations += 1;
-                self.update_progress();
             }
             ZenEvent::OperationComplete => {
+                self.finished_at.get_or_insert_with(Instant::now);
                 self.state = ZenState::Complete;
+                self.totals.completed = true;
                 self.current_operation = "digital harmony achieved".to_string();
                 self.progress = 1.0;
             }
             ZenEvent::Error(error) => {
+                self.totals.errors += 1;
                 self.state = ZenState::Error;
                 self.events.push(format!("encountered turbulence: {}", error));
             }
             ZenEvent::DetailedError(error) => {
+                self.totals.errors += 1;
                 self.detailed_errors.push(error.clone());
                 self.error_collector.add_error(error.clone());
                 self.events.push(format!("turbulence detected: {}", error));
                 // don't immediately switch to error state - collect errors and continue
             }
             ZenEvent::Warning(warning) => {
+                self.totals.warnings += 1;
                 self.warnings.push(warning.clone());
                 self.error_collector.add_warning(warning.clone());
                 self.events.push(format!("gentle warning: {}", warning));
             }
             ZenEvent::ErrorSummary(collector) => {
-                self.error_collector = collector.clone();
+                // its errors and warnings came as events of their own and were counted there
                 if collector.has_errors() {
                     self.state = ZenState::Error;
                     self.current_operation = format!("meditation disrupted - {}", collector.get_summary());
@@ -364,6 +633,20 @@ impl ZenGarden {
             ZenEvent::LogMessage(message) => {
                 self.events.push(message);
             }
+            ZenEvent::NextSteps(steps) => {
+                self.next_steps = steps;
+            }
+            ZenEvent::RunSummary(lines) => {
+                self.run_summary = lines;
+            }
+            ZenEvent::RunTotals(totals) => {
+                // error counts come from the events already seen, not the report
+                self.totals = crate::report::RunTotals { errors: self.totals.errors, warnings: self.totals.warnings, ..totals };
+            }
+            ZenEvent::OperationsAdvanced(steps) => {
+                self.completed_operations = (self.completed_operations + steps).min(self.total_operations);
+                self.update_progress();
+            }
             ZenEvent::SetTotalOperations(total) => {
                 self.total_operations = total;
                 self.completed_operations = 0;
@@ -372,21 +655,180 @@ impl ZenGarden {
         }
     }
 
-    fn update_progress(&mut self) {
-        if self.total_operations > 0 {
-            self.progress = self.completed_operations as f64 / self.total_operations as f64;
+    /// Spawn the cleaning operations, pausing on the preflight screen first when --preflight finds problems
+    fn begin_cleaning(&mut self, cards: Vec<CardType>, args: &CliArgs, tx: &mpsc::UnboundedSender<ZenEvent>) {
+        if args.preflight && !self.preflight_seen {
+            self.preflight_issues = crate::preflight::check_permissions(&discover_targets(&cards, args).unwrap_or_default(), !args.no_signout, &args.db_files);
+            if !self.preflight_issues.is_empty() {
+                self.preflight_seen = true;
+                self.pending_cards = cards;
+                self.state = ZenState::Preflight;
+                return;
+            }
+        }
+
+        // a single directory leaves nothing to choose between
+        if !self.directories_chosen {
+            // with --strict-discovery a failure shows up when the modules run, not as an empty checklist blocking them
+            let targets = discover_targets(&cards, args).unwrap_or_default();
+            if targets.len() > 1 {
+                self.directory_choices = targets.into_iter().map(|dir| (dir, true)).collect();
+                self.directory_cursor = 0;
+                self.pending_cards = cards;
+                self.state = ZenState::DirectorySelection;
+                return;
+            }
+        }
+
+        // a second start while one run is going would clean everything twice
+        if self.cleaning_started {
+            return;
+        }
+        self.cleaning_started = true;
+        self.state = ZenState::Scanning;
+
+        // spawn new background task with selected cards
+        let tx_ops = tx.clone();
+        let mut args_ops = args.clone();
+        args_ops.deselected = self.directory_choices.iter()
+            .filter(|(_, ticked)| !ticked)
+            .map(|(dir, _)| dir.clone())
+            .collect();
+        args_ops.pause = self.pause.clone();
+        tokio::spawn(async move {
+            // a panic would otherwise leave the garden waiting for a completion that never comes
+            let tx_panic = tx_ops.clone();
+            if let Err(e) = tokio::spawn(zen_operations_with_cards(tx_ops, args_ops, cards)).await {
+                if e.is_panic() {
+                    let _ = tx_panic.send(ZenEvent::Error(format!("the cleaning run panicked - {}", panic_cause(e))));
+                }
+            }
+        });
+    }
+
+    fn selected_card_types(&self) -> Vec<CardType> {
+        self.cards.iter()
+            .filter(|c| c.is_selected)
+            .map(|c| c.card_type.clone())
+            .collect()
+    }
+
+    /// Indices of the overview nodes not hidden under a collapsed parent
+    fn visible_overview(&self) -> Vec<usize> {
+        let mut visible = Vec::new();
+        let mut hidden_below: Option<usize> = None;
+
+        for (idx, node) in self.overview.iter().enumerate() {
+            if let Some(depth) = hidden_below {
+                if node.depth > depth {
+                    continue;
+                }
+                hidden_below = None;
+            }
+
+            visible.push(idx);
+            if node.has_children && !node.expanded {
+                hidden_below = Some(node.depth);
+            }
+        }
+
+        visible
+    }
+
+    fn focused_overview_node(&mut self) -> Option<&mut OverviewNode> {
+        let idx = *self.visible_overview().get(self.overview_cursor)?;
+        self.overview.get_mut(idx).filter(|node| node.has_children)
+    }
+
+    fn collapse_overview_node(&mut self) {
+        let visible = self.visible_overview();
+        let Some(&idx) = visible.get(self.overview_cursor) else { return; };
+
+        let node = &mut self.overview[idx];
+        if node.has_children && node.expanded {
+            node.expanded = false;
+            return;
+        }
+
+        // already collapsed or a leaf - jump to the parent instead
+        let depth = node.depth;
+        if let Some(parent) = visible[..self.overview_cursor].iter().rposition(|&i| self.overview[i].depth < depth) {
+            self.overview_cursor = parent;
         }
     }
 
+    /// Recompute the gauge from the operation counts, every tick, so a burst of events can't leave it behind them
+    fn update_progress(&mut self) {
+        self.progress = if self.state == ZenState::Complete {
+            1.0
+        } else if self.total_operations == 0 {
+            0.0
+        } else {
+            (self.completed_operations as f64 / self.total_operations as f64).clamp(0.0, 1.0)
+        };
+    }
+
     fn update_animations(&mut self) {
+        // an unconfirmed stone quietly settles back once its window has passed
+        if self.armed_stone.is_some_and(|(_, at)| at.elapsed() > STONE_CONFIRM_WINDOW) {
+            self.armed_stone = None;
+        }
+
         let elapsed = self.start_time.elapsed().as_secs_f64();
         self.breathing_phase = (elapsed * 0.5).sin() * 0.5 + 0.5;
         self.water_flow = (elapsed * 2.0) as usize % 20;
     }
 
+    /// Add processes started since the last scan, e.g. respawned helpers, keeping known stones as they are
+    fn rescan_stones(&mut self) {
+        let selected_pid = self.processes.get(self.selected_stone).map(|stone| stone.pid);
+        let mut added = 0;
+
+        for stone in discover_vscode_processes() {
+            match self.processes.iter_mut().find(|known| known.pid == stone.pid) {
+                // a closed stone stays closed, but its tree may have grown
+                Some(known) => known.children = stone.children,
+                None => {
+                    self.processes.push(stone);
+                    added += 1;
+                }
+            }
+        }
+
+        self.selected_stone = selected_pid
+            .and_then(|pid| self.processes.iter().position(|stone| stone.pid == pid))
+            .unwrap_or(0)
+            .min(self.processes.len().saturating_sub(1));
+        self.events.push(format!("looked again - {} new restless spirits", added));
+    }
+
+    /// First press marks the selected stone, a second press in time kills its process tree
+    fn press_stone(&mut self, tx: &mpsc::UnboundedSender<ZenEvent>) {
+        let confirmed = self.armed_stone
+            .is_some_and(|(stone, at)| stone == self.selected_stone && at.elapsed() <= STONE_CONFIRM_WINDOW);
+
+        let Some(stone) = self.processes.get_mut(self.selected_stone) else { return; };
+        if stone.is_terminated {
+            return;
+        }
+
+        if !confirmed {
+            self.armed_stone = Some((self.selected_stone, Instant::now()));
+            return;
+        }
+
+        self.armed_stone = None;
+        let pid = stone.pid;
+        let name = stone.name.clone();
+        stone.is_terminated = true;
+        let _ = tx.send(ZenEvent::ProcessTerminated(name));
+        self.terminate_process(pid);
+    }
+
     fn terminate_process(&self, pid: u32) {
-        use kill_tree::blocking::kill_tree;
-        let _ = kill_tree(pid);
+        // waiting out the grace period here would freeze the garden
+        let grace = self.grace;
+        std::thread::spawn(move || crate::process::terminate_gracefully(&[pid], grace));
     }
 
     fn ui(&self, f: &mut Frame) {
@@ -407,10 +849,92 @@ impl ZenGarden {
         match self.state {
             ZenState::Welcome => self.render_welcome(f, inner),
             ZenState::CardSelection => self.render_card_selection(f, inner),
+            ZenState::Overview => self.render_overview(f, inner),
+            ZenState::Preflight => self.render_preflight(f, inner),
+            ZenState::DirectorySelection => self.render_directory_selection(f, inner),
             ZenState::Scanning | ZenState::Processing => self.render_meditation(f, inner),
             ZenState::Complete => self.render_enlightenment(f, inner),
             ZenState::Error => self.render_turbulence(f, inner),
         }
+
+        if self.show_help {
+            self.render_help(f, size);
+        }
+    }
+
+    /// Hold or release the operations task, which notices at its next directory
+    fn toggle_pause(&mut self) {
+        let message = if self.pause.toggle() {
+            "paused - the current directory finishes, then the garden waits"
+        } else {
+            "resumed - the garden continues"
+        };
+        self.events.push(message.to_string());
+    }
+
+    /// The keys that do something in the current state, as (keys, action)
+    fn help_entries(&self) -> Vec<(&'static str, &'static str)> {
+        let mut entries = match self.state {
+            ZenState::Welcome => vec![("enter", "choose cleaning modules")],
+            ZenState::CardSelection => vec![
+                ("↑↓ / tab", "move between cards"),
+                ("space", "tick or untick a card"),
+                ("enter", "look at what the ticked cards would clean"),
+            ],
+            ZenState::Overview => vec![
+                ("↑↓", "move"),
+                ("←→ / space", "fold or unfold"),
+                ("enter", "begin cleaning"),
+                ("backspace", "back to the cards"),
+            ],
+            ZenState::Preflight => vec![("enter", "clean anyway")],
+            ZenState::DirectorySelection => vec![
+                ("↑↓", "move"),
+                ("space", "tick or untick a directory"),
+                ("enter", "clean the ticked directories"),
+            ],
+            ZenState::Scanning | ZenState::Processing => vec![
+                ("↑↓", "select a stone"),
+                ("enter / space", "close the selected process, pressed twice"),
+                ("r", "look for editor processes again"),
+                ("p", "pause or resume before the next directory"),
+                ("pgup / pgdn", "scroll the log a page"),
+                ("k / j", "scroll the log a line"),
+            ],
+            ZenState::Complete if !self.key_diff.is_empty() => vec![("d", "show or hide the keys that were changed")],
+            ZenState::Complete | ZenState::Error => Vec::new(),
+        };
+        entries.push(("q / esc", "quit"));
+        entries.push(("?", "show or hide this help"));
+        entries
+    }
+
+    fn render_help(&self, f: &mut Frame, area: Rect) {
+        let entries = self.help_entries();
+        let width = 64.min(area.width);
+        let height = (entries.len() as u16 + 4).min(area.height);
+        let overlay = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        let help_block = Block::default()
+            .title("🔔 keys")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan))
+            .style(Style::default().bg(Color::Black));
+
+        let mut lines = vec![Line::from("")];
+        lines.extend(entries.iter().map(|(keys, action)| Line::from(vec![
+            Span::styled(format!("{:>15}  ", keys), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(*action, Style::default().fg(Color::White)),
+        ])));
+
+        f.render_widget(Clear, overlay);
+        f.render_widget(Paragraph::new(lines).block(help_block), overlay);
     }
 
     fn render_welcome(&self, f: &mut Frame, area: Rect) {
@@ -460,7 +984,7 @@ impl ZenGarden {
         // instructions
         let instructions = Paragraph::new(Text::from(vec![
             Line::from(Span::styled(
-                "press [enter] to scan for processes • [↑↓] to select stones • [enter] to close • [q] to exit",
+                "press [enter] to scan for processes • [↑↓] to select stones • [enter] twice to close • [?] for help • [q] to exit",
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::DIM),
             )),
         ]))
@@ -489,7 +1013,7 @@ impl ZenGarden {
 
         // automatic grid layout for cards
         let cards_per_row = 3; // can be adjusted for more cards later
-        let card_rows = (self.cards.len() + cards_per_row - 1) / cards_per_row;
+        let card_rows = self.cards.len().div_ceil(cards_per_row);
 
         // create row constraints
         let row_constraints: Vec<Constraint> = (0..card_rows)
@@ -527,9 +1051,9 @@ impl ZenGarden {
         // instructions
         let selected_count = self.cards.iter().filter(|c| c.is_selected).count();
         let instruction_text = if selected_count > 0 {
-            format!("space: toggle • tab: next • enter: run {} module(s) • q: quit", selected_count)
+            format!("space: toggle • tab: next • enter: run {} module(s) • ?: help • q: quit", selected_count)
         } else {
-            "space: toggle • tab: next • enter: run (select at least one) • q: quit".to_string()
+            "space: toggle • tab: next • enter: run (select at least one) • ?: help • q: quit".to_string()
         };
 
         let instructions = Paragraph::new(Line::from(vec![
@@ -539,60 +1063,237 @@ impl ZenGarden {
         f.render_widget(instructions, chunks[2]);
     }
 
-    fn render_cleaning_card(&self, f: &mut Frame, area: Rect, card: &CleaningCard, is_focused: bool) {
-        let border_color = if is_focused {
-            Color::Yellow
-        } else if card.is_selected {
-            Color::Green
-        } else {
-            Color::Gray
-        };
+    fn render_overview(&self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(5),
+                Constraint::Length(3),
+            ])
+            .split(area);
 
-        let border_style = if is_focused {
-            Modifier::BOLD
+        let title = Paragraph::new(Line::from(vec![
+            Span::styled("🌿 ", Style::default().fg(Color::Green)),
+            Span::styled("the garden before tending", Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+            Span::styled(" 🌿", Style::default().fg(Color::Green)),
+        ]))
+        .alignment(Alignment::Center);
+        f.render_widget(title, chunks[0]);
+
+        let tree_block = Block::default()
+            .title("🌳 discovered locations")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Green));
+
+        let tree_area = tree_block.inner(chunks[1]);
+        f.render_widget(tree_block, chunks[1]);
+
+        let visible = self.visible_overview();
+        let height = tree_area.height as usize;
+        // keep the cursor on screen once the tree outgrows the panel
+        let offset = (self.overview_cursor + 1).saturating_sub(height);
+
+        let items: Vec<ListItem> = if visible.is_empty() {
+            vec![ListItem::new("nothing found - the garden is already pure")]
         } else {
-            Modifier::empty()
-        };
+            visible.iter()
+                .enumerate()
+                .skip(offset)
+                .take(height)
+                .map(|(row, &idx)| {
+                    let node = &self.overview[idx];
+                    let marker = match (node.has_children, node.expanded) {
+                        (true, true) => "▾ ",
+                        (true, false) => "▸ ",
+                        (false, _) => "  ",
+                    };
+                    let color = match node.depth {
+                        0 => Color::Cyan,
+                        1 => Color::White,
+                        _ => Color::Gray,
+                    };
 
-        // compact card title
-        let short_name = match card.card_type {
-            CardType::Augment => "Augment",
-            CardType::Cursor => "Cursor",
+                    let mut style = Style::default().fg(color);
+                    if row == self.overview_cursor {
+                        style = style.fg(Color::Yellow).add_modifier(Modifier::BOLD);
+                    }
+
+                    ListItem::new(format!("{}{}{}", "  ".repeat(node.depth), marker, node.label)).style(style)
+                })
+                .collect()
         };
 
-        let card_block = Block::default()
-            .title(format!(" {} ", short_name))
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(border_color).add_modifier(border_style));
+        f.render_widget(List::new(items), tree_area);
 
-        let card_area = card_block.inner(area);
-        f.render_widget(card_block, area);
+        let instructions = Paragraph::new(Line::from(vec![
+            Span::styled(
+                "↑↓: move • ←→/space: fold • enter: begin cleaning • backspace: back • q: quit",
+                Style::default().fg(Color::Cyan).add_modifier(Modifier::ITALIC),
+            ),
+        ]))
+        .alignment(Alignment::Center);
+        f.render_widget(instructions, chunks[2]);
+    }
 
-        // compact content layout
-        let content_chunks = Layout::default()
+    fn render_directory_selection(&self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(1), // icon line
-                Constraint::Length(1), // status line
-                Constraint::Length(1), // focus indicator
+                Constraint::Length(3),
+                Constraint::Min(5),
+                Constraint::Length(3),
             ])
-            .split(card_area);
-
-        // icon
-        let icon = match card.card_type {
-            CardType::Augment => "🔧",
-            CardType::Cursor => "🖱️",
-        };
+            .split(area);
 
-        let icon_widget = Paragraph::new(Line::from(Span::styled(
-            icon,
-            Style::default().add_modifier(Modifier::BOLD),
+        let ticked = self.directory_choices.iter().filter(|(_, ticked)| *ticked).count();
+        let title = Paragraph::new(Line::from(Span::styled(
+            format!("🪨 {} of {} groves will be cleaned", ticked, self.directory_choices.len()),
+            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
         )))
         .alignment(Alignment::Center);
-        f.render_widget(icon_widget, content_chunks[0]);
+        f.render_widget(title, chunks[0]);
 
-        // status
-        let status_text = if card.is_selected { "✓ selected" } else { "○ not selected" };
+        let stones_block = Block::default()
+            .title("🪨 directory stones (↑↓ to move, space to toggle)")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Green));
+
+        let stones_area = stones_block.inner(chunks[1]);
+        f.render_widget(stones_block, chunks[1]);
+
+        // the same stones as the process list: solid for kept, empty for left alone
+        let visible = stones_area.height as usize;
+        let first = self.directory_cursor.saturating_sub(visible.saturating_sub(1));
+        let stone_items: Vec<ListItem> = self.directory_choices.iter()
+            .enumerate()
+            .skip(first)
+            .take(visible)
+            .map(|(i, (dir, ticked))| {
+                let symbol = if *ticked { "●" } else { "○" };
+                let style = if i == self.directory_cursor {
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                } else if *ticked {
+                    Style::default().fg(Color::White)
+                } else {
+                    Style::default().fg(Color::DarkGray)
+                };
+                ListItem::new(format!("{} {}", symbol, dir.display())).style(style)
+            })
+            .collect();
+
+        f.render_widget(List::new(stone_items), stones_area);
+
+        let instructions = Paragraph::new(Line::from(Span::styled(
+            "space: toggle • enter: clean the ticked directories • q: quit",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::ITALIC),
+        )))
+        .alignment(Alignment::Center);
+        f.render_widget(instructions, chunks[2]);
+    }
+
+    fn render_preflight(&self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(5),
+                Constraint::Length(3),
+            ])
+            .split(area);
+
+        let blocked = self.preflight_issues.iter().filter(|issue| !issue.fixable).count();
+        let title = Paragraph::new(Line::from(Span::styled(
+            format!("🪨 {} stones on the path - {} blocked, {} the cleaner can move", self.preflight_issues.len(), blocked, self.preflight_issues.len() - blocked),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        )))
+        .alignment(Alignment::Center);
+        f.render_widget(title, chunks[0]);
+
+        let issues_block = Block::default()
+            .title("🔒 permission check")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow));
+
+        let issues_area = issues_block.inner(chunks[1]);
+        f.render_widget(issues_block, chunks[1]);
+
+        let issue_items: Vec<ListItem> = self.preflight_issues.iter()
+            .map(|issue| {
+                let (marker, color) = if issue.fixable { ("○", Color::Yellow) } else { ("✗", Color::Red) };
+                let text = format!("{} {} - {}", marker, issue.path.display(), issue.problem);
+                ListItem::new(bounded_lines(&text, issues_area.width as usize)).style(Style::default().fg(color))
+            })
+            .collect();
+
+        f.render_widget(List::new(issue_items), issues_area);
+
+        let instructions = Paragraph::new(Line::from(Span::styled(
+            "enter: clean anyway • q: quit and fix permissions first",
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::ITALIC),
+        )))
+        .alignment(Alignment::Center);
+        f.render_widget(instructions, chunks[2]);
+    }
+
+    fn render_cleaning_card(&self, f: &mut Frame, area: Rect, card: &CleaningCard, is_focused: bool) {
+        let border_color = if is_focused {
+            Color::Yellow
+        } else if card.is_selected {
+            Color::Green
+        } else {
+            Color::Gray
+        };
+
+        let border_style = if is_focused {
+            Modifier::BOLD
+        } else {
+            Modifier::empty()
+        };
+
+        // compact card title
+        let short_name = match card.card_type {
+            CardType::Augment => "Augment",
+            CardType::Cursor => "Cursor",
+            CardType::Windsurf => "Windsurf",
+            CardType::VSCodium => "VSCodium",
+        };
+
+        let card_block = Block::default()
+            .title(format!(" {} ", short_name))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(border_color).add_modifier(border_style));
+
+        let card_area = card_block.inner(area);
+        f.render_widget(card_block, area);
+
+        // compact content layout
+        let content_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1), // icon line
+                Constraint::Length(1), // status line
+                Constraint::Length(1), // focus indicator
+            ])
+            .split(card_area);
+
+        // icon
+        let icon = match card.card_type {
+            CardType::Augment => "🔧",
+            CardType::Cursor => "🖱️",
+            CardType::Windsurf => "🏄",
+            CardType::VSCodium => "🧩",
+        };
+
+        let icon_widget = Paragraph::new(Line::from(Span::styled(
+            icon,
+            Style::default().add_modifier(Modifier::BOLD),
+        )))
+        .alignment(Alignment::Center);
+        f.render_widget(icon_widget, content_chunks[0]);
+
+        // status
+        let status_text = if card.is_selected { "✓ selected" } else { "○ not selected" };
         let status_color = if card.is_selected { Color::Green } else { Color::Gray };
 
         let status = Paragraph::new(Line::from(Span::styled(
@@ -623,12 +1324,19 @@ impl ZenGarden {
             ])
             .split(area);
 
-        // current operation
-        let operation = Paragraph::new(Line::from(Span::styled(
-            &self.current_operation,
-            Style::default().fg(Color::Cyan).add_modifier(Modifier::ITALIC),
-        )))
-        .alignment(Alignment::Center);
+        // current operation, or the pause in its place
+        let operation = if self.pause.is_paused() {
+            Span::styled(
+                "⏸ paused - press p to resume",
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::styled(
+                self.current_operation.as_str(),
+                Style::default().fg(Color::Cyan).add_modifier(Modifier::ITALIC),
+            )
+        };
+        let operation = Paragraph::new(Line::from(operation)).alignment(Alignment::Center);
         f.render_widget(operation, chunks[0]);
 
         // meditation space with dynamic elements
@@ -651,7 +1359,7 @@ impl ZenGarden {
 
     fn render_meditation_stones(&self, f: &mut Frame, area: Rect) {
         let stones_block = Block::default()
-            .title("🪨 meditation stones (↑↓ to select, enter to close)")
+            .title("🪨 meditation stones (↑↓ to select, enter twice to close, r to rescan)")
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::Green));
 
@@ -671,13 +1379,26 @@ impl ZenGarden {
 
                 let style = if stone.is_terminated {
                     Style::default().fg(Color::DarkGray)
+                } else if self.armed_stone.is_some_and(|(armed, _)| armed == i) {
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
                 } else if i == self.selected_stone {
                     Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
                 } else {
                     Style::default().fg(Color::White)
                 };
 
-                ListItem::new(format!("{} {}", symbol, stone.name))
+                // the selected stone unfolds into the helpers that would be closed with it
+                let armed = self.armed_stone.is_some_and(|(armed, _)| armed == i);
+                let label = if armed { format!("{} {} - press enter again to close", symbol, stone.name) } else { format!("{} {}", symbol, stone.name) };
+                let mut lines = vec![Line::from(label)];
+                if i == self.selected_stone && !stone.is_terminated {
+                    lines.extend(stone.children.iter().map(|(depth, label)| Line::from(Span::styled(
+                        format!("{}└ {}", "  ".repeat(*depth), label),
+                        Style::default().fg(Color::DarkGray),
+                    ))));
+                }
+
+                ListItem::new(lines)
                     .style(style)
             })
             .collect();
@@ -708,8 +1429,13 @@ impl ZenGarden {
         let progress_gauge = Gauge::default()
             .block(Block::default())
             .gauge_style(Style::default().fg(Color::Blue))
-            .percent((self.progress * 100.0).min(100.0) as u16)
-            .label(format!("cleansing... {:.0}%", (self.progress * 100.0).min(100.0)));
+            .ratio(self.progress.clamp(0.0, 1.0))
+            // until the steps are counted there is no percentage worth showing
+            .label(if self.total_operations == 0 && self.state != ZenState::Complete {
+                "cleansing...".to_string()
+            } else {
+                format!("cleansing... {:.0}%", self.progress * 100.0)
+            });
 
         let water_content = Layout::default()
             .direction(Direction::Vertical)
@@ -747,50 +1473,75 @@ impl ZenGarden {
     }
 
     fn render_zen_log(&self, f: &mut Frame, area: Rect) {
+        let title = if self.log_top.is_some() {
+            "🌸 mindful observations - scrolled back, pgdn to follow"
+        } else {
+            "🌸 mindful observations (pgup/pgdn or j/k to scroll)"
+        };
         let log_block = Block::default()
-            .title("🌸 mindful observations")
+            .title(title)
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::Yellow));
 
         let log_area = log_block.inner(area);
         f.render_widget(log_block, area);
 
-        let log_items: Vec<ListItem> = self.events.iter()
-            .rev()
-            .take(4)
-            .map(|event| ListItem::new(format!("• {}", event)))
+        // the rightmost column is kept for the scrollbar
+        let width = log_area.width.saturating_sub(1) as usize;
+        let lines: Vec<Line> = self.events.iter()
+            .flat_map(|event| bounded_lines(&format!("• {}", event), width).lines)
             .collect();
 
-        let log_list = List::new(log_items)
+        let height = log_area.height as usize;
+        self.log_view.set((lines.len(), height));
+        let max_top = lines.len().saturating_sub(height);
+        let top = self.log_top.unwrap_or(max_top).min(max_top);
+
+        let window = lines[top..(top + height).min(lines.len())].to_vec();
+        let log = Paragraph::new(window)
             .style(Style::default().fg(Color::Yellow));
-        f.render_widget(log_list, log_area);
+        f.render_widget(log, log_area);
+
+        if max_top > 0 {
+            let mut state = ScrollbarState::new(max_top).position(top);
+            let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .style(Style::default().fg(Color::Yellow));
+            f.render_stateful_widget(scrollbar, log_area, &mut state);
+        }
+    }
+
+    /// Move the log window by `delta` lines, following the tail again once it reaches the bottom
+    fn scroll_log(&mut self, delta: isize) {
+        let (total, height) = self.log_view.get();
+        let max_top = total.saturating_sub(height);
+        let top = self.log_top.unwrap_or(max_top) as isize + delta;
+        self.log_top = if max_top == 0 || top >= max_top as isize { None } else { Some(top.max(0) as usize) };
     }
 
     fn render_enlightenment(&self, f: &mut Frame, area: Rect) {
         let has_issues = self.error_collector.has_errors() || self.error_collector.has_warnings();
+        let next_steps_height = if self.next_steps.is_empty() { 0 } else { self.next_steps.len() as u16 + 2 };
+        let summary_height = if self.run_summary.is_empty() { 0 } else { self.run_summary.len().min(6) as u16 + 2 };
 
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints(if has_issues {
-                [
-                    Constraint::Length(3),
-                    Constraint::Min(5),
-                    Constraint::Length(4),
-                    Constraint::Length(3),
-                ]
-            } else {
-                [
-                    Constraint::Length(3),
-                    Constraint::Min(5),
-                    Constraint::Length(0),
-                    Constraint::Length(3),
-                ]
-            })
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(5),
+                Constraint::Length(if has_issues { 4 } else { 0 }),
+                Constraint::Length(summary_height),
+                Constraint::Length(next_steps_height),
+                Constraint::Length(3),
+            ])
             .split(area);
 
         // completion message with summary
         let completion_title = if has_issues {
             format!("🌸 meditation complete - {} 🌸", self.error_collector.get_summary())
+        } else if self.totals.is_noop() {
+            "🌸 already at peace - nothing required cleaning 🌸".to_string()
         } else {
             "🌸 digital harmony achieved 🌸".to_string()
         };
@@ -822,25 +1573,96 @@ impl ZenGarden {
             Line::from(""),
         ];
 
-        let garden = Paragraph::new(garden_text)
-            .style(Style::default().fg(Color::Magenta))
-            .alignment(Alignment::Center);
-        f.render_widget(garden, chunks[1]);
+        if self.show_diff {
+            self.render_key_diff(f, chunks[1]);
+        } else {
+            let garden = Paragraph::new(garden_text)
+                .style(Style::default().fg(Color::Magenta))
+                .alignment(Alignment::Center);
+            f.render_widget(garden, chunks[1]);
+        }
 
         // show issues summary if there were any
         if has_issues {
             self.render_completion_summary(f, chunks[2]);
         }
 
+        if !self.run_summary.is_empty() {
+            self.render_run_summary(f, chunks[3]);
+        }
+
+        if !self.next_steps.is_empty() {
+            self.render_next_steps(f, chunks[4]);
+        }
+
         // exit instructions
+        let exit_hint = if self.key_diff.is_empty() {
+            "press [q] to return to the world with renewed digital mindfulness"
+        } else {
+            "press [d] to see the changed keys • [q] to return to the world with renewed digital mindfulness"
+        };
         let exit_text = Paragraph::new(Text::from(vec![
             Line::from(Span::styled(
-                "press [q] to return to the world with renewed digital mindfulness",
+                exit_hint,
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::DIM),
             )),
         ]))
         .alignment(Alignment::Center);
-        f.render_widget(exit_text, chunks[3]);
+        f.render_widget(exit_text, chunks[5]);
+    }
+
+    fn render_key_diff(&self, f: &mut Frame, area: Rect) {
+        let diff_block = Block::default()
+            .title("🔑 changed keys (d to hide)")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Magenta));
+
+        let diff_area = diff_block.inner(area);
+        f.render_widget(diff_block, area);
+
+        let width = diff_area.width as usize;
+        let mut lines: Vec<Line> = Vec::new();
+        for (location, changed) in &self.key_diff {
+            lines.push(Line::from(Span::styled(location.clone(), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))));
+            for (key, old, new) in changed {
+                let old = if old.is_empty() { "not set" } else { old.as_str() };
+                lines.extend(bounded_lines(&format!("• {}: {} -> {}", key, old, new), width).lines);
+            }
+        }
+
+        f.render_widget(Paragraph::new(lines).style(Style::default().fg(Color::White)), diff_area);
+    }
+
+    fn render_run_summary(&self, f: &mut Frame, area: Rect) {
+        let summary_block = Block::default()
+            .title("🌾 harvest")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Green));
+
+        let summary_area = summary_block.inner(area);
+        f.render_widget(summary_block, area);
+
+        let summary_items: Vec<ListItem> = self.run_summary.iter()
+            .map(|line| ListItem::new(format!("• {}", line)).style(Style::default().fg(Color::Green)))
+            .collect();
+
+        f.render_widget(List::new(summary_items), summary_area);
+    }
+
+    fn render_next_steps(&self, f: &mut Frame, area: Rect) {
+        let steps_block = Block::default()
+            .title("🌱 next steps")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan));
+
+        let steps_area = steps_block.inner(area);
+        f.render_widget(steps_block, area);
+
+        let step_items: Vec<ListItem> = self.next_steps.iter()
+            .map(|step| ListItem::new(format!("• {}", step)).style(Style::default().fg(Color::Cyan)))
+            .collect();
+
+        f.render_widget(List::new(step_items), steps_area);
     }
 
     fn render_completion_summary(&self, f: &mut Frame, area: Rect) {
@@ -977,7 +1799,7 @@ impl ZenGarden {
                 } else {
                     Style::default().fg(Color::White)
                 };
-                ListItem::new(format!("• {}", event)).style(style)
+                ListItem::new(bounded_lines(&format!("• {}", event), log_area.width as usize)).style(style)
             })
             .collect();
 
@@ -986,157 +1808,147 @@ impl ZenGarden {
     }
 }
 
-async fn zen_operations(tx: mpsc::UnboundedSender<ZenEvent>, args: CliArgs) {
-    tokio::time::sleep(Duration::from_millis(100)).await;
+/// Most lines a single log event may take up before it is cut short
+const MAX_EVENT_LINES: usize = 2;
 
-    // scanning phase
-    let _ = tx.send(ZenEvent::StartScanning);
-    tokio::time::sleep(Duration::from_millis(50)).await;
+/// Wrap an event to `width`, flattening embedded newlines and ending with … when it had to be cut
+fn bounded_lines(text: &str, width: usize) -> Text<'static> {
+    let chars: Vec<char> = text.split_whitespace().collect::<Vec<_>>().join(" ").chars().collect();
+    let width = width.max(1);
 
-    // find storage locations first to calculate total operations
-    let directories = find_vscode_storage_directories();
+    let mut lines: Vec<Line> = chars.chunks(width)
+        .take(MAX_EVENT_LINES)
+        .map(|chunk| Line::from(chunk.iter().collect::<String>()))
+        .collect();
 
-    // calculate total operations for accurate progress
-    let mut total_ops = 0;
-    for _dir in &directories {
-        total_ops += 1; // storage update
-        if !args.no_signout {
-            total_ops += 1; // database cleaning
+    if chars.len() > width * MAX_EVENT_LINES {
+        if let Some(last) = lines.pop() {
+            let mut kept: String = last.to_string().chars().take(width.saturating_sub(1)).collect();
+            kept.push('…');
+            lines.push(Line::from(kept));
         }
     }
-    let _ = tx.send(ZenEvent::SetTotalOperations(total_ops));
 
-    // find processes
-    if !args.no_terminate {
-        let discovered_processes = discover_vscode_processes();
-        if discovered_processes.is_empty() {
-            let _ = tx.send(ZenEvent::LogMessage("no restless processes found - digital spirits already at peace".to_string()));
-        } else {
-            for process_stone in discovered_processes {
-                let _ = tx.send(ZenEvent::ProcessFound(process_stone));
-                tokio::time::sleep(Duration::from_millis(20)).await;
-            }
-        }
-    }
+    Text::from(lines)
+}
 
-    // find storage locations
-    for dir in &directories {
-        let display_name = dir.file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-        let _ = tx.send(ZenEvent::LocationFound(display_name));
-        tokio::time::sleep(Duration::from_millis(10)).await;
+/// Cards chosen on the command line, in flag order
+fn flag_cards(args: &CliArgs) -> Vec<CardType> {
+    let mut cards = Vec::new();
+    if args.augment {
+        cards.push(CardType::Augment);
     }
-
-    if directories.is_empty() {
-        let _ = tx.send(ZenEvent::LogMessage("no vscode installations found - digital space already pure".to_string()));
-        tokio::time::sleep(Duration::from_millis(100)).await;
-        let _ = tx.send(ZenEvent::OperationComplete);
-        return;
+    if args.cursor {
+        cards.push(CardType::Cursor);
+    }
+    if args.windsurf {
+        cards.push(CardType::Windsurf);
     }
+    if args.vscodium {
+        cards.push(CardType::VSCodium);
+    }
+    cards
+}
 
-    // processing phase - processes are now terminated interactively via meditation stones
-    tokio::time::sleep(Duration::from_millis(50)).await;
+/// `cards` without repeats, in the order each was first picked
+fn unique_cards(mut cards: Vec<CardType>) -> Vec<CardType> {
+    let mut seen = Vec::new();
+    cards.retain(|card| {
+        let first = !seen.contains(card);
+        seen.push(card.clone());
+        first
+    });
+    cards
+}
 
-    // update storage and clean databases - continue even if some operations fail
-    let mut overall_error_collector = crate::utils::ErrorCollector::new();
+/// The storage directories the selected cards would clean, in discovery order
+fn discover_targets(selected_cards: &[CardType], args: &CliArgs) -> crate::utils::Result<Vec<std::path::PathBuf>> {
+    crate::plan::discover_targets(
+        args,
+        selected_cards.contains(&CardType::Augment),
+        selected_cards.contains(&CardType::Cursor),
+        selected_cards.contains(&CardType::Windsurf),
+        selected_cards.contains(&CardType::VSCodium),
+    )
+}
 
-    for directory in directories {
-        let display_name = directory.file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
+/// Build the overview tree from a read-only discovery pass: installs, then directories, then files
+fn build_overview(selected_cards: &[CardType], args: &CliArgs) -> Vec<OverviewNode> {
+    use crate::doctor::count_database_rows;
+    use crate::filesystem::install_name;
 
-        // update storage - continue even if this fails
-        match update_vscode_storage(&directory, &tx) {
-            Ok(_) => {
-                let _ = tx.send(ZenEvent::StorageUpdated(display_name.clone()));
-            }
-            Err(e) => {
-                let error = crate::utils::CleanerError::FileSystem {
-                    operation: "storage update".to_string(),
-                    path: directory.display().to_string(),
-                    source: e.to_string(),
-                };
-                overall_error_collector.add_error(error.clone());
-                let _ = tx.send(ZenEvent::DetailedError(error));
-                // still mark as "updated" to continue progress tracking
-                let _ = tx.send(ZenEvent::StorageUpdated(format!("{} (with errors)", display_name.clone())));
-            }
+    let directories = discover_targets(selected_cards, args).unwrap_or_default();
+
+    // group by install, keeping installs in discovery order
+    let mut installs: Vec<(String, Vec<std::path::PathBuf>)> = Vec::new();
+    for dir in directories {
+        let name = install_name(&dir);
+        match installs.iter_mut().find(|(install, _)| *install == name) {
+            Some((_, dirs)) => dirs.push(dir),
+            None => installs.push((name, vec![dir])),
         }
-        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
 
-        // clean database - continue even if this fails
-        if !args.no_signout {
-            match clean_vscode_databases(&directory, &tx) {
-                Ok(_) => {
-                    let _ = tx.send(ZenEvent::DatabaseCleaned(display_name));
+    let mut nodes = Vec::new();
+    for (install, dirs) in installs {
+        nodes.push(OverviewNode {
+            depth: 0,
+            label: format!("{} ({} locations)", install, dirs.len()),
+            expanded: true,
+            has_children: true,
+        });
+
+        for dir in dirs {
+            let mut files = Vec::new();
+            if dir.is_file() {
+                files.push("machine id file".to_string());
+            } else {
+                if dir.join("storage.json").exists() {
+                    files.push("storage.json present".to_string());
                 }
-                Err(e) => {
-                    let error = crate::utils::CleanerError::Database {
-                        operation: "database cleaning".to_string(),
-                        path: directory.display().to_string(),
-                        source: e.to_string(),
-                    };
-                    overall_error_collector.add_error(error.clone());
-                    let _ = tx.send(ZenEvent::DetailedError(error));
-                    // still mark as "cleaned" to continue progress tracking
-                    let _ = tx.send(ZenEvent::DatabaseCleaned(format!("{} (with errors)", display_name)));
+                for db in crate::database::database_files(&dir, &args.db_files) {
+                    if let Some(rows) = count_database_rows(&dir.join(&db), &args.extensions) {
+                        files.push(format!("{} present ({} matching rows)", db, rows));
+                    } else if dir.join(&db).exists() {
+                        files.push(format!("{} present (unreadable)", db));
+                    }
                 }
             }
-            tokio::time::sleep(Duration::from_millis(50)).await;
-        }
-    }
 
-    // send final error summary if there were any errors
-    if overall_error_collector.has_errors() {
-        let _ = tx.send(ZenEvent::ErrorSummary(overall_error_collector));
+            nodes.push(OverviewNode {
+                depth: 1,
+                label: dir.display().to_string(),
+                expanded: true,
+                has_children: !files.is_empty(),
+            });
+            nodes.extend(files.into_iter().map(|label| OverviewNode {
+                depth: 2,
+                label,
+                expanded: false,
+                has_children: false,
+            }));
+        }
     }
 
-    // completion
-    tokio::time::sleep(Duration::from_millis(50)).await;
-    let _ = tx.send(ZenEvent::OperationComplete);
+    nodes
 }
 
 fn discover_vscode_processes() -> Vec<ProcessStone> {
     use sysinfo::System;
-    use crate::utils::VSCODE_PROCESSES;
 
     let mut stones = Vec::new();
 
-    for (pid, process) in System::new_all().processes() {
-        let cmd = process.cmd().join(" ".as_ref()).to_string_lossy().to_string();
-        let name = process.name().to_string_lossy().to_string();
-        let exe = process.exe().map(|p| p.to_string_lossy().to_lowercase()).unwrap_or_default();
-
-        let name_lower = name.to_lowercase();
-        let cmd_lower = cmd.to_lowercase();
-        let exe_lower = exe.to_lowercase();
-
-        let is_vscode = VSCODE_PROCESSES.iter().any(|&vs| name.eq_ignore_ascii_case(vs))
-            || cmd_lower.contains("vscode")
-            || exe_lower.contains("microsoft vs code")
-            || exe_lower.contains("visual studio code")
-            || name_lower.contains("cursor")
-            || name_lower.contains("code-insiders")
-            || name_lower.contains("windsurf")
-            || name_lower.contains("trae")
-            || name_lower.contains("vscodium")
-            || exe_lower.contains("/code")
-            || exe_lower.contains("\\code.exe")
-            || exe_lower.contains("/cursor")
-            || exe_lower.contains("\\cursor.exe")
-            || (exe_lower.contains("code") && exe_lower.contains("electron"))
-            || exe_lower.contains(".app/contents/macos/electron");
-
-        if is_vscode {
+    // one snapshot for both the matches and their trees, so a stone shows what kill_tree will take with it
+    let system = System::new_all();
+    for (pid, process) in system.processes() {
+        if crate::process::is_editor_process(process) {
             stones.push(ProcessStone {
-                name: name.clone(),
+                name: process.name().to_string_lossy().to_string(),
                 pid: pid.as_u32(),
-                path: exe,
+                path: process.exe().map(|p| p.to_string_lossy().to_lowercase()).unwrap_or_default(),
                 is_selected: false,
                 is_terminated: false,
+                children: crate::process::process_tree(&system, *pid),
             });
         }
     }
@@ -1144,34 +1956,224 @@ fn discover_vscode_processes() -> Vec<ProcessStone> {
     stones
 }
 
-async fn zen_operations_with_cards(tx: mpsc::UnboundedSender<ZenEvent>, _args: CliArgs, selected_cards: Vec<CardType>) {
+/// How often a headless run with a piped stderr prints its progress, at most
+const PROGRESS_LINE_EVERY: Duration = Duration::from_secs(2);
+
+/// A rough estimate of the time left, assuming the remaining steps take as long as the finished ones did on average
+fn time_left(elapsed: Duration, completed: usize, total: usize) -> Option<Duration> {
+    if completed == 0 || completed >= total {
+        return None;
+    }
+    Some(elapsed.div_f64(completed as f64).mul_f64((total - completed) as f64))
+}
+
+/// "42s" or "3m 05s"
+fn format_time_left(left: Duration) -> String {
+    let secs = left.as_secs().max(1);
+    if secs < 60 { format!("{}s", secs) } else { format!("{}m {:02}s", secs / 60, secs % 60) }
+}
+
+/// Send a module's problems one event each, then the summary that ends the run in turbulence when any of them is an error
+fn send_problems(tx: &mpsc::UnboundedSender<ZenEvent>, problems: crate::utils::ErrorCollector) {
+    for warning in &problems.warnings {
+        let _ = tx.send(ZenEvent::Warning(warning.clone()));
+    }
+    for error in &problems.errors {
+        let _ = tx.send(ZenEvent::DetailedError(error.clone()));
+    }
+    if problems.has_errors() {
+        let _ = tx.send(ZenEvent::ErrorSummary(problems));
+    }
+}
+
+/// Run one module's clean on the blocking pool, its directory loops would otherwise hold up the thread the events are read on
+async fn run_module<T: Send + 'static>(
+    args: &CliArgs,
+    tx: &mpsc::UnboundedSender<ZenEvent>,
+    clean: impl FnOnce(&CliArgs, &mpsc::UnboundedSender<ZenEvent>) -> std::result::Result<T, String> + Send + 'static,
+) -> std::result::Result<T, String> {
+    let (args, tx) = (args.clone(), tx.clone());
+    tokio::task::spawn_blocking(move || clean(&args, &tx)).await
+        .unwrap_or_else(|e| Err(format!("it panicked - {}", panic_cause(e))))
+}
+
+/// The message a task panicked with, or why it stopped otherwise
+fn panic_cause(error: tokio::task::JoinError) -> String {
+    if !error.is_panic() {
+        return error.to_string();
+    }
+    let payload = error.into_panic();
+    payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "no message".to_string())
+}
+
+/// Run the selected cards, emitting StartScanning, SetTotalOperations, per-card LocationFound/StorageUpdated/DatabaseCleaned,
+/// then RunSummary, RunTotals and NextSteps before OperationComplete
+async fn zen_operations_with_cards(tx: mpsc::UnboundedSender<ZenEvent>, mut args: CliArgs, selected_cards: Vec<CardType>) {
+    // clones of the arguments share their ledgers, so each run, e.g. each one under --watch, starts its own
+    args.backups = Default::default();
+    args.unlocked = Default::default();
+
+    // a card picked both by flag and by hand must still only run once
+    let selected_cards = unique_cards(selected_cards);
+
     tokio::time::sleep(Duration::from_millis(100)).await;
 
     // scanning phase
     let _ = tx.send(ZenEvent::StartScanning);
     tokio::time::sleep(Duration::from_millis(50)).await;
 
-    let mut total_operations = 0;
-    let mut _completed_operations = 0;
+    // report mistyped telemetry keys up front since they would otherwise do nothing
+    let (resolved_keys, key_warnings) = crate::utils::resolve_telemetry_keys(&args.telemetry_keys, &args.extra_keys, args.allow_unknown_keys, &args.preserve_keys);
+    let (_, format_warnings) = crate::utils::resolve_id_formats(&args.id_formats, &resolved_keys);
+    for warning in key_warnings.into_iter().chain(format_warnings) {
+        let _ = tx.send(ZenEvent::Warning(warning));
+    }
+    for key in &args.preserve_keys {
+        let _ = tx.send(ZenEvent::LogMessage(format!("leaving {} untouched - it is a preserved key", key.trim())));
+    }
 
     // determine what operations we need to do
     let do_augment = selected_cards.contains(&CardType::Augment);
     let do_cursor = selected_cards.contains(&CardType::Cursor);
+    let do_windsurf = selected_cards.contains(&CardType::Windsurf);
+    let do_vscodium = selected_cards.contains(&CardType::VSCodium);
+
+    // snapshot the targets before anything changes so the report shows what was found
+    let (keys, _) = crate::utils::resolve_telemetry_keys(&args.telemetry_keys, &args.extra_keys, args.allow_unknown_keys, &args.preserve_keys);
+    let (augment_dirs, discovery_warnings) = if do_augment {
+        crate::filesystem::discover_storage_directories(&args, args.strict_discovery).unwrap_or_default()
+    } else {
+        Default::default()
+    };
+    // with --strict-discovery a failure here is reported by the module itself when it runs
+    let cursor_dirs = if do_cursor { crate::cursor::find_cursor_storage_directories(&args).unwrap_or_default() } else { Vec::new() };
+    let windsurf_dirs = if do_windsurf { crate::windsurf::find_windsurf_storage_directories(&args).unwrap_or_default() } else { Vec::new() };
+    let vscodium_dirs = if do_vscodium { crate::vscodium::find_vscodium_storage_directories(&args).unwrap_or_default() } else { Vec::new() };
+
+    // one --limit covers the whole run, the directories past it are deselected so no module reaches them
+    let found: Vec<_> = augment_dirs.iter().chain(&cursor_dirs).chain(&windsurf_dirs).chain(&vscodium_dirs).cloned().collect();
+    let remaining = crate::filesystem::share_limit(&mut args, &found);
+    let [augment_dirs, cursor_dirs, windsurf_dirs, vscodium_dirs] = [augment_dirs, cursor_dirs, windsurf_dirs, vscodium_dirs]
+        .map(|dirs| dirs.into_iter().filter(|dir| !args.deselected.contains(dir)).collect::<Vec<_>>());
+
+    // an editor left running rewrites what was just cleaned, so with --no-terminate the directories it holds are skipped
+    let mut running = Vec::new();
+    if args.no_terminate && !args.dry_run {
+        running = crate::process::running_editors();
+        if !running.is_empty() && !args.ignore_running {
+            let candidates: Vec<_> = augment_dirs.iter().chain(&cursor_dirs).chain(&windsurf_dirs).chain(&vscodium_dirs).cloned().collect();
+            for (directory, editor) in crate::process::directories_in_use(&candidates) {
+                if !args.deselected.contains(&directory) {
+                    let _ = tx.send(ZenEvent::Warning(format!("{} is in use by {} - skipped, close it or pass --force", directory.display(), editor)));
+                    args.deselected.push(directory);
+                }
+            }
+        }
+    }
+    let kept = |dirs: Vec<std::path::PathBuf>| -> Vec<std::path::PathBuf> { dirs.into_iter().filter(|dir| !args.deselected.contains(dir)).collect() };
+    let augment_dirs = kept(augment_dirs);
+    let cursor_dirs = kept(cursor_dirs);
+    let windsurf_dirs = kept(windsurf_dirs);
+    let vscodium_dirs = kept(vscodium_dirs);
+    let mut targets: Vec<_> = augment_dirs.iter().chain(&cursor_dirs).chain(&windsurf_dirs).chain(&vscodium_dirs).cloned().collect();
+
+    // one step per module for termination, and one per directory for its storage update and again for its databases,
+    // each advanced by the module as it finishes
+    let terminate_step = usize::from(!args.no_terminate);
+    let passes = 1 + usize::from(!args.no_signout);
+    let augment_steps = if do_augment && !args.dry_run { terminate_step + augment_dirs.len() * passes } else { 0 };
+    let cursor_steps = if do_cursor && !args.dry_run { terminate_step + cursor_dirs.len() * passes } else { 0 };
+    let windsurf_steps = if do_windsurf && !args.dry_run { terminate_step + windsurf_dirs.len() * passes } else { 0 };
+    let vscodium_steps = if do_vscodium && !args.dry_run { terminate_step + vscodium_dirs.len() * passes } else { 0 };
+    let extra_steps = [args.clean_extensions_json, args.clean_cache, args.scan_workspaces.is_some()]
+        .iter()
+        .filter(|&&enabled| enabled)
+        .count();
+    let _ = tx.send(ZenEvent::SetTotalOperations(augment_steps + cursor_steps + windsurf_steps + vscodium_steps + extra_steps));
+    let mut seen = std::collections::HashSet::new();
+    targets.retain(|target| seen.insert(target.clone()));
+
+    let mut report = crate::report::RunReport::default();
+    report.directories = targets.iter()
+        .map(|target| crate::report::DirectoryReport::snapshot(target, &keys, &args))
+        .collect();
+    report.summarize_editors();
+    report.shared_ids = crate::report::shared_identities(&targets, &keys);
+    for shared in &report.shared_ids {
+        let _ = tx.send(ZenEvent::LogMessage(format!("found one identity in many groves - {}", shared.describe())));
+    }
+    for warning in discovery_warnings {
+        let _ = tx.send(ZenEvent::Warning(warning));
+    }
 
-    if do_augment {
-        total_operations += 3; // processes, storage, database
+    // compare with the ids the last run wrote to show what the editor regenerated since
+    if let Some(path) = args.export_ids.as_ref().filter(|path| path.exists()) {
+        match crate::report::load_exported_ids(path) {
+            Ok(previous) => {
+                let regenerated = crate::report::regenerated_ids(&previous, &crate::report::collect_ids(&targets, &keys));
+                if regenerated.is_empty() {
+                    let _ = tx.send(ZenEvent::LogMessage("identifiers unchanged since the last clean - the editor left them in peace".to_string()));
+                }
+                for (dir, key) in regenerated {
+                    let _ = tx.send(ZenEvent::Warning(format!("the editor regenerated {} in {} since the last clean", key, dir)));
+                }
+            }
+            Err(e) => {
+                let _ = tx.send(ZenEvent::Warning(e.to_string()));
+            }
+        }
     }
-    if do_cursor {
-        total_operations += 2; // processes, config
+
+    if !running.is_empty() && args.ignore_running {
+        let _ = tx.send(ZenEvent::Warning(format!("cleaning while {} runs - it may overwrite the new ids and keep databases locked, so results can be partial", running.join(", "))));
     }
 
-    let _ = tx.send(ZenEvent::SetTotalOperations(total_operations));
+    // process augment cleaning - a closed channel means the ui quit and the run is cancelled
+    // a dry run only records the plan - none of the cleaning steps below run
+    if args.dry_run {
+        let _ = tx.send(ZenEvent::PhaseStarted("plan".to_string()));
+        report.dry_run = true;
+        let plan = crate::plan::build_plan_for(&args, &targets);
+        if !args.no_terminate {
+            let modules: [(bool, &[&str]); 4] = [
+                (do_augment, &crate::augment::AUGMENT_PROCESSES),
+                (do_cursor, &crate::cursor::CURSOR_PROCESSES),
+                (do_windsurf, &crate::windsurf::WINDSURF_PROCESSES),
+                (do_vscodium, &crate::vscodium::VSCODIUM_PROCESSES),
+            ];
+            let mut stopping = Vec::new();
+            for (_, names) in modules.iter().filter(|(selected, _)| *selected) {
+                for process in crate::process::running_processes_named(names) {
+                    if !stopping.contains(&process) {
+                        stopping.push(process);
+                    }
+                }
+            }
+            for process in stopping {
+                let _ = tx.send(ZenEvent::LogMessage(format!("would terminate {}", process)));
+            }
+        }
+        for directory in &targets {
+            let planned = plan.target(directory).map(|target| target.changes.clone()).unwrap_or_default();
+            for change in &planned {
+                let _ = tx.send(ZenEvent::LogMessage(format!("would {} in {}", change, directory.display())));
+            }
+            if let Some(entry) = report.directories.iter_mut().find(|d| d.path == directory.display().to_string()) {
+                entry.planned = planned;
+            }
+        }
+        let _ = tx.send(ZenEvent::LogMessage("dry run - the garden was observed, not touched".to_string()));
+    }
 
-    // process augment cleaning
-    if do_augment {
+    // a pause from the interface holds the run here, between modules, and between directories inside them
+    args.pause.resumed().await;
+    if do_augment && !args.dry_run && !tx.is_closed() {
+        let _ = tx.send(ZenEvent::PhaseStarted("augment".to_string()));
         let _ = tx.send(ZenEvent::LogMessage("beginning augment extension purification...".to_string()));
 
-        match crate::augment::clean_augment_extension(&_args).await {
+        match run_module(&args, &tx, |args, tx| tokio::runtime::Handle::current().block_on(crate::augment::clean_augment_extension(args, tx)).map_err(|e| e.to_string())).await {
             Ok(result) => {
                 for process in result.processes_terminated {
                     let _ = tx.send(ZenEvent::ProcessTerminated(process));
@@ -1180,17 +2182,26 @@ async fn zen_operations_with_cards(tx: mpsc::UnboundedSender<ZenEvent>, _args: C
                     let _ = tx.send(ZenEvent::LocationFound(dir.to_string_lossy().to_string()));
                 }
                 for storage in result.storage_updated {
+                    report.mark_cleaned(&storage);
                     let _ = tx.send(ZenEvent::StorageUpdated(storage));
                 }
+                for (location, changed) in result.keys_changed {
+                    let _ = tx.send(ZenEvent::KeysChanged(location, changed));
+                }
                 for db in result.databases_cleaned {
+                    report.mark_cleaned(&db);
                     let _ = tx.send(ZenEvent::DatabaseCleaned(db));
                 }
+                if args.clean_history_keys {
+                    let _ = tx.send(ZenEvent::LogMessage(format!("swept {} history entries from editor state", result.history_entries_cleared)));
+                }
 
-                if result.errors.has_errors() {
-                    let _ = tx.send(ZenEvent::ErrorSummary(result.errors));
+                for compaction in &result.compacted {
+                    let _ = tx.send(ZenEvent::LogMessage(compaction.describe()));
                 }
+                report.record_errors(&result.errors);
+                send_problems(&tx, result.errors);
 
-                _completed_operations += 3;
             }
             Err(e) => {
                 let _ = tx.send(ZenEvent::Error(format!("augment cleaning failed: {}", e)));
@@ -1201,27 +2212,39 @@ async fn zen_operations_with_cards(tx: mpsc::UnboundedSender<ZenEvent>, _args: C
     }
 
     // process cursor cleaning
-    if do_cursor {
+    args.pause.resumed().await;
+    if do_cursor && !args.dry_run && !tx.is_closed() {
+        let _ = tx.send(ZenEvent::PhaseStarted("cursor".to_string()));
         let _ = tx.send(ZenEvent::LogMessage("beginning cursor ide purification...".to_string()));
 
-        match crate::cursor::clean_cursor_ide(&_args).await {
+        match run_module(&args, &tx, |args, tx| tokio::runtime::Handle::current().block_on(crate::cursor::clean_cursor_ide(args, tx)).map_err(|e| e.to_string())).await {
             Ok(result) => {
                 for process in result.processes_terminated {
                     let _ = tx.send(ZenEvent::ProcessTerminated(process));
                 }
                 for dir in result.directories_removed {
+                    if result.config_updated {
+                        report.mark_cleaned(&dir.display().to_string());
+                    }
                     let _ = tx.send(ZenEvent::LocationFound(dir.to_string_lossy().to_string()));
                 }
 
                 if result.config_updated {
                     let _ = tx.send(ZenEvent::StorageUpdated("cursor configuration".to_string()));
                 }
+                for (location, changed) in result.keys_changed {
+                    let _ = tx.send(ZenEvent::KeysChanged(location, changed));
+                }
+                if args.clean_history_keys {
+                    let _ = tx.send(ZenEvent::LogMessage(format!("swept {} history entries from cursor state", result.history_entries_cleared)));
+                }
 
-                if result.errors.has_errors() {
-                    let _ = tx.send(ZenEvent::ErrorSummary(result.errors));
+                for compaction in &result.compacted {
+                    let _ = tx.send(ZenEvent::LogMessage(compaction.describe()));
                 }
+                report.record_errors(&result.errors);
+                send_problems(&tx, result.errors);
 
-                _completed_operations += 2;
             }
             Err(e) => {
                 let _ = tx.send(ZenEvent::Error(format!("cursor cleaning failed: {}", e)));
@@ -1231,8 +2254,351 @@ async fn zen_operations_with_cards(tx: mpsc::UnboundedSender<ZenEvent>, _args: C
         tokio::time::sleep(Duration::from_millis(100)).await;
     }
 
+    // process windsurf cleaning
+    args.pause.resumed().await;
+    if do_windsurf && !args.dry_run && !tx.is_closed() {
+        let _ = tx.send(ZenEvent::PhaseStarted("windsurf".to_string()));
+        let _ = tx.send(ZenEvent::LogMessage("beginning windsurf ide purification...".to_string()));
+
+        match run_module(&args, &tx, |args, tx| tokio::runtime::Handle::current().block_on(crate::windsurf::clean_windsurf_ide(args, tx)).map_err(|e| e.to_string())).await {
+            Ok(result) => {
+                for process in result.processes_terminated {
+                    let _ = tx.send(ZenEvent::ProcessTerminated(process));
+                }
+                for dir in result.directories_found {
+                    if result.config_updated {
+                        report.mark_cleaned(&dir.display().to_string());
+                    }
+                    let _ = tx.send(ZenEvent::LocationFound(dir.to_string_lossy().to_string()));
+                }
+
+                if result.config_updated {
+                    let _ = tx.send(ZenEvent::StorageUpdated("windsurf configuration".to_string()));
+                }
+                for (location, changed) in result.keys_changed {
+                    let _ = tx.send(ZenEvent::KeysChanged(location, changed));
+                }
+                if args.clean_history_keys {
+                    let _ = tx.send(ZenEvent::LogMessage(format!("swept {} history entries from windsurf state", result.history_entries_cleared)));
+                }
+
+                for compaction in &result.compacted {
+                    let _ = tx.send(ZenEvent::LogMessage(compaction.describe()));
+                }
+                report.record_errors(&result.errors);
+                send_problems(&tx, result.errors);
+
+            }
+            Err(e) => {
+                let _ = tx.send(ZenEvent::Error(format!("windsurf cleaning failed: {}", e)));
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // process vscodium cleaning
+    args.pause.resumed().await;
+    if do_vscodium && !args.dry_run && !tx.is_closed() {
+        let _ = tx.send(ZenEvent::PhaseStarted("vscodium".to_string()));
+        let _ = tx.send(ZenEvent::LogMessage("beginning vscodium purification...".to_string()));
+
+        match run_module(&args, &tx, |args, tx| tokio::runtime::Handle::current().block_on(crate::vscodium::clean_vscodium(args, tx)).map_err(|e| e.to_string())).await {
+            Ok(result) => {
+                for process in result.processes_terminated {
+                    let _ = tx.send(ZenEvent::ProcessTerminated(process));
+                }
+                for dir in result.directories_found {
+                    if result.config_updated {
+                        report.mark_cleaned(&dir.display().to_string());
+                    }
+                    let _ = tx.send(ZenEvent::LocationFound(dir.to_string_lossy().to_string()));
+                }
+
+                if result.config_updated {
+                    let _ = tx.send(ZenEvent::StorageUpdated("vscodium configuration".to_string()));
+                }
+                for (location, changed) in result.keys_changed {
+                    let _ = tx.send(ZenEvent::KeysChanged(location, changed));
+                }
+                if args.clean_history_keys {
+                    let _ = tx.send(ZenEvent::LogMessage(format!("swept {} history entries from vscodium state", result.history_entries_cleared)));
+                }
+
+                for compaction in &result.compacted {
+                    let _ = tx.send(ZenEvent::LogMessage(compaction.describe()));
+                }
+                report.record_errors(&result.errors);
+                send_problems(&tx, result.errors);
+
+            }
+            Err(e) => {
+                let _ = tx.send(ZenEvent::Error(format!("vscodium cleaning failed: {}", e)));
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    args.pause.resumed().await;
+    if args.clean_extensions_json && !tx.is_closed() {
+        let _ = tx.send(ZenEvent::PhaseStarted("extensions".to_string()));
+        for manifest in crate::extensions::find_extensions_manifests() {
+            if args.dry_run {
+                match crate::extensions::count_identifying_fields(&manifest) {
+                    Ok(count) => {
+                        let _ = tx.send(ZenEvent::LogMessage(format!("would strip {} identifying fields from {}", count, manifest.display())));
+                    }
+                    Err(e) => {
+                        let _ = tx.send(ZenEvent::Warning(format!("could not read {}: {}", manifest.display(), e)));
+                    }
+                }
+                continue;
+            }
+
+            let mut warnings = crate::utils::ErrorCollector::new();
+            match crate::extensions::sanitize_extensions_json(&manifest, &args.unlocked, &mut warnings) {
+                Ok(count) => {
+                    let _ = tx.send(ZenEvent::LogMessage(format!("stripped {} identifying fields from {}", count, manifest.display())));
+                }
+                Err(e) => {
+                    let _ = tx.send(ZenEvent::Warning(format!("could not sanitize {}: {}", manifest.display(), e)));
+                }
+            }
+            for warning in warnings.warnings {
+                let _ = tx.send(ZenEvent::Warning(warning));
+            }
+        }
+        let _ = tx.send(ZenEvent::OperationsAdvanced(1));
+    }
+
+    args.pause.resumed().await;
+    if args.clean_cache && !tx.is_closed() {
+        let _ = tx.send(ZenEvent::PhaseStarted("cache".to_string()));
+        let mut errors = crate::utils::ErrorCollector::new();
+        let mut freed = 0;
+
+        for folder in crate::cache::find_cache_folders(&targets) {
+            let size = crate::cache::format_size(folder.bytes);
+            let root = folder.path.parent().unwrap_or(&folder.path);
+
+            if folder.bytes > args.cache_confirm_mb * 1024 * 1024 {
+                errors.add_warning(format!("{} is {} - raise --cache-confirm-mb to remove it", folder.path.display(), size));
+            } else if !args.ignore_running && crate::cache::install_in_use(root) {
+                errors.add_warning(format!("{} is in use by a running editor - left in place", folder.path.display()));
+            } else if args.dry_run {
+                let _ = tx.send(ZenEvent::LogMessage(format!("would release {} of cache in {}", size, folder.path.display())));
+            } else {
+                match crate::cache::remove_cache_folder(&folder) {
+                    Ok(_) => {
+                        freed += folder.bytes;
+                        let _ = tx.send(ZenEvent::LogMessage(format!("released {} of cache in {}", size, folder.path.display())));
+                    }
+                    Err(e) => errors.add_error(crate::utils::CleanerError::FileSystem {
+                        operation: "removing cache".to_string(),
+                        path: folder.path.display().to_string(),
+                        source: e.to_string(),
+                    }),
+                }
+            }
+        }
+
+        if freed > 0 {
+            let _ = tx.send(ZenEvent::LogMessage(format!("{} of cache swept away", crate::cache::format_size(freed))));
+        }
+        report.record_errors(&errors);
+        for warning in errors.warnings {
+            let _ = tx.send(ZenEvent::Warning(warning));
+        }
+        for error in errors.errors {
+            let _ = tx.send(ZenEvent::DetailedError(error));
+        }
+        let _ = tx.send(ZenEvent::OperationsAdvanced(1));
+    }
+
+    if let Some(root) = args.scan_workspaces.as_ref().filter(|_| !tx.is_closed()) {
+        let _ = tx.send(ZenEvent::PhaseStarted("project settings".to_string()));
+        let settings_files = crate::workspaces::find_workspace_settings(root, args.max_depth);
+        let _ = tx.send(ZenEvent::LogMessage(format!("found {} project settings under {}", settings_files.len(), root.display())));
+
+        for settings in settings_files {
+            if args.dry_run {
+                match crate::workspaces::read_settings(&settings) {
+                    Ok(data) => {
+                        for key in crate::workspaces::identifying_keys(&data) {
+                            let _ = tx.send(ZenEvent::LogMessage(format!("would remove {} from {}", key, settings.display())));
+                        }
+                    }
+                    Err(e) => {
+                        let _ = tx.send(ZenEvent::Warning(format!("{}: {}", settings.display(), e)));
+                    }
+                }
+                continue;
+            }
+
+            let mut warnings = crate::utils::ErrorCollector::new();
+            match crate::workspaces::sanitize_workspace_settings(&settings, &args.unlocked, &mut warnings) {
+                Ok(keys) if keys.is_empty() => {}
+                Ok(keys) => {
+                    let _ = tx.send(ZenEvent::LogMessage(format!("removed {} from {}", keys.join(", "), settings.display())));
+                }
+                Err(e) => {
+                    let _ = tx.send(ZenEvent::Warning(format!("{}: {}", settings.display(), e)));
+                }
+            }
+            for warning in warnings.warnings {
+                let _ = tx.send(ZenEvent::Warning(warning));
+            }
+        }
+        let _ = tx.send(ZenEvent::OperationsAdvanced(1));
+    }
+
+    if let Some(path) = args.export_ids.as_ref().filter(|_| !args.dry_run) {
+        if let Err(e) = crate::report::export_ids(path, &targets, &keys) {
+            let _ = tx.send(ZenEvent::Warning(format!("could not export ids to {}: {}", path.display(), e)));
+        }
+    }
+
+    let _ = tx.send(ZenEvent::PhaseStarted("summary".to_string()));
+    report.unlocked = args.unlocked.files();
+    for file in report.unlocked.iter().filter(|file| !file.restored) {
+        let _ = tx.send(ZenEvent::Warning(format!("{} was read-only and could not be locked again", file.path)));
+    }
+    report.group_directories(args.group_by);
+    if let Some(path) = &args.report {
+        match report.write(path) {
+            Ok(_) => {
+                let _ = tx.send(ZenEvent::LogMessage(format!("report written to {}", path.display())));
+            }
+            Err(e) => {
+                let _ = tx.send(ZenEvent::Warning(format!("could not write report to {}: {}", path.display(), e)));
+            }
+        }
+    }
+
+    if remaining > 0 {
+        let _ = tx.send(ZenEvent::Warning(format!("{} directories left for the next run (--limit) - run again to continue with them", remaining)));
+    }
+
+    let _ = tx.send(ZenEvent::RunSummary(report.summary_lines(args.group_by)));
+    let _ = tx.send(ZenEvent::RunTotals(report.totals()));
+    let _ = tx.send(ZenEvent::NextSteps(crate::next_steps::next_steps(&args, &targets)));
+
     // completion
     let _ = tx.send(ZenEvent::LogMessage("digital purification complete - mind at peace".to_string()));
     tokio::time::sleep(Duration::from_millis(50)).await;
     let _ = tx.send(ZenEvent::OperationComplete);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::Progress;
+    use clap::Parser;
+
+    fn database_error(path: &str) -> crate::utils::CleanerError {
+        crate::utils::CleanerError::Database { operation: "cleaning state.vscdb".to_string(), path: path.to_string(), source: "locked".to_string() }
+    }
+
+    #[test]
+    fn a_module_error_is_counted_once() {
+        let mut garden = ZenGarden::new(&CliArgs::parse_from(["cleaner"]));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let mut problems = crate::utils::ErrorCollector::new();
+        problems.add_error(database_error("/a"));
+        problems.add_error(database_error("/b"));
+        problems.add_warning("left one alone".to_string());
+        send_problems(&tx, problems);
+        drop(tx);
+        while let Ok(event) = rx.try_recv() {
+            garden.handle_event(event);
+        }
+
+        assert_eq!(garden.totals.errors, 2);
+        assert_eq!(garden.totals.warnings, 1);
+        assert_eq!(garden.error_collector.errors.len(), 2);
+        assert_eq!(garden.events.iter().filter(|line| line.starts_with("turbulence detected")).count(), 2);
+        assert_eq!(garden.state, ZenState::Error);
+    }
+
+    #[test]
+    fn errors_reported_through_progress_are_counted_once() {
+        let mut garden = ZenGarden::new(&CliArgs::parse_from(["cleaner"]));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        // what clean_vscode_databases does with a channel as its progress
+        let mut summary = crate::utils::ErrorCollector::new();
+        summary.add_error(database_error("/a"));
+        tx.error(database_error("/a"));
+        tx.error_summary(summary);
+        drop(tx);
+        while let Ok(event) = rx.try_recv() {
+            garden.handle_event(event);
+        }
+
+        assert_eq!(garden.totals.errors, 1);
+    }
+
+    #[test]
+    fn a_card_picked_twice_runs_once() {
+        let picked = vec![CardType::Augment, CardType::Cursor, CardType::Augment, CardType::VSCodium, CardType::Cursor];
+
+        assert_eq!(unique_cards(picked), vec![CardType::Augment, CardType::Cursor, CardType::VSCodium]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn an_augment_run_cleans_a_fixture_tree() {
+        let base = std::env::temp_dir().join(format!("cleaner-zen-garden-augment-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let global = base.join("Code").join("User").join("globalStorage");
+        std::fs::create_dir_all(&global).unwrap();
+        std::fs::write(global.join("storage.json"), "{\"telemetry.machineId\": \"old\"}").unwrap();
+        let conn = rusqlite::Connection::open(global.join("state.vscdb")).unwrap();
+        conn.execute("CREATE TABLE ItemTable (key TEXT UNIQUE ON CONFLICT REPLACE, value BLOB);", []).unwrap();
+        conn.execute("INSERT INTO ItemTable VALUES ('Augment.vscode-augment', 'session'), ('workbench.colorTheme', 'dark');", []).unwrap();
+        drop(conn);
+
+        let mut args = CliArgs::parse_from(["cleaner", "--no-terminate", "--no-backup"]);
+        args.discovery_roots = Some(crate::filesystem::DiscoveryRoots { base_dirs: vec![base.clone()], ..Default::default() });
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        zen_operations_with_cards(tx, args, vec![CardType::Augment]).await;
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        assert!(matches!(events.first(), Some(ZenEvent::StartScanning)));
+        assert!(matches!(events.last(), Some(ZenEvent::OperationComplete)));
+        let total = events.iter().find_map(|event| match event { ZenEvent::SetTotalOperations(total) => Some(*total), _ => None });
+        let advanced: usize = events.iter().map(|event| match event { ZenEvent::OperationsAdvanced(steps) => *steps, _ => 0 }).sum();
+        assert_eq!(total, Some(2));
+        assert_eq!(advanced, 2);
+        assert!(events.iter().any(|event| matches!(event, ZenEvent::StorageUpdated(_))));
+        assert!(events.iter().any(|event| matches!(event, ZenEvent::DatabaseCleaned(_))));
+        assert!(events.iter().any(|event| matches!(event, ZenEvent::LogMessage(line) if line.starts_with("compacted 'state.vscdb' from"))));
+        assert!(!events.iter().any(|event| matches!(event, ZenEvent::Error(_) | ZenEvent::DetailedError(_))));
+
+        let storage = std::fs::read_to_string(global.join("storage.json")).unwrap();
+        assert!(storage.contains("telemetry.machineId") && !storage.contains("\"old\""));
+        let conn = rusqlite::Connection::open(global.join("state.vscdb")).unwrap();
+        let keys: Vec<String> = conn.prepare("SELECT key FROM ItemTable;").unwrap()
+            .query_map([], |row| row.get(0)).unwrap().map(|key| key.unwrap()).collect();
+        assert_eq!(keys, vec!["workbench.colorTheme"]);
+        // --no-backup only skips the storage.json copy, the database is still backed up before its rows go
+        let backups: Vec<String> = std::fs::read_dir(crate::backup::backup_dir_for(&global)).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string()).collect();
+        assert!(backups.iter().any(|name| name.starts_with("state.vscdb")));
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn a_panicking_module_fails_instead_of_aborting() {
+        let args = CliArgs::parse_from(["cleaner"]);
+        let (tx, _rx) = mpsc::unbounded_channel();
+
+        let result: std::result::Result<(), String> = run_module(&args, &tx, |_, _| panic!("the grove caught fire")).await;
+
+        assert_eq!(result, Err("it panicked - the grove caught fire".to_string()));
+    }
+}